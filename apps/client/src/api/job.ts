@@ -4,3 +4,15 @@ import API from '.';
 export function getJobs(): Promise<ApiResult<JobReport[]>> {
 	return API.get('/jobs');
 }
+
+export function cancelJob(id: string): Promise<ApiResult<unknown>> {
+	return API.delete(`/jobs/${id}`);
+}
+
+export function pauseJob(id: string): Promise<ApiResult<unknown>> {
+	return API.post(`/jobs/${id}/pause`);
+}
+
+export function resumeJob(id: string): Promise<ApiResult<unknown>> {
+	return API.post(`/jobs/${id}/resume`);
+}
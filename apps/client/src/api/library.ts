@@ -43,3 +43,15 @@ export function createLibrary(payload: CreateLibraryArgs): Promise<ApiResult<Lib
 export function editLibrary(payload: UpdateLibraryArgs): Promise<ApiResult<Library>> {
 	return API.put(`/libraries/${payload.id}`, payload);
 }
+
+export function regenerateLibraryThumbnails(id: string): Promise<ApiResult<unknown>> {
+	return API.post(`/libraries/${id}/thumbnails`);
+}
+
+export function convertLibraryMedia(id: string): Promise<ApiResult<unknown>> {
+	return API.post(`/libraries/${id}/convert`);
+}
+
+export function verifyLibrary(id: string): Promise<ApiResult<unknown>> {
+	return API.post(`/libraries/${id}/verify`);
+}
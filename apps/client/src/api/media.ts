@@ -1,4 +1,4 @@
-import { ApiResult, Media, PageableApiResult, ReadProgress } from '@stump/core';
+import { ApiResult, Media, MediaPage, PageableApiResult, ReadProgress } from '@stump/core';
 import API, { baseURL } from '.';
 
 type GetMediaById = ApiResult<Media>;
@@ -11,6 +11,10 @@ export function getPaginatedMedia(page: number): Promise<PageableApiResult<Media
 	return API.get(`/media?page=${page}`);
 }
 
+export function getErroredMedia(page?: number): Promise<PageableApiResult<Media[]>> {
+	return API.get(page ? `/media/errored?page=${page}` : '/media/errored?unpaged=true');
+}
+
 export function getMediaById(id: string): Promise<GetMediaById> {
 	return API.get(`/media/${id}`);
 }
@@ -19,10 +23,42 @@ export function getMediaThumbnail(id: string): string {
 	return `${baseURL}/media/${id}/thumbnail`;
 }
 
+export function regenerateMediaThumbnail(id: string): Promise<ApiResult<unknown>> {
+	return API.post(`/media/${id}/thumbnail`);
+}
+
+export function uploadMediaCover(id: string, cover: File): Promise<ApiResult<Media>> {
+	return API.put(`/media/${id}/cover`, cover, { headers: { 'Content-Type': cover.type } });
+}
+
+export function setMediaCoverPage(id: string, page: number): Promise<ApiResult<Media>> {
+	return API.put(`/media/${id}/cover/${page}`);
+}
+
+export function removeMediaCover(id: string): Promise<ApiResult<Media>> {
+	return API.delete(`/media/${id}/cover`);
+}
+
+export function convertMedia(id: string): Promise<ApiResult<unknown>> {
+	return API.post(`/media/${id}/convert`);
+}
+
+export function verifyMedia(id: string): Promise<GetMediaById> {
+	return API.post(`/media/${id}/verify`);
+}
+
+export function retryMedia(id: string): Promise<GetMediaById> {
+	return API.post(`/media/${id}/retry`);
+}
+
 export function getMediaPage(id: string, page: number): string {
 	return `${baseURL}/media/${id}/page/${page}`;
 }
 
+export function getMediaPages(id: string): Promise<ApiResult<MediaPage[]>> {
+	return API.get(`/media/${id}/pages`);
+}
+
 export function updateMediaProgress(id: string, page: number): Promise<ReadProgress> {
 	return API.put(`/media/${id}/progress/${page}`);
 }
@@ -16,3 +16,27 @@ export function getNextInSeries(id: string): Promise<ApiResult<Media | undefined
 export function getSeriesThumbnail(id: string): string {
 	return `${baseURL}/series/${id}/thumbnail`;
 }
+
+export function regenerateSeriesThumbnails(id: string): Promise<ApiResult<unknown>> {
+	return API.post(`/series/${id}/thumbnails`);
+}
+
+export function uploadSeriesCover(id: string, cover: File): Promise<ApiResult<Series>> {
+	return API.put(`/series/${id}/cover`, cover, { headers: { 'Content-Type': cover.type } });
+}
+
+export function removeSeriesCover(id: string): Promise<ApiResult<Series>> {
+	return API.delete(`/series/${id}/cover`);
+}
+
+export function convertSeriesMedia(id: string): Promise<ApiResult<unknown>> {
+	return API.post(`/series/${id}/convert`);
+}
+
+export function verifySeriesMedia(id: string): Promise<ApiResult<unknown>> {
+	return API.post(`/series/${id}/verify`);
+}
+
+export function scanSeries(id: string): Promise<ApiResult<unknown>> {
+	return API.post(`/series/${id}/scan`);
+}
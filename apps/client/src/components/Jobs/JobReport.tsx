@@ -1,9 +1,15 @@
 import { useMemo } from 'react';
+import toast from 'react-hot-toast';
 import shallow from 'zustand/shallow';
+import { useMutation } from '@tanstack/react-query';
+import { cancelJob, pauseJob, resumeJob } from '~api/job';
+import client from '~api/client';
 import { useStore } from '~stores/mainStore';
 
 import {
 	Box,
+	Button,
+	ButtonGroup,
 	Heading,
 	HStack,
 	Progress,
@@ -35,13 +41,44 @@ function JobReportComponent(jobReport: JobReport) {
 	);
 }
 
+function JobControls({ job }: { job: JobReport }) {
+	const { mutateAsync: cancel } = useMutation(['cancelJob'], { mutationFn: cancelJob });
+	const { mutateAsync: pause } = useMutation(['pauseJob'], { mutationFn: pauseJob });
+	const { mutateAsync: resume } = useMutation(['resumeJob'], { mutationFn: resumeJob });
+
+	function handleControl(control: (id: string) => Promise<unknown>) {
+		if (!job.id) {
+			return;
+		}
+
+		control(job.id)
+			.then(() => client.invalidateQueries(['getJobReports']))
+			.catch(() => toast.error('Failed to update the job'));
+	}
+
+	return (
+		<ButtonGroup size="xs" variant="ghost">
+			{job.status === 'PAUSED' && <Button onClick={() => handleControl(resume)}>Resume</Button>}
+			{job.status === 'RUNNING' && <Button onClick={() => handleControl(pause)}>Pause</Button>}
+			<Button colorScheme="red" onClick={() => handleControl(cancel)}>
+				Cancel
+			</Button>
+		</ButtonGroup>
+	);
+}
+
 export function RunningJobs({ jobs }: { jobs: JobReport[] }) {
 	const zustandJobs = useStore((state) => state.jobs, shallow);
 
 	const runningJobs = useMemo(() => {
 		return jobs
-			.filter((job) => job.status === 'RUNNING' && job.id && zustandJobs[job.id])
-			.map((job) => ({ ...job, ...zustandJobs[job.id!] }));
+			.filter(
+				(job) =>
+					(job.status === 'RUNNING' || job.status === 'PAUSED') &&
+					job.id &&
+					zustandJobs[job.id],
+			)
+			.map((job) => ({ ...job, ...zustandJobs[job.id!], status: job.status }));
 	}, [zustandJobs, jobs]);
 
 	// TODO: generalize this since I use it in other places
@@ -66,7 +103,12 @@ export function RunningJobs({ jobs }: { jobs: JobReport[] }) {
 
 			{runningJobs.map((job) => (
 				<div className="flex flex-col space-y-2 p-2 w-full text-xs">
-					<Text fontWeight="medium">{trim(job.message) ?? 'Job in Progress'}</Text>
+					<HStack justify="space-between">
+						<Text fontWeight="medium">
+							{job.status === 'PAUSED' ? 'Paused' : trim(job.message) ?? 'Job in Progress'}
+						</Text>
+						<JobControls job={job} />
+					</HStack>
 					<Progress
 						value={Number(job.currentTask)}
 						max={Number(job.taskCount)}
@@ -103,7 +145,10 @@ export function QueuedJobs({ jobs }: { jobs: JobReport[] }) {
 
 			<VStack spacing={4} align="start">
 				{queuedJobs.map((job, i) => (
-					<JobReportComponent key={job.id ?? i} {...job} />
+					<HStack key={job.id ?? i} align="start" justify="space-between" w="full">
+						<JobReportComponent {...job} />
+						<JobControls job={job} />
+					</HStack>
 				))}
 			</VStack>
 		</Stack>
@@ -114,7 +159,7 @@ export function JobHistory({ jobs }: { jobs: JobReport[] }) {
 	const zustandJobs = useStore((state) => state.jobs, shallow);
 
 	const pastJobs = useMemo(() => {
-		return jobs.filter((job) => job.status === 'COMPLETED');
+		return jobs.filter((job) => job.status === 'COMPLETED' || job.status === 'CANCELLED');
 	}, [zustandJobs, jobs]);
 
 	// TODO: truncate, allow for 'View More' button or something
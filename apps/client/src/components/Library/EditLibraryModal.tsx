@@ -75,6 +75,7 @@ export default function EditLibraryModal({ disabled, library }: Props) {
 		const libraryOptions = {
 			...rest,
 			id: library.libraryOptions.id,
+			thumbnailOptions: library.libraryOptions.thumbnailOptions,
 		} as LibraryOptions;
 
 		let existingTags = tags.filter((tag) => formTags.some((t: TagOption) => t.value === tag.name));
@@ -11,6 +11,7 @@ import {
 	Tabs,
 } from '@chakra-ui/react';
 import { zodResolver } from '@hookform/resolvers/zod';
+import { Select } from 'chakra-react-select';
 import { FieldValues, useForm } from 'react-hook-form';
 import { z } from 'zod';
 import DirectoryPickerModal from '~components/DirectoryPickerModal';
@@ -20,10 +21,17 @@ import Input from '~ui/Input';
 import { Tab } from '~ui/Tabs';
 import TextArea from '~ui/TextArea';
 import { TagOption } from '~hooks/useTags';
-import { Library, LibraryScanMode } from '@stump/core';
+import { Library, LibraryScanMode, SeriesStrategy } from '@stump/core';
 import { useLibraries } from '~hooks/useLibraries';
 import Checkbox from '~ui/Checkbox';
 
+const seriesStrategies: { label: string; value: SeriesStrategy }[] = [
+	{ label: 'Every folder with books', value: 'DIRECTORY' },
+	{ label: 'Top-level folders only', value: 'TOP_LEVEL' },
+	{ label: 'Deepest folders only', value: 'LEAF' },
+	{ label: 'ComicInfo series name', value: 'COMIC_INFO' },
+];
+
 interface Props {
 	tags: TagOption[];
 	onSubmit(values: FieldValues): void;
@@ -89,6 +97,19 @@ export default function LibraryModalForm({ tags, onSubmit, fetchingTags, reset,
 		convertRarToZip: z.boolean().default(false),
 		hardDeleteConversions: z.boolean().default(false),
 		createWebpThumbnails: z.boolean().default(false),
+		treatImageFoldersAsMedia: z.boolean().default(false),
+		watchForChanges: z.boolean().default(false),
+		seriesStrategy: z.string().default('DIRECTORY'),
+		// Note: the globs are edited one per line, like the lines of a .stumpignore file.
+		ignoreGlobs: z
+			.string()
+			.default('')
+			.transform((val) =>
+				val
+					.split('\n')
+					.map((glob) => glob.trim())
+					.filter(Boolean),
+			),
 	});
 
 	const form = useForm({
@@ -102,6 +123,10 @@ export default function LibraryModalForm({ tags, onSubmit, fetchingTags, reset,
 					convertRarToZip: library.libraryOptions.convertRarToZip,
 					hardDeleteConversions: library.libraryOptions.hardDeleteConversions,
 					createWebpThumbnails: library.libraryOptions.createWebpThumbnails,
+					treatImageFoldersAsMedia: library.libraryOptions.treatImageFoldersAsMedia,
+					watchForChanges: library.libraryOptions.watchForChanges,
+					seriesStrategy: library.libraryOptions.seriesStrategy,
+					ignoreGlobs: library.libraryOptions.ignoreGlobs.join('\n'),
 					scanMode: 'BATCHED',
 			  }
 			: {},
@@ -114,7 +139,11 @@ export default function LibraryModalForm({ tags, onSubmit, fetchingTags, reset,
 	}, [form.formState.errors]);
 
 	// const convertRarToZip = form.watch('convertRarToZip');
-	const [scanMode, convertRarToZip] = form.watch(['scanMode', 'convertRarToZip']);
+	const [scanMode, convertRarToZip, seriesStrategy] = form.watch([
+		'scanMode',
+		'convertRarToZip',
+		'seriesStrategy',
+	]);
 
 	useEffect(() => {
 		if (reset) {
@@ -235,6 +264,43 @@ export default function LibraryModalForm({ tags, onSubmit, fetchingTags, reset,
 								Permanently delete rar files after conversion
 							</Checkbox>
 						</FormControl>
+
+						<FormControl>
+							<Checkbox
+								title="When a folder contains only images, treat the entire folder as a single book rather than scanning each image individually"
+								colorScheme="brand"
+								{...form.register('treatImageFoldersAsMedia')}
+							>
+								Treat folders of images as books
+							</Checkbox>
+						</FormControl>
+
+						<FormControl>
+							<Checkbox
+								title="Watch the library folder for changes, and scan the affected series as soon as files are added, changed or removed"
+								colorScheme="brand"
+								{...form.register('watchForChanges')}
+							>
+								Watch for changes
+							</Checkbox>
+						</FormControl>
+
+						<FormControl>
+							<FormLabel htmlFor="seriesStrategy">Series detection</FormLabel>
+							<Select
+								value={seriesStrategies.find((s) => s.value === (seriesStrategy || 'DIRECTORY'))}
+								options={seriesStrategies}
+								onChange={(option) => option && form.setValue('seriesStrategy', option.value)}
+							/>
+						</FormControl>
+
+						<FormControl>
+							<FormLabel htmlFor="ignoreGlobs">Ignored paths</FormLabel>
+							<TextArea
+								placeholder="One pattern per line, e.g. @eaDir/ or *_extras"
+								{...form.register('ignoreGlobs')}
+							/>
+						</FormControl>
 					</TabPanel>
 				</TabPanels>
 			</Tabs>
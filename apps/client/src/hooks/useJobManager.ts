@@ -14,7 +14,7 @@ export function useJobManager() {
 	function handleJobEvent(event: ClientEvent) {
 		const { key, data } = event;
 
-		if (['JobComplete', 'JobFailed'].includes(key)) {
+		if (['JobComplete', 'JobFailed', 'JobCancelled', 'JobPaused', 'JobResumed'].includes(key)) {
 			client.invalidateQueries(['getJobReports']);
 		}
 
@@ -38,8 +38,19 @@ export function useJobManager() {
 			case 'JobFailed':
 				toast.error(`Job ${data.runner_id} failed.`);
 				break;
+			case 'JobCancelled':
+				client.invalidateQueries(['getLibrary']);
+				client.invalidateQueries(['getLibrariesStats']);
+				client.invalidateQueries(['getSeries']);
+				toast(`Job ${data} cancelled.`);
+				break;
+			case 'JobPaused':
+			case 'JobResumed':
+				break;
 			case 'CreatedMedia':
 			case 'CreatedMediaBatch':
+			case 'UpdatedMedia':
+			case 'MovedMedia':
 			case 'CreatedSeries':
 				// I set a timeout here to give the backend a little time to analyze at least
 				// one of the books in a new series before triggering a refetch. This is to
@@ -39,9 +39,28 @@ export interface LibraryOptions {
 	convertRarToZip: boolean;
 	hardDeleteConversions: boolean;
 	createWebpThumbnails: boolean;
+	treatImageFoldersAsMedia: boolean;
+	watchForChanges: boolean;
+	ignoreGlobs: Array<string>;
+	seriesStrategy: SeriesStrategy;
+	thumbnailOptions: ThumbnailOptions | null;
 	libraryId: string | null;
 }
 
+export type SeriesStrategy = 'DIRECTORY' | 'TOP_LEVEL' | 'LEAF' | 'COMIC_INFO';
+
+export interface ThumbnailOptions {
+	maxWidth: number;
+	maxHeight: number;
+	format: ImageFormat;
+	quality: number;
+	filter: ImageResizeFilter;
+}
+
+export type ImageFormat = 'webp' | 'jpeg' | 'png';
+
+export type ImageResizeFilter = 'NEAREST' | 'TRIANGLE' | 'CATMULL_ROM' | 'GAUSSIAN' | 'LANCZOS3';
+
 export interface CreateLibraryArgs {
 	name: string;
 	path: string;
@@ -74,12 +93,14 @@ export interface Series {
 	path: string;
 	description: string | null;
 	status: FileStatus;
+	statusReason: string | null;
 	updatedAt: string;
 	libraryId: string;
 	library: Library | null;
 	media: Array<Media> | null;
 	mediaCount: number | null;
 	tags: Array<Tag> | null;
+	coverPath: string | null;
 }
 
 export interface Media {
@@ -92,22 +113,59 @@ export interface Media {
 	updatedAt: string;
 	checksum: string | null;
 	path: string;
+	modifiedAt: string | null;
 	status: FileStatus;
+	statusReason: string | null;
 	seriesId: string;
 	series: Series | null;
 	readProgresses: Array<ReadProgress> | null;
 	currentPage: number | null;
 	tags: Array<Tag> | null;
+	metadata: MediaMetadata | null;
+	coverPath: string | null;
+	coverPage: number | null;
+	sortKey: number | null;
 }
 
 export interface MediaMetadata {
-	Series: string | null;
-	Number: number | null;
-	Web: string | null;
-	Summary: string | null;
-	Publisher: string | null;
-	Genre: string | null;
-	PageCount: number | null;
+	title: string | null;
+	series: string | null;
+	number: string | null;
+	volume: number | null;
+	count: number | null;
+	summary: string | null;
+	year: number | null;
+	month: number | null;
+	day: number | null;
+	writer: string | null;
+	penciller: string | null;
+	inker: string | null;
+	colorist: string | null;
+	letterer: string | null;
+	coverArtist: string | null;
+	editor: string | null;
+	publisher: string | null;
+	genre: string | null;
+	web: string | null;
+	pageCount: number | null;
+	languageIso: string | null;
+	characters: string | null;
+	teams: string | null;
+	locations: string | null;
+	storyArc: string | null;
+	ageRating: string | null;
+	manga: string | null;
+}
+
+export interface MediaPage {
+	page: number;
+	name: string;
+	offset: number | null;
+	size: number;
+	mime: string;
+	width: number | null;
+	height: number | null;
+	isSpread: boolean;
 }
 
 export interface ReadProgress {
@@ -143,7 +201,7 @@ export interface EpubContent {
 	playOrder: number;
 }
 
-export type JobStatus = 'RUNNING' | 'QUEUED' | 'COMPLETED' | 'CANCELLED' | 'FAILED';
+export type JobStatus = 'RUNNING' | 'QUEUED' | 'PAUSED' | 'COMPLETED' | 'CANCELLED' | 'FAILED';
 
 export interface JobUpdate {
 	runnerId: string;
@@ -160,6 +218,7 @@ export interface JobReport {
 	status: JobStatus;
 	taskCount: number | null;
 	completedTaskCount: number | null;
+	movedMediaCount: number | null;
 	secondsElapsed: bigint | null;
 	completedAt: string | null;
 }
@@ -169,9 +228,15 @@ export type ClientEvent =
 	| { key: 'JobProgress'; data: JobUpdate }
 	| { key: 'JobComplete'; data: string }
 	| { key: 'JobFailed'; data: { runner_id: string; message: string } }
+	| { key: 'JobCancelled'; data: string }
+	| { key: 'JobPaused'; data: string }
+	| { key: 'JobResumed'; data: string }
 	| { key: 'CreateEntityFailed'; data: { runner_id: string | null; path: string; message: string } }
+	| { key: 'ConvertMediaFailed'; data: { runner_id: string | null; path: string; message: string } }
+	| { key: 'MovedMedia'; data: { runner_id: string | null; from: string; to: string } }
 	| { key: 'CreatedMedia'; data: Media }
 	| { key: 'CreatedMediaBatch'; data: bigint }
+	| { key: 'UpdatedMedia'; data: Media }
 	| { key: 'CreatedSeries'; data: Series }
 	| { key: 'CreatedSeriesBatch'; data: bigint };
 
@@ -0,0 +1,2 @@
+-- AlterTable
+ALTER TABLE "library_options" ADD COLUMN "treatImageFoldersAsMedia" BOOLEAN NOT NULL DEFAULT false;
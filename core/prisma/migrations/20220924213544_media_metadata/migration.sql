@@ -0,0 +1,36 @@
+-- CreateTable
+CREATE TABLE "media_metadata" (
+    "id" TEXT NOT NULL PRIMARY KEY,
+    "title" TEXT,
+    "series" TEXT,
+    "number" TEXT,
+    "volume" INTEGER,
+    "count" INTEGER,
+    "summary" TEXT,
+    "year" INTEGER,
+    "month" INTEGER,
+    "day" INTEGER,
+    "writer" TEXT,
+    "penciller" TEXT,
+    "inker" TEXT,
+    "colorist" TEXT,
+    "letterer" TEXT,
+    "coverArtist" TEXT,
+    "editor" TEXT,
+    "publisher" TEXT,
+    "genre" TEXT,
+    "web" TEXT,
+    "pageCount" INTEGER,
+    "languageIso" TEXT,
+    "characters" TEXT,
+    "teams" TEXT,
+    "locations" TEXT,
+    "storyArc" TEXT,
+    "ageRating" TEXT,
+    "manga" TEXT,
+    "mediaId" TEXT NOT NULL,
+    CONSTRAINT "media_metadata_mediaId_fkey" FOREIGN KEY ("mediaId") REFERENCES "media" ("id") ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+-- CreateIndex
+CREATE UNIQUE INDEX "media_metadata_mediaId_key" ON "media_metadata"("mediaId");
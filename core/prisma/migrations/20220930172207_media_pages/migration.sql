@@ -0,0 +1,16 @@
+-- CreateTable
+CREATE TABLE "media_pages" (
+    "id" TEXT NOT NULL PRIMARY KEY,
+    "page" INTEGER NOT NULL,
+    "name" TEXT NOT NULL,
+    "offset" BIGINT,
+    "size" BIGINT NOT NULL,
+    "mime" TEXT NOT NULL,
+    "width" INTEGER,
+    "height" INTEGER,
+    "mediaId" TEXT NOT NULL,
+    CONSTRAINT "media_pages_mediaId_fkey" FOREIGN KEY ("mediaId") REFERENCES "media" ("id") ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+-- CreateIndex
+CREATE UNIQUE INDEX "media_pages_mediaId_page_key" ON "media_pages"("mediaId", "page");
@@ -0,0 +1,2 @@
+-- AlterTable
+ALTER TABLE "media_pages" ADD COLUMN "isSpread" BOOLEAN NOT NULL DEFAULT false;
@@ -0,0 +1,6 @@
+-- AlterTable
+ALTER TABLE "library_options" ADD COLUMN "thumbnailMaxWidth" INTEGER;
+ALTER TABLE "library_options" ADD COLUMN "thumbnailMaxHeight" INTEGER;
+ALTER TABLE "library_options" ADD COLUMN "thumbnailFormat" TEXT;
+ALTER TABLE "library_options" ADD COLUMN "thumbnailQuality" INTEGER;
+ALTER TABLE "library_options" ADD COLUMN "thumbnailFilter" TEXT;
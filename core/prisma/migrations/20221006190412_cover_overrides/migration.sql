@@ -0,0 +1,6 @@
+-- AlterTable
+ALTER TABLE "series" ADD COLUMN "coverPath" TEXT;
+
+-- AlterTable
+ALTER TABLE "media" ADD COLUMN "coverPath" TEXT;
+ALTER TABLE "media" ADD COLUMN "coverPage" INTEGER;
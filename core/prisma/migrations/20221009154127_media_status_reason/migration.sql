@@ -0,0 +1,2 @@
+-- AlterTable
+ALTER TABLE "media" ADD COLUMN "statusReason" TEXT;
@@ -0,0 +1,2 @@
+-- AlterTable
+ALTER TABLE "series" ADD COLUMN "statusReason" TEXT;
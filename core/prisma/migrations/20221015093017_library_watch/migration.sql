@@ -0,0 +1,2 @@
+-- AlterTable
+ALTER TABLE "library_options" ADD COLUMN "watchForChanges" BOOLEAN NOT NULL DEFAULT false;
@@ -0,0 +1,2 @@
+-- AlterTable
+ALTER TABLE "library_options" ADD COLUMN "seriesStrategy" TEXT NOT NULL DEFAULT 'DIRECTORY';
@@ -0,0 +1,20 @@
+-- RedefineTables
+PRAGMA foreign_keys=OFF;
+CREATE TABLE "new_jobs" (
+    "id" TEXT NOT NULL PRIMARY KEY,
+    "kind" TEXT NOT NULL,
+    "details" TEXT,
+    "params" TEXT,
+    "status" TEXT NOT NULL DEFAULT 'RUNNING',
+    "taskCount" INTEGER NOT NULL DEFAULT 1,
+    "completedTaskCount" INTEGER NOT NULL DEFAULT 0,
+    "movedMediaCount" INTEGER,
+    "secondsElapsed" INTEGER NOT NULL DEFAULT 0,
+    "createdAt" DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "completedAt" DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+INSERT INTO "new_jobs" ("completedAt", "completedTaskCount", "createdAt", "details", "id", "kind", "movedMediaCount", "params", "secondsElapsed", "status", "taskCount") SELECT "completedAt", "completedTaskCount", "completedAt", "details", "id", "kind", "movedMediaCount", "params", "secondsElapsed", "status", "taskCount" FROM "jobs";
+DROP TABLE "jobs";
+ALTER TABLE "new_jobs" RENAME TO "jobs";
+PRAGMA foreign_key_check;
+PRAGMA foreign_keys=ON;
@@ -67,10 +67,16 @@ impl Ctx {
 
 	/// Emits a client event and persists a log based on the failure.
 	pub async fn handle_failure_event(&self, event: ClientEvent) {
-		use prisma::log;
-
 		// TODO: maybe log::error! here?
 
+		self.handle_log_event(event).await;
+	}
+
+	/// Emits a client event and persists a log based on it, for events which should be
+	/// kept after the client has received them (e.g. failures, or moved media).
+	pub async fn handle_log_event(&self, event: ClientEvent) {
+		use prisma::log;
+
 		self.emit_client_event(event.clone());
 
 		let tentative_log = TentativeLog::from(event);
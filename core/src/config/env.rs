@@ -15,6 +15,15 @@ pub struct Env {
 	pub stump_client_dir: Option<String>,
 	pub stump_config_dir: Option<String>,
 	pub stump_allowed_origins: Option<Vec<String>>,
+	pub stump_page_cache_size: Option<u64>,
+	pub stump_thumbnail_max_width: Option<u32>,
+	pub stump_thumbnail_max_height: Option<u32>,
+	pub stump_thumbnail_format: Option<String>,
+	pub stump_thumbnail_quality: Option<u8>,
+	pub stump_thumbnail_filter: Option<String>,
+	pub stump_trash_dir: Option<String>,
+	pub stump_max_jobs: Option<usize>,
+	pub stump_job_limits: Option<Vec<String>>,
 }
 
 impl Default for Env {
@@ -27,6 +36,15 @@ impl Default for Env {
 			stump_client_dir: Some(String::from("client")),
 			stump_config_dir: None,
 			stump_allowed_origins: None,
+			stump_page_cache_size: None,
+			stump_thumbnail_max_width: None,
+			stump_thumbnail_max_height: None,
+			stump_thumbnail_format: None,
+			stump_thumbnail_quality: None,
+			stump_thumbnail_filter: None,
+			stump_trash_dir: None,
+			stump_max_jobs: None,
+			stump_job_limits: None,
 		}
 	}
 }
@@ -79,6 +97,61 @@ impl Env {
 			}
 		}
 
+		if let Ok(page_cache_size) = std::env::var("STUMP_PAGE_CACHE_SIZE") {
+			match page_cache_size.parse() {
+				Ok(page_cache_size) => env.stump_page_cache_size = Some(page_cache_size),
+				Err(_) => log::debug!(
+					"Invalid STUMP_PAGE_CACHE_SIZE value: {}",
+					page_cache_size
+				),
+			}
+		}
+
+		if let Ok(max_width) = std::env::var("STUMP_THUMBNAIL_MAX_WIDTH") {
+			env.stump_thumbnail_max_width = max_width.parse().ok();
+		}
+
+		if let Ok(max_height) = std::env::var("STUMP_THUMBNAIL_MAX_HEIGHT") {
+			env.stump_thumbnail_max_height = max_height.parse().ok();
+		}
+
+		if let Ok(format) = std::env::var("STUMP_THUMBNAIL_FORMAT") {
+			env.stump_thumbnail_format = Some(format);
+		}
+
+		if let Ok(quality) = std::env::var("STUMP_THUMBNAIL_QUALITY") {
+			env.stump_thumbnail_quality = quality.parse().ok();
+		}
+
+		if let Ok(filter) = std::env::var("STUMP_THUMBNAIL_FILTER") {
+			env.stump_thumbnail_filter = Some(filter);
+		}
+
+		if let Ok(trash_dir) = std::env::var("STUMP_TRASH_DIR") {
+			if !trash_dir.is_empty() {
+				env.stump_trash_dir = Some(trash_dir);
+			} else {
+				log::debug!("Invalid STUMP_TRASH_DIR value: EMPTY");
+			}
+		}
+
+		if let Ok(max_jobs) = std::env::var("STUMP_MAX_JOBS") {
+			match max_jobs.parse() {
+				Ok(max_jobs) => env.stump_max_jobs = Some(max_jobs),
+				Err(_) => log::debug!("Invalid STUMP_MAX_JOBS value: {}", max_jobs),
+			}
+		}
+
+		if let Ok(job_limits) = std::env::var("STUMP_JOB_LIMITS") {
+			env.stump_job_limits = Some(
+				job_limits
+					.split(',')
+					.map(|limit| limit.trim().to_string())
+					.filter(|limit| !limit.is_empty())
+					.collect(),
+			);
+		}
+
 		env.stump_config_dir = Some(get_config_dir().to_string_lossy().to_string());
 
 		env.write()?;
@@ -139,6 +212,46 @@ impl Env {
 			}
 		}
 
+		if let Some(page_cache_size) = env.stump_page_cache_size {
+			std::env::set_var("STUMP_PAGE_CACHE_SIZE", page_cache_size.to_string());
+		}
+
+		if let Some(max_width) = env.stump_thumbnail_max_width {
+			std::env::set_var("STUMP_THUMBNAIL_MAX_WIDTH", max_width.to_string());
+		}
+
+		if let Some(max_height) = env.stump_thumbnail_max_height {
+			std::env::set_var("STUMP_THUMBNAIL_MAX_HEIGHT", max_height.to_string());
+		}
+
+		if let Some(format) = env.stump_thumbnail_format {
+			std::env::set_var("STUMP_THUMBNAIL_FORMAT", format);
+		}
+
+		if let Some(quality) = env.stump_thumbnail_quality {
+			std::env::set_var("STUMP_THUMBNAIL_QUALITY", quality.to_string());
+		}
+
+		if let Some(filter) = env.stump_thumbnail_filter {
+			std::env::set_var("STUMP_THUMBNAIL_FILTER", filter);
+		}
+
+		if let Some(trash_dir) = env.stump_trash_dir {
+			if !trash_dir.is_empty() {
+				std::env::set_var("STUMP_TRASH_DIR", trash_dir);
+			}
+		}
+
+		if let Some(max_jobs) = env.stump_max_jobs {
+			std::env::set_var("STUMP_MAX_JOBS", max_jobs.to_string());
+		}
+
+		if let Some(job_limits) = env.stump_job_limits {
+			if !job_limits.is_empty() {
+				std::env::set_var("STUMP_JOB_LIMITS", job_limits.join(","));
+			}
+		}
+
 		Ok(())
 	}
 
@@ -1,4 +1,9 @@
-use std::path::{Path, PathBuf};
+use std::{
+	collections::HashMap,
+	path::{Path, PathBuf},
+};
+
+use crate::types::models::library::ThumbnailOptions;
 
 pub mod context;
 pub mod cors;
@@ -61,6 +66,87 @@ pub fn get_cache_dir() -> PathBuf {
 	cache_dir
 }
 
+/// Gets the directory used to cache transformed pages, which lives within the cache
+/// directory.
+pub fn get_page_cache_dir() -> PathBuf {
+	let page_cache_dir = get_cache_dir().join("pages");
+
+	check_configuration_dir(&page_cache_dir);
+
+	page_cache_dir
+}
+
+/// Gets the maximum size, in bytes, of the page cache. This may be overridden using
+/// STUMP_PAGE_CACHE_SIZE, which is in megabytes. Defaults to 512MB.
+pub fn get_page_cache_size() -> u64 {
+	std::env::var("STUMP_PAGE_CACHE_SIZE")
+		.ok()
+		.and_then(|val| val.parse::<u64>().ok())
+		.unwrap_or(512)
+		* 1024 * 1024
+}
+
+/// Gets the server default thumbnail options, used by libraries which don't set their own.
+/// Each option may be overridden using STUMP_THUMBNAIL_MAX_WIDTH, STUMP_THUMBNAIL_MAX_HEIGHT,
+/// STUMP_THUMBNAIL_FORMAT, STUMP_THUMBNAIL_QUALITY and STUMP_THUMBNAIL_FILTER.
+pub fn get_thumbnail_options() -> ThumbnailOptions {
+	fn var<T: std::str::FromStr>(key: &str) -> Option<T> {
+		std::env::var(key)
+			.ok()
+			.and_then(|val| val.parse::<T>().ok())
+	}
+
+	let defaults = ThumbnailOptions::default();
+
+	ThumbnailOptions {
+		max_width: var("STUMP_THUMBNAIL_MAX_WIDTH").unwrap_or(defaults.max_width),
+		max_height: var("STUMP_THUMBNAIL_MAX_HEIGHT").unwrap_or(defaults.max_height),
+		format: var("STUMP_THUMBNAIL_FORMAT").unwrap_or(defaults.format),
+		quality: var("STUMP_THUMBNAIL_QUALITY").unwrap_or(defaults.quality),
+		filter: var("STUMP_THUMBNAIL_FILTER").unwrap_or(defaults.filter),
+	}
+}
+
+/// Gets the maximum number of jobs which may run at the same time. This may be overridden
+/// using STUMP_MAX_JOBS, and defaults to 2.
+pub fn get_max_jobs() -> usize {
+	std::env::var("STUMP_MAX_JOBS")
+		.ok()
+		.and_then(|val| val.parse::<usize>().ok())
+		.unwrap_or(2)
+		.max(1)
+}
+
+/// Gets the maximum number of jobs of a kind which may run at the same time, keyed by the
+/// kind of job. These are set using STUMP_JOB_LIMITS, e.g. "LibraryScanJob=1,
+/// ThumbnailGenerationJob=2". Kinds without a limit are only bound by `get_max_jobs`.
+pub fn get_job_kind_limits() -> HashMap<String, usize> {
+	std::env::var("STUMP_JOB_LIMITS")
+		.map(|val| parse_job_kind_limits(&val))
+		.unwrap_or_default()
+}
+
+fn parse_job_kind_limits(value: &str) -> HashMap<String, usize> {
+	value
+		.split(',')
+		.filter(|limit| !limit.trim().is_empty())
+		.filter_map(|limit| {
+			let parsed = limit.split_once('=').and_then(|(kind, max)| {
+				Some((
+					kind.trim().to_string(),
+					max.trim().parse::<usize>().ok()?.max(1),
+				))
+			});
+
+			if parsed.is_none() {
+				log::warn!("Invalid job limit in STUMP_JOB_LIMITS: {}", limit);
+			}
+
+			parsed
+		})
+		.collect()
+}
+
 pub fn get_thumbnails_dir() -> PathBuf {
 	let thumbnails_dir = get_config_dir().join("thumbnails");
 
@@ -69,6 +155,46 @@ pub fn get_thumbnails_dir() -> PathBuf {
 	thumbnails_dir
 }
 
+/// Gets the directory converted files are moved to, unless the library is configured to
+/// hard delete them. This may be overridden using STUMP_TRASH_DIR, and defaults to a
+/// trash directory within the config directory.
+pub fn get_trash_dir() -> PathBuf {
+	let trash_dir = std::env::var("STUMP_TRASH_DIR")
+		.ok()
+		.filter(|val| !val.is_empty())
+		.map(PathBuf::from)
+		.unwrap_or_else(|| get_config_dir().join("trash"));
+
+	check_configuration_dir(&trash_dir);
+
+	trash_dir
+}
+
+/// Gets the directory used to store cover images uploaded for series and media.
+pub fn get_covers_dir() -> PathBuf {
+	let covers_dir = get_config_dir().join("covers");
+
+	check_configuration_dir(&covers_dir);
+
+	covers_dir
+}
+
 pub fn stump_in_docker() -> bool {
 	std::env::var("STUMP_IN_DOCKER").is_ok()
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parses_job_kind_limits() {
+		let limits =
+			parse_job_kind_limits("LibraryScanJob=1, ThumbnailGenerationJob = 3,Bad,X=y");
+
+		assert_eq!(limits.len(), 2);
+		assert_eq!(limits.get("LibraryScanJob"), Some(&1));
+		assert_eq!(limits.get("ThumbnailGenerationJob"), Some(&3));
+		assert!(parse_job_kind_limits("").is_empty());
+	}
+}
@@ -7,8 +7,12 @@ use rocket::serde::DeserializeOwned;
 use serde::Deserialize;
 
 use crate::{
-	prisma::PrismaClient,
-	types::{alias::ApiResult, pageable::PageParams},
+	prisma::{library, PrismaClient},
+	types::{
+		alias::ApiResult,
+		models::library::{LibraryOptions, ThumbnailOptions},
+		pageable::PageParams,
+	},
 };
 
 use super::migration::CountQueryReturn;
@@ -30,6 +34,14 @@ pub trait PrismaClientTrait {
 		&self,
 		series_ids: Vec<String>,
 	) -> ApiResult<HashMap<String, u32>>;
+	async fn library_options(
+		&self,
+		library_id: Option<String>,
+	) -> ApiResult<LibraryOptions>;
+	async fn thumbnail_options(
+		&self,
+		library_id: Option<String>,
+	) -> ApiResult<ThumbnailOptions>;
 }
 
 #[async_trait::async_trait]
@@ -93,6 +105,41 @@ impl PrismaClientTrait for PrismaClient {
 			.map(|data| (data.series_id.to_owned(), data.count))
 			.collect())
 	}
+
+	/// Returns the options of the library, or the default options when the library
+	/// doesn't exist.
+	async fn library_options(
+		&self,
+		library_id: Option<String>,
+	) -> ApiResult<LibraryOptions> {
+		let library = match library_id {
+			Some(id) => {
+				self.library()
+					.find_unique(library::id::equals(id))
+					.with(library::library_options::fetch())
+					.exec()
+					.await?
+			},
+			None => None,
+		};
+
+		Ok(library
+			.and_then(|library| library.library_options().ok().cloned())
+			.map(|opt| opt.into())
+			.unwrap_or_default())
+	}
+
+	/// Returns the thumbnail options of the library, falling back to the server defaults
+	/// when the library doesn't exist or doesn't set its own.
+	async fn thumbnail_options(
+		&self,
+		library_id: Option<String>,
+	) -> ApiResult<ThumbnailOptions> {
+		Ok(self
+			.library_options(library_id)
+			.await?
+			.get_thumbnail_options())
+	}
 }
 
 pub trait FindManyTrait {
@@ -2,12 +2,13 @@ use std::sync::Arc;
 
 use rocket::tokio::{self, sync::mpsc};
 
-use crate::{config::context::Ctx, job::pool::JobPool};
+use crate::{config::context::Ctx, fs::watcher::LibraryWatcher, job::pool::JobPool};
 
 use super::ClientRequest;
 
 pub struct EventManager {
 	job_pool: Arc<JobPool>,
+	library_watcher: Arc<LibraryWatcher>,
 }
 
 impl EventManager {
@@ -17,6 +18,17 @@ impl EventManager {
 	) -> Arc<Self> {
 		let this = Arc::new(Self {
 			job_pool: JobPool::new(),
+			library_watcher: LibraryWatcher::new(ctx.get_ctx()),
+		});
+
+		this.job_pool.init(&ctx);
+
+		let watcher_ctx = ctx.get_ctx();
+		let library_watcher = this.library_watcher.clone();
+		tokio::spawn(async move {
+			if let Err(e) = library_watcher.init(&watcher_ctx).await {
+				log::error!("Failed to watch libraries for changes: {:?}", e);
+			}
 		});
 
 		let this_cpy = this.clone();
@@ -47,6 +59,29 @@ impl EventManager {
 						// FIXME: I know, this will break.
 						let _ = return_sender.send(job_report.unwrap());
 					},
+					ClientRequest::WatchLibrary { id, path } => {
+						this_cpy.library_watcher.watch_library(id, &path);
+					},
+					ClientRequest::UnwatchLibrary(id) => {
+						this_cpy.library_watcher.unwatch_library(&id);
+					},
+					ClientRequest::CancelJob(id, return_sender) => {
+						let cancelled =
+							this_cpy.job_pool.clone().cancel_job(&ctx, &id).await;
+
+						let _ = return_sender.send(cancelled);
+					},
+					ClientRequest::PauseJob(id, return_sender) => {
+						let paused = this_cpy.job_pool.clone().pause_job(&ctx, &id).await;
+
+						let _ = return_sender.send(paused);
+					},
+					ClientRequest::ResumeJob(id, return_sender) => {
+						let resumed =
+							this_cpy.job_pool.clone().resume_job(&ctx, &id).await;
+
+						let _ = return_sender.send(resumed);
+					},
 					// TODO: remove this
 					#[allow(unreachable_patterns)]
 					_ => unimplemented!("I can't do that yet!"),
@@ -12,6 +12,22 @@ use crate::{
 pub enum ClientRequest {
 	QueueJob(Box<dyn Job>),
 	GetJobReports(oneshot::Sender<Vec<JobReport>>),
+	/// Starts watching a library for changes, given its id and path.
+	WatchLibrary {
+		id: String,
+		path: String,
+	},
+	/// Stops watching a library for changes, given its id.
+	UnwatchLibrary(String),
+	/// Cancels a running job, given its runner id. Responds with false if there is no
+	/// such job, or if it was already cancelled.
+	CancelJob(String, oneshot::Sender<bool>),
+	/// Pauses a running job, given its runner id. Responds with false if there is no such
+	/// job, or if it isn't running.
+	PauseJob(String, oneshot::Sender<bool>),
+	/// Resumes a paused job, given its runner id. Responds with false if there is no such
+	/// job, or if it isn't paused.
+	ResumeJob(String, oneshot::Sender<bool>),
 }
 
 pub enum ClientResponse {
@@ -29,15 +45,31 @@ pub enum ClientEvent {
 		runner_id: String,
 		message: String,
 	},
+	/// A job stopped before completing, after being cancelled. Holds the runner id.
+	JobCancelled(String),
+	/// A job was paused, and waits until it is resumed. Holds the runner id.
+	JobPaused(String),
+	JobResumed(String),
 	CreateEntityFailed {
 		runner_id: Option<String>,
 		path: String,
 		message: String,
 	},
+	ConvertMediaFailed {
+		runner_id: Option<String>,
+		path: String,
+		message: String,
+	},
+	MovedMedia {
+		runner_id: Option<String>,
+		from: String,
+		to: String,
+	},
 	CreatedMedia(prisma::media::Data),
 	// TODO: not sure if I should send the number of insertions or the insertions themselves.
 	// cloning the vector is potentially expensive.
 	CreatedMediaBatch(u64),
+	UpdatedMedia(prisma::media::Data),
 	CreatedSeries(prisma::series::Data),
 	// TODO: not sure if I should send the number of insertions or the insertions themselves.
 	// cloning the vector is potentially expensive.
@@ -17,6 +17,26 @@ use std::os::windows::prelude::*;
 pub const DIGEST_SAMPLE_SIZE: u64 = 10000;
 pub const DIGEST_SAMPLE_COUNT: u64 = 4;
 
+/// Calculates the number of bytes to sample when generating a checksum for a file. This is
+/// based on the size of the file itself, rather than the entries it may contain.
+pub fn sample_size(path: &str) -> Result<u64, io::Error> {
+	let file_size = std::fs::metadata(path)?.len();
+	let threshold = DIGEST_SAMPLE_SIZE * DIGEST_SAMPLE_COUNT;
+
+	if file_size < threshold {
+		return Ok(file_size);
+	}
+
+	let division = file_size / threshold;
+
+	// if the file size is 4x the threshold, we'll take up to the threshold.
+	if division > 4 {
+		Ok(threshold)
+	} else {
+		Ok(file_size / 2)
+	}
+}
+
 async fn read_async(
 	file: &mut File,
 	offset: u64,
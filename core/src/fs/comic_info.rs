@@ -0,0 +1,190 @@
+use std::{fmt::Display, str::FromStr};
+
+use serde::{de, Deserialize, Deserializer};
+
+use crate::types::models::media::MediaMetadata;
+
+/// Deserializes the text of an optional ComicInfo.xml element. Empty elements (e.g.
+/// `<Year></Year>`) are treated as missing, rather than failing to parse the whole file.
+fn deserialize_element<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+	D: Deserializer<'de>,
+	T: FromStr,
+	T::Err: Display,
+{
+	let value = Option::<String>::deserialize(deserializer)?;
+
+	match value.as_deref().map(str::trim) {
+		None | Some("") => Ok(None),
+		Some(value) => value.parse::<T>().map(Some).map_err(de::Error::custom),
+	}
+}
+
+/// The elements of a ComicInfo.xml file which are persisted as `MediaMetadata`, see
+/// https://anansi-project.github.io/docs/comicinfo/schemas/v2.0. This is kept separate
+/// from `MediaMetadata`, which is serialized with the camelCase keys of the API.
+#[derive(Debug, Default, Deserialize)]
+struct ComicInfo {
+	#[serde(rename = "Title", default, deserialize_with = "deserialize_element")]
+	title: Option<String>,
+	#[serde(rename = "Series", default, deserialize_with = "deserialize_element")]
+	series: Option<String>,
+	#[serde(rename = "Number", default, deserialize_with = "deserialize_element")]
+	number: Option<String>,
+	#[serde(rename = "Volume", default, deserialize_with = "deserialize_element")]
+	volume: Option<i32>,
+	#[serde(rename = "Count", default, deserialize_with = "deserialize_element")]
+	count: Option<i32>,
+	#[serde(rename = "Summary", default, deserialize_with = "deserialize_element")]
+	summary: Option<String>,
+	#[serde(rename = "Year", default, deserialize_with = "deserialize_element")]
+	year: Option<i32>,
+	#[serde(rename = "Month", default, deserialize_with = "deserialize_element")]
+	month: Option<i32>,
+	#[serde(rename = "Day", default, deserialize_with = "deserialize_element")]
+	day: Option<i32>,
+	#[serde(rename = "Writer", default, deserialize_with = "deserialize_element")]
+	writer: Option<String>,
+	#[serde(
+		rename = "Penciller",
+		default,
+		deserialize_with = "deserialize_element"
+	)]
+	penciller: Option<String>,
+	#[serde(rename = "Inker", default, deserialize_with = "deserialize_element")]
+	inker: Option<String>,
+	#[serde(rename = "Colorist", default, deserialize_with = "deserialize_element")]
+	colorist: Option<String>,
+	#[serde(rename = "Letterer", default, deserialize_with = "deserialize_element")]
+	letterer: Option<String>,
+	#[serde(
+		rename = "CoverArtist",
+		default,
+		deserialize_with = "deserialize_element"
+	)]
+	cover_artist: Option<String>,
+	#[serde(rename = "Editor", default, deserialize_with = "deserialize_element")]
+	editor: Option<String>,
+	#[serde(
+		rename = "Publisher",
+		default,
+		deserialize_with = "deserialize_element"
+	)]
+	publisher: Option<String>,
+	#[serde(rename = "Genre", default, deserialize_with = "deserialize_element")]
+	genre: Option<String>,
+	#[serde(rename = "Web", default, deserialize_with = "deserialize_element")]
+	web: Option<String>,
+	#[serde(
+		rename = "PageCount",
+		default,
+		deserialize_with = "deserialize_element"
+	)]
+	page_count: Option<usize>,
+	#[serde(
+		rename = "LanguageISO",
+		default,
+		deserialize_with = "deserialize_element"
+	)]
+	language_iso: Option<String>,
+	#[serde(
+		rename = "Characters",
+		default,
+		deserialize_with = "deserialize_element"
+	)]
+	characters: Option<String>,
+	#[serde(rename = "Teams", default, deserialize_with = "deserialize_element")]
+	teams: Option<String>,
+	#[serde(
+		rename = "Locations",
+		default,
+		deserialize_with = "deserialize_element"
+	)]
+	locations: Option<String>,
+	#[serde(rename = "StoryArc", default, deserialize_with = "deserialize_element")]
+	story_arc: Option<String>,
+	#[serde(
+		rename = "AgeRating",
+		default,
+		deserialize_with = "deserialize_element"
+	)]
+	age_rating: Option<String>,
+	#[serde(rename = "Manga", default, deserialize_with = "deserialize_element")]
+	manga: Option<String>,
+}
+
+impl From<ComicInfo> for MediaMetadata {
+	fn from(comic_info: ComicInfo) -> Self {
+		MediaMetadata {
+			title: comic_info.title,
+			series: comic_info.series,
+			number: comic_info.number,
+			volume: comic_info.volume,
+			count: comic_info.count,
+			summary: comic_info.summary,
+			year: comic_info.year,
+			month: comic_info.month,
+			day: comic_info.day,
+			writer: comic_info.writer,
+			penciller: comic_info.penciller,
+			inker: comic_info.inker,
+			colorist: comic_info.colorist,
+			letterer: comic_info.letterer,
+			cover_artist: comic_info.cover_artist,
+			editor: comic_info.editor,
+			publisher: comic_info.publisher,
+			genre: comic_info.genre,
+			web: comic_info.web,
+			page_count: comic_info.page_count,
+			language_iso: comic_info.language_iso,
+			characters: comic_info.characters,
+			teams: comic_info.teams,
+			locations: comic_info.locations,
+			story_arc: comic_info.story_arc,
+			age_rating: comic_info.age_rating,
+			manga: comic_info.manga,
+		}
+	}
+}
+
+/// Parses the contents of a ComicInfo.xml file into a `MediaMetadata`. Returns `None` if
+/// the file is empty or invalid.
+pub fn parse_comic_info(contents: &str) -> Option<MediaMetadata> {
+	if contents.is_empty() {
+		return None;
+	}
+
+	match serde_xml_rs::from_str::<ComicInfo>(contents) {
+		Ok(comic_info) => Some(comic_info.into()),
+		Err(e) => {
+			log::debug!("Failed to parse ComicInfo.xml: {}", e);
+			None
+		},
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn empty_elements_are_missing() {
+		let comic_info = r#"<?xml version="1.0" encoding="utf-8"?>
+<ComicInfo>
+	<Title>Batman</Title>
+	<Year></Year>
+	<Month />
+	<Writer> </Writer>
+	<PageCount>24</PageCount>
+</ComicInfo>"#;
+
+		let metadata =
+			parse_comic_info(comic_info).expect("Failed to parse ComicInfo.xml");
+
+		assert_eq!(metadata.title.as_deref(), Some("Batman"));
+		assert_eq!(metadata.year, None);
+		assert_eq!(metadata.month, None);
+		assert_eq!(metadata.writer, None);
+		assert_eq!(metadata.page_count, Some(24));
+	}
+}
@@ -1,4 +1,5 @@
 use std::{
+	collections::HashMap,
 	fs::File,
 	os::unix::prelude::MetadataExt,
 	path::{Path, PathBuf},
@@ -48,6 +49,94 @@ fn load_epub(path: &str) -> ProcessFileResult<EpubDoc<File>> {
 	Ok(EpubDoc::new(path).map_err(|e| ProcessFileError::EpubOpenError(e.to_string()))?)
 }
 
+/// Returns the first non-empty value of the given OPF metadata entry.
+fn get_metadata_value(
+	metadata: &HashMap<String, Vec<String>>,
+	key: &str,
+) -> Option<String> {
+	metadata
+		.get(key)?
+		.iter()
+		.map(|value| value.trim())
+		.find(|value| !value.is_empty())
+		.map(|value| value.to_string())
+}
+
+/// Returns all non-empty values of the given OPF metadata entry, joined by a comma. This
+/// matches how ComicInfo.xml represents lists (e.g. multiple writers).
+fn get_metadata_list(
+	metadata: &HashMap<String, Vec<String>>,
+	key: &str,
+) -> Option<String> {
+	let values = metadata
+		.get(key)?
+		.iter()
+		.map(|value| value.trim())
+		.filter(|value| !value.is_empty())
+		.collect::<Vec<&str>>();
+
+	if values.is_empty() {
+		None
+	} else {
+		Some(values.join(", "))
+	}
+}
+
+/// Splits an OPF date (e.g. `2019`, `2019-04` or `2019-04-20T00:00:00+00:00`) into its
+/// year, month and day.
+fn parse_epub_date(date: &str) -> (Option<i32>, Option<i32>, Option<i32>) {
+	let mut parts = date
+		.split('T')
+		.next()
+		.unwrap_or_default()
+		.split('-')
+		.map(|part| part.trim().parse::<i32>().ok());
+
+	(
+		parts.next().flatten(),
+		parts.next().flatten(),
+		parts.next().flatten(),
+	)
+}
+
+/// Maps the OPF metadata of an epub into a `MediaMetadata`. Dublin Core elements are
+/// used where possible, and the calibre series fields are used for series information.
+fn get_epub_metadata(metadata: &HashMap<String, Vec<String>>) -> Option<MediaMetadata> {
+	let (year, month, day) = get_metadata_value(metadata, "date")
+		.map(|date| parse_epub_date(&date))
+		.unwrap_or_default();
+
+	// calibre stores the series index as a float, e.g. "1.0", so it is normalized to
+	// match how ComicInfo.xml numbers are typically written.
+	let number = get_metadata_value(metadata, "calibre:series_index").map(|index| {
+		index
+			.parse::<f64>()
+			.map(|number| number.to_string())
+			.unwrap_or(index)
+	});
+
+	let epub_metadata = MediaMetadata {
+		title: get_metadata_value(metadata, "title"),
+		series: get_metadata_value(metadata, "calibre:series"),
+		number,
+		summary: get_metadata_value(metadata, "description"),
+		year,
+		month,
+		day,
+		writer: get_metadata_list(metadata, "creator"),
+		publisher: get_metadata_value(metadata, "publisher"),
+		genre: get_metadata_list(metadata, "subject"),
+		language_iso: get_metadata_value(metadata, "language"),
+		..MediaMetadata::default()
+	};
+
+	if epub_metadata == MediaMetadata::default() {
+		None
+	} else {
+		Some(epub_metadata)
+	}
+}
+
 pub fn process_epub(path: &Path) -> ProcessFileResult<ProcessedMediaFile> {
 	log::info!("Processing Epub: {}", path.display());
 
@@ -55,7 +144,7 @@ pub fn process_epub(path: &Path) -> ProcessFileResult<ProcessedMediaFile> {
 
 	let pages = epub_file.get_num_pages() as i32;
 
-	let metadata: Option<MediaMetadata> = None;
+	let metadata = get_epub_metadata(&epub_file.metadata);
 
 	Ok(ProcessedMediaFile {
 		thumbnail_path: None,
@@ -224,7 +313,7 @@ mod tests {
 	use super::get_epub_resource;
 
 	use rocket::{http::ContentType, tokio};
-	use std::{path::PathBuf, str::FromStr};
+	use std::{collections::HashMap, path::PathBuf, str::FromStr};
 
 	use crate::{config::context::*, prisma::media, types::models::epub::Epub};
 
@@ -291,6 +380,39 @@ mod tests {
 		Ok(())
 	}
 
+	#[test]
+	fn can_map_epub_metadata() {
+		let metadata = HashMap::from([
+			("title".to_string(), vec!["Dune".to_string()]),
+			(
+				"creator".to_string(),
+				vec!["Frank Herbert".to_string(), "".to_string()],
+			),
+			(
+				"date".to_string(),
+				vec!["1965-08-01T00:00:00+00:00".to_string()],
+			),
+			("calibre:series".to_string(), vec!["Dune".to_string()]),
+			("calibre:series_index".to_string(), vec!["1.0".to_string()]),
+			("language".to_string(), vec!["en".to_string()]),
+		]);
+
+		let metadata = super::get_epub_metadata(&metadata).unwrap();
+
+		assert_eq!(metadata.title.as_deref(), Some("Dune"));
+		assert_eq!(metadata.writer.as_deref(), Some("Frank Herbert"));
+		assert_eq!(metadata.series.as_deref(), Some("Dune"));
+		assert_eq!(metadata.number.as_deref(), Some("1"));
+		assert_eq!(metadata.language_iso.as_deref(), Some("en"));
+		assert_eq!(
+			(metadata.year, metadata.month, metadata.day),
+			(Some(1965), Some(8), Some(1))
+		);
+		assert_eq!(metadata.publisher, None);
+
+		assert!(super::get_epub_metadata(&HashMap::new()).is_none());
+	}
+
 	#[test]
 	fn canonical_correction() {
 		let invalid = PathBuf::from("OEBPS/../Styles/style.css");
@@ -1,16 +1,26 @@
-use image::{imageops, io::Reader, DynamicImage, EncodableLayout, GenericImageView};
+use image::{
+	codecs::jpeg::JpegEncoder, imageops, io::Reader, DynamicImage, EncodableLayout,
+	GenericImageView, ImageOutputFormat,
+};
 use rayon::prelude::{IntoParallelIterator, ParallelIterator};
 use std::{
 	fs::File,
-	io::{Read, Write},
+	io::{self, Cursor, Read, Write},
 	path::{Path, PathBuf},
 };
 use webp::{Encoder, WebPMemory};
 
 use crate::{
-	config::get_thumbnails_dir,
-	prisma::media,
-	types::{alias::ProcessFileResult, errors::ProcessFileError},
+	config::{get_covers_dir, get_thumbnails_dir},
+	prisma::{media, series},
+	types::{
+		alias::ProcessFileResult,
+		enums::ImageFormat,
+		errors::ProcessFileError,
+		http::ImageResponse,
+		models::{library::ThumbnailOptions, page::MediaPage},
+		query::ImageRequestParams,
+	},
 };
 
 use super::media_file;
@@ -24,83 +34,329 @@ pub fn get_image_bytes<P: AsRef<Path>>(path: P) -> ProcessFileResult<Vec<u8>> {
 	Ok(buf)
 }
 
-pub fn webp_from_path<P: AsRef<Path>>(file_path: P) -> ProcessFileResult<Vec<u8>> {
-	let image = Reader::open(file_path.as_ref())?
-		.with_guessed_format()?
-		.decode()?;
+/// Reads the dimensions of an image from its header, without decoding the entire image.
+pub fn get_image_dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+	Reader::new(Cursor::new(bytes))
+		.with_guessed_format()
+		.ok()?
+		.into_dimensions()
+		.ok()
+}
+
+/// The number of bytes read from the start of an image to find its dimensions, which
+/// covers the header of nearly every image, including jpegs with large EXIF or ICC data.
+const IMAGE_HEADER_SIZE: u64 = 64 * 1024;
+
+/// Reads the dimensions of an image from a reader (e.g. an archive entry) without reading,
+/// or decompressing, the rest of the image. The image is only read in full when its
+/// dimensions aren't within its first `IMAGE_HEADER_SIZE` bytes, e.g. for some tiffs.
+pub fn read_image_dimensions<R: Read + ?Sized>(
+	reader: &mut R,
+) -> io::Result<Option<(u32, u32)>> {
+	let mut header = Vec::new();
+	Read::take(&mut *reader, IMAGE_HEADER_SIZE).read_to_end(&mut header)?;
+
+	if let Some(dimensions) = get_image_dimensions(&header) {
+		return Ok(Some(dimensions));
+	}
+
+	if (header.len() as u64) < IMAGE_HEADER_SIZE {
+		return Ok(None);
+	}
 
+	reader.read_to_end(&mut header)?;
+
+	Ok(get_image_dimensions(&header))
+}
+
+/// The quality used when encoding a transformed image, if none was requested.
+const DEFAULT_QUALITY: u8 = 75;
+
+/// The largest cover image that may be uploaded, in mebibytes.
+pub const MAX_COVER_SIZE: u64 = 20;
+
+/// The formats a thumbnail may be stored in. The format of a thumbnail depends on the
+/// options it was generated with, so every format is checked when looking one up.
+const THUMBNAIL_FORMATS: [ImageFormat; 3] =
+	[ImageFormat::Webp, ImageFormat::Jpeg, ImageFormat::Png];
+
+/// Scales an image down to fit within the given dimensions, keeping its aspect ratio.
+/// Images are never scaled up.
+fn resize_image(
+	image: DynamicImage,
+	max_width: Option<u32>,
+	max_height: Option<u32>,
+	filter: imageops::FilterType,
+) -> DynamicImage {
 	let (width, height) = image.dimensions();
 
-	// TODO: perhaps make size factor configurable?
-	let size_factor = 0.5;
+	let max_width = max_width.unwrap_or(width).min(width);
+	let max_height = max_height.unwrap_or(height).min(height);
+
+	if max_width < width || max_height < height {
+		image.resize(max_width, max_height, filter)
+	} else {
+		image
+	}
+}
+
+/// Encodes an image in the given format. The quality is ignored for png.
+fn encode_image(
+	image: &DynamicImage,
+	format: ImageFormat,
+	quality: u8,
+) -> ProcessFileResult<Vec<u8>> {
+	let quality = quality.clamp(1, 100);
+
+	let mut buf = Vec::new();
+
+	match format {
+		ImageFormat::Webp => {
+			let image = DynamicImage::ImageRgba8(image.to_rgba8());
+
+			let encoder: Encoder = Encoder::from_image(&image)
+				.map_err(|err| ProcessFileError::WebpEncodeError(err.to_string()))?;
+
+			let encoded_webp: WebPMemory = encoder.encode(quality as f32);
+
+			buf.extend_from_slice(encoded_webp.as_bytes());
+		},
+		ImageFormat::Jpeg => {
+			// Note: jpeg has no alpha channel, so it is dropped before encoding.
+			JpegEncoder::new_with_quality(&mut buf, quality)
+				.encode_image(&image.to_rgb8())?;
+		},
+		ImageFormat::Png => {
+			image.write_to(&mut Cursor::new(&mut buf), ImageOutputFormat::Png)?;
+		},
+	}
+
+	Ok(buf)
+}
+
+/// Transforms an image according to the request params: it is scaled down to fit within
+/// the requested dimensions, keeping its aspect ratio, and is then encoded in the
+/// requested format. When no format is requested, the format of the original image is
+/// kept if possible, otherwise webp is used.
+pub fn transform_image(
+	image_response: ImageResponse,
+	params: &ImageRequestParams,
+) -> ProcessFileResult<ImageResponse> {
+	if params.is_empty() {
+		return Ok(image_response);
+	}
+
+	let (content_type, bytes) = image_response;
 
-	let image = DynamicImage::ImageRgba8(imageops::resize(
-		&image,
-		(width as f64 * size_factor) as u32,
-		(height as f64 * size_factor) as u32,
-		// TODO: determine best filter
+	let image = resize_image(
+		image::load_from_memory(&bytes)?,
+		params.width,
+		params.height,
 		imageops::FilterType::Triangle,
-	));
+	);
 
-	let encoder: Encoder = Encoder::from_image(&image)
-		.map_err(|err| ProcessFileError::WebpEncodeError(err.to_string()))?;
+	let format = params
+		.format
+		.or_else(|| ImageFormat::from_content_type(&content_type))
+		.unwrap_or(ImageFormat::Webp);
 
-	let encoded_webp: WebPMemory = encoder.encode(65f32);
+	let buf = encode_image(&image, format, params.quality.unwrap_or(DEFAULT_QUALITY))?;
 
-	Ok(encoded_webp.as_bytes().to_vec())
+	Ok((format.content_type(), buf))
 }
 
+/// Creates a thumbnail from the bytes of an image, according to the thumbnail options.
 // TODO: this is **super** slow!!!!
-pub fn webp_from_bytes(bytes: &[u8]) -> ProcessFileResult<Vec<u8>> {
-	let image = image::load_from_memory(bytes)?;
+pub fn create_thumbnail(
+	bytes: &[u8],
+	options: &ThumbnailOptions,
+) -> ProcessFileResult<Vec<u8>> {
+	let image = resize_image(
+		image::load_from_memory(bytes)?,
+		Some(options.max_width),
+		Some(options.max_height),
+		options.filter.into(),
+	);
+
+	encode_image(&image, options.format, options.quality)
+}
 
-	let (width, height) = image.dimensions();
+/// Creates a thumbnail from the bytes of an image and writes it to the thumbnails
+/// directory, replacing any existing thumbnail for the id.
+pub fn write_thumbnail(
+	id: &str,
+	bytes: &[u8],
+	options: &ThumbnailOptions,
+) -> ProcessFileResult<PathBuf> {
+	let thumbnail_buf = create_thumbnail(bytes, options)?;
 
-	// TODO: perhaps make size factor configurable?
-	let size_factor = 0.5;
+	remove_thumbnail(id)?;
 
-	let image = DynamicImage::ImageRgba8(imageops::resize(
-		&image,
-		(width as f64 * size_factor) as u32,
-		(height as f64 * size_factor) as u32,
-		// TODO: determine best filter
-		imageops::FilterType::Triangle,
-	));
+	let thumbnail_path =
+		get_thumbnails_dir().join(format!("{}.{}", id, options.format.extension()));
 
-	let encoder: Encoder = Encoder::from_image(&image)
-		.map_err(|err| ProcessFileError::WebpEncodeError(err.to_string()))?;
+	let mut thumbnail_image = File::create(&thumbnail_path)?;
+	thumbnail_image.write_all(&thumbnail_buf)?;
 
-	let encoded_webp: WebPMemory = encoder.encode(5.0);
+	Ok(thumbnail_path)
+}
 
-	Ok(encoded_webp.as_bytes().to_vec())
+/// Returns the entry of a page in the page index of a media. Returns `None` if the page
+/// index wasn't loaded along with the media (see `media::page_index::fetch`), or if the
+/// media has no page index.
+pub fn find_indexed_page(media: &media::Data, page: i32) -> Option<MediaPage> {
+	media
+		.page_index()
+		.ok()?
+		.iter()
+		.find(|indexed_page| indexed_page.page == page)
+		.cloned()
+		.map(Into::into)
 }
 
-pub fn generate_thumbnail(id: &str, path: &str) -> ProcessFileResult<PathBuf> {
-	let (_, buf) = media_file::get_page(path, 1)?;
-	let webp_buf = webp_from_bytes(&buf)?;
+/// Generates a thumbnail for a media from its first page, read using its entry in the
+/// page index if given. Existing thumbnails are kept unless `overwrite` is true, in which
+/// case they are replaced, regardless of the format they were generated in.
+pub fn generate_thumbnail(
+	id: &str,
+	path: &str,
+	first_page: Option<&MediaPage>,
+	options: &ThumbnailOptions,
+	overwrite: bool,
+) -> ProcessFileResult<PathBuf> {
+	if let Some(existing_path) = get_thumbnail_path(id) {
+		if !overwrite {
+			log::trace!("Thumbnail already exists for {}", &id);
+			return Ok(existing_path);
+		}
+	}
 
-	let thumbnail_path = get_thumbnails_dir().join(format!("{}.webp", &id));
+	let (_, buf) = media_file::get_page_with_index(path, 1, first_page)?;
 
-	if !thumbnail_path.exists() {
-		let mut webp_image = File::create(&thumbnail_path)?;
+	write_thumbnail(id, &buf, options)
+}
 
-		webp_image.write_all(&webp_buf)?;
-	} else {
-		log::trace!("Thumbnail already exists for {}", &id);
+/// Reads a cover image from disk. Missing or unreadable covers are logged and ignored, so
+/// that callers may fall back to the default cover.
+fn read_cover_image(path: &str) -> Option<ImageResponse> {
+	match get_image_bytes(path) {
+		Ok(bytes) => Some((media_file::guess_content_type(path), bytes)),
+		Err(e) => {
+			log::warn!("Failed to read cover image {}: {}", path, e);
+			None
+		},
 	}
+}
 
-	Ok(thumbnail_path)
+/// Reads the cover of a media: its cover image if one was uploaded, otherwise its cover
+/// page, which defaults to the first page. The cover page is read using the page index,
+/// when it was loaded along with the media.
+pub fn get_media_cover(media: &media::Data) -> ProcessFileResult<ImageResponse> {
+	if let Some(cover) = media.cover_path.as_deref().and_then(read_cover_image) {
+		return Ok(cover);
+	}
+
+	let page = media.cover_page.unwrap_or(1);
+
+	media_file::get_page_with_index(
+		&media.path,
+		page,
+		find_indexed_page(media, page).as_ref(),
+	)
+}
+
+/// Reads the cover image of a series, if it has one. Series without a cover image use the
+/// cover of their first media.
+pub fn get_series_cover(series: &series::Data) -> Option<ImageResponse> {
+	series.cover_path.as_deref().and_then(read_cover_image)
+}
+
+/// Generates a thumbnail for a media from its cover, replacing any existing thumbnail.
+pub fn generate_media_thumbnail(
+	media: &media::Data,
+	options: &ThumbnailOptions,
+) -> ProcessFileResult<PathBuf> {
+	let (_, buf) = get_media_cover(media)?;
+
+	write_thumbnail(&media.id, &buf, options)
+}
+
+/// Generates a thumbnail for a series from its cover image, replacing any existing
+/// thumbnail. When the series has no cover image, its thumbnail is removed instead.
+pub fn generate_series_thumbnail(
+	series: &series::Data,
+	options: &ThumbnailOptions,
+) -> ProcessFileResult<Option<PathBuf>> {
+	match get_series_cover(series) {
+		Some((_, buf)) => Ok(Some(write_thumbnail(&series.id, &buf, options)?)),
+		None => {
+			remove_thumbnail(&series.id)?;
+			Ok(None)
+		},
+	}
+}
+
+/// Returns true if the cover image at the given path was uploaded, rather than found
+/// while scanning.
+pub fn is_uploaded_cover(path: &str) -> bool {
+	Path::new(path).starts_with(get_covers_dir())
+}
+
+/// Writes an uploaded cover image for a series or media to the covers directory,
+/// replacing any previously uploaded cover for the id.
+pub fn save_cover_image(id: &str, bytes: &[u8]) -> ProcessFileResult<PathBuf> {
+	let extension = infer::get(bytes)
+		.filter(|kind| kind.mime_type().starts_with("image/"))
+		.map(|kind| kind.extension())
+		.ok_or_else(|| {
+			ProcessFileError::UnsupportedFileType(
+				"Cover images must be images".to_string(),
+			)
+		})?;
+
+	remove_cover_image(id)?;
+
+	let cover_path = get_covers_dir().join(format!("{}.{}", id, extension));
+
+	let mut cover_image = File::create(&cover_path)?;
+	cover_image.write_all(bytes)?;
+
+	Ok(cover_path)
+}
+
+/// Removes the uploaded cover image for a series or media, if there is one.
+pub fn remove_cover_image(id: &str) -> ProcessFileResult<()> {
+	for entry in std::fs::read_dir(get_covers_dir())?.filter_map(|entry| entry.ok()) {
+		let path = entry.path();
+
+		if path.file_stem().and_then(|stem| stem.to_str()) == Some(id) {
+			std::fs::remove_file(path)?;
+		}
+	}
+
+	Ok(())
 }
 
 // TODO: does this need to return a result?
-pub fn generate_thumbnails(media: Vec<media::Data>) -> ProcessFileResult<Vec<PathBuf>> {
+pub fn generate_thumbnails(
+	media: Vec<media::Data>,
+	options: &ThumbnailOptions,
+) -> ProcessFileResult<Vec<PathBuf>> {
 	log::debug!("Enter generate_thumbnails");
 
 	// TODO: this might make the stack overflow lol
 	let results = media
 		.into_par_iter()
 		// .with_max_len(5)
-		.map(|m| generate_thumbnail(m.id.as_str(), &m.path.as_str()))
+		.map(|m| {
+			generate_thumbnail(
+				m.id.as_str(),
+				m.path.as_str(),
+				find_indexed_page(&m, 1).as_ref(),
+				options,
+				false,
+			)
+		})
 		.filter_map(|res| {
 			if res.is_err() {
 				log::error!("Error generating thumbnail: {:?}", res.err());
@@ -117,20 +373,37 @@ pub fn generate_thumbnails(media: Vec<media::Data>) -> ProcessFileResult<Vec<Pat
 }
 
 pub fn get_thumbnail_path(id: &str) -> Option<PathBuf> {
-	let thumbnail_path = get_thumbnails_dir().join(format!("{}.webp", id));
+	let thumbnails_dir = get_thumbnails_dir();
 
-	if thumbnail_path.exists() {
-		Some(thumbnail_path)
-	} else {
-		None
+	THUMBNAIL_FORMATS
+		.iter()
+		.map(|format| thumbnails_dir.join(format!("{}.{}", id, format.extension())))
+		.find(|thumbnail_path| thumbnail_path.exists())
+}
+
+/// Reads the thumbnail of a media, if one has been generated.
+pub fn get_thumbnail(id: &str) -> ProcessFileResult<Option<ImageResponse>> {
+	match get_thumbnail_path(id) {
+		Some(thumbnail_path) => {
+			let content_type =
+				media_file::guess_content_type(&thumbnail_path.to_string_lossy());
+
+			Ok(Some((content_type, get_image_bytes(thumbnail_path)?)))
+		},
+		None => Ok(None),
 	}
 }
 
 pub fn remove_thumbnail(id: &str) -> ProcessFileResult<()> {
-	let thumbnail_path = get_thumbnails_dir().join(format!("{}.webp", id));
+	let thumbnails_dir = get_thumbnails_dir();
+
+	for format in THUMBNAIL_FORMATS {
+		let thumbnail_path =
+			thumbnails_dir.join(format!("{}.{}", id, format.extension()));
 
-	if thumbnail_path.exists() {
-		std::fs::remove_file(thumbnail_path)?;
+		if thumbnail_path.exists() {
+			std::fs::remove_file(thumbnail_path)?;
+		}
 	}
 
 	Ok(())
@@ -149,4 +422,67 @@ pub fn remove_thumbnails(id_list: &[String]) -> ProcessFileResult<()> {
 
 // TODO: tests!
 #[cfg(test)]
-mod tests {}
+mod tests {
+	use super::*;
+
+	use image::{ImageBuffer, Rgba};
+	use rocket::http::ContentType;
+
+	fn png_bytes(width: u32, height: u32) -> Vec<u8> {
+		let image = DynamicImage::ImageRgba8(ImageBuffer::from_pixel(
+			width,
+			height,
+			Rgba([255, 0, 0, 255]),
+		));
+
+		let mut buf = Vec::new();
+		image
+			.write_to(&mut Cursor::new(&mut buf), ImageOutputFormat::Png)
+			.unwrap();
+
+		buf
+	}
+
+	#[test]
+	fn transforms_image_to_requested_size_and_format() {
+		let params = ImageRequestParams {
+			width: Some(50),
+			format: Some(ImageFormat::Jpeg),
+			..Default::default()
+		};
+
+		let (content_type, bytes) =
+			transform_image((ContentType::PNG, png_bytes(200, 100)), &params).unwrap();
+
+		assert_eq!(content_type, ContentType::JPEG);
+		assert_eq!(get_image_dimensions(&bytes), Some((50, 25)));
+	}
+
+	#[test]
+	fn creates_thumbnail_within_max_dimensions() {
+		let options = ThumbnailOptions {
+			max_width: 100,
+			max_height: 100,
+			..Default::default()
+		};
+
+		let thumbnail = create_thumbnail(&png_bytes(100, 400), &options).unwrap();
+
+		assert_eq!(get_image_dimensions(&thumbnail), Some((25, 100)));
+	}
+
+	#[test]
+	fn never_scales_images_up() {
+		let params = ImageRequestParams {
+			width: Some(400),
+			height: Some(400),
+			..Default::default()
+		};
+
+		let (content_type, bytes) =
+			transform_image((ContentType::PNG, png_bytes(200, 100)), &params).unwrap();
+
+		assert_eq!(content_type, ContentType::PNG);
+		assert_eq!(get_image_dimensions(&bytes), Some((200, 100)));
+	}
+}
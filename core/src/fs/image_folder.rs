@@ -0,0 +1,254 @@
+use std::{
+	fs::File,
+	io::Read,
+	path::{Path, PathBuf},
+};
+
+use crate::{
+	fs::{
+		checksum, media_file,
+		page_order::{self, COMIC_INFO_FILE},
+		scanner::ScannedFileTrait,
+	},
+	types::{
+		alias::ProcessFileResult,
+		errors::ProcessFileError,
+		http,
+		models::{media::ProcessedMediaFile, page::MediaPage},
+	},
+};
+
+fn is_comic_info(path: &Path) -> bool {
+	path.file_name()
+		.and_then(|name| name.to_str())
+		.map(|name| name.eq_ignore_ascii_case(COMIC_INFO_FILE))
+		.unwrap_or(false)
+}
+
+/// Returns true if the directory at the given path should be treated as a single media,
+/// i.e. it has no subdirectories and contains only images (and optionally a
+/// ComicInfo.xml file). Hidden files are not considered.
+pub fn is_image_folder(path: &Path) -> bool {
+	if !path.is_dir() {
+		return false;
+	}
+
+	let items = match std::fs::read_dir(path) {
+		Ok(items) => items,
+		Err(e) => {
+			log::error!("Failed to read directory {:?}: {}", path, e);
+			return false;
+		},
+	};
+
+	let mut image_count = 0;
+
+	for item in items.filter_map(|item| item.ok()) {
+		let item_path = item.path();
+
+		if item_path.is_invisible_file() || is_comic_info(&item_path) {
+			continue;
+		} else if item_path.is_dir() || !item_path.is_img() {
+			return false;
+		}
+
+		image_count += 1;
+	}
+
+	image_count > 0
+}
+
+/// Reads the ComicInfo.xml file of the folder, if present. The name is matched case
+/// insensitively, like when the folder is detected (see `is_image_folder`).
+pub fn read_image_folder_comic_info(path: &Path) -> ProcessFileResult<Option<String>> {
+	let comic_info_path = std::fs::read_dir(path)?
+		.filter_map(|item| item.ok())
+		.map(|item| item.path())
+		.find(|item_path| item_path.is_file() && is_comic_info(item_path));
+
+	let comic_info_path = match comic_info_path {
+		Some(comic_info_path) => comic_info_path,
+		None => return Ok(None),
+	};
+
+	let mut contents = String::new();
+	File::open(comic_info_path)?.read_to_string(&mut contents)?;
+
+	Ok(Some(contents))
+}
+
+/// Returns the paths of the images within the folder, in page order.
+pub fn get_folder_images(path: &Path) -> ProcessFileResult<Vec<PathBuf>> {
+	let image_names = std::fs::read_dir(path)?
+		.filter_map(|item| item.ok())
+		.map(|item| item.path())
+		.filter(|item_path| !item_path.is_invisible_file() && item_path.is_img())
+		.filter_map(|item_path| {
+			item_path
+				.file_name()
+				.map(|name| name.to_string_lossy().to_string())
+		})
+		.collect::<Vec<String>>();
+
+	let comic_info = read_image_folder_comic_info(path)?;
+
+	Ok(page_order::order_pages(image_names, comic_info.as_deref())
+		.into_iter()
+		.map(|name| path.join(name))
+		.collect())
+}
+
+/// Returns the total size, in bytes, of the files directly within the folder.
+pub fn get_folder_size(path: &Path) -> u64 {
+	std::fs::read_dir(path)
+		.map(|items| {
+			items
+				.filter_map(|item| item.ok())
+				.filter_map(|item| item.metadata().ok())
+				.filter(|metadata| metadata.is_file())
+				.map(|metadata| metadata.len())
+				.sum()
+		})
+		.unwrap_or(0)
+}
+
+/// Generates a checksum for an image folder. Each image is digested the same way a single
+/// file would be, and the resulting digests are then digested together in page order.
+pub fn digest_image_folder(images: &[PathBuf]) -> Option<String> {
+	let mut digests = String::new();
+
+	for image in images {
+		let image_str = image.to_string_lossy();
+
+		let digest = checksum::sample_size(&image_str)
+			.and_then(|size| checksum::digest(&image_str, size));
+
+		match digest {
+			Ok(digest) => digests.push_str(&digest),
+			Err(e) => {
+				log::error!(
+					"Failed to digest image {}. Unable to generate checksum: {}",
+					image_str,
+					e
+				);
+				return None;
+			},
+		}
+	}
+
+	match checksum::digest_from_reader(digests.as_bytes()) {
+		Ok(digest) => Some(digest),
+		Err(e) => {
+			log::error!("Failed to digest image folder: {}", e);
+			None
+		},
+	}
+}
+
+/// Processes a folder of images as a single media, includes: metadata (when a
+/// ComicInfo.xml file is present), page count, and the generated checksum for the folder.
+pub fn process_image_folder(path: &Path) -> ProcessFileResult<ProcessedMediaFile> {
+	log::info!("Processing image folder: {}", path.display());
+
+	let images = get_folder_images(path)?;
+
+	Ok(ProcessedMediaFile {
+		thumbnail_path: None,
+		path: path.to_path_buf(),
+		checksum: digest_image_folder(&images),
+		metadata: read_image_folder_comic_info(path)?
+			.and_then(media_file::process_comic_info),
+		pages: images.len() as i32,
+	})
+}
+
+/// Builds the page index of an image folder, where the name of each page is the file name
+/// of the image within the folder.
+pub fn index_image_folder_pages(path: &Path) -> ProcessFileResult<Vec<MediaPage>> {
+	let images = get_folder_images(path)?;
+
+	let mut index = Vec::with_capacity(images.len());
+
+	for (i, image_path) in images.iter().enumerate() {
+		let name = image_path
+			.file_name()
+			.map(|name| name.to_string_lossy().to_string())
+			.unwrap_or_default();
+
+		index.push(media_file::index_page(
+			i + 1,
+			&name,
+			None,
+			image_path.metadata()?.len(),
+			::image::image_dimensions(image_path).ok(),
+		));
+	}
+
+	Ok(index)
+}
+
+/// Verifies every image of an image folder by decoding its header.
+pub fn verify_image_folder(path: &Path) -> ProcessFileResult<()> {
+	let images = get_folder_images(path)?;
+
+	if images.is_empty() {
+		return Err(ProcessFileError::ArchiveEmptyError);
+	}
+
+	for image_path in images {
+		::image::image_dimensions(&image_path)?;
+	}
+
+	Ok(())
+}
+
+/// Get an image from an image folder by index (page).
+pub fn get_image_folder_page(
+	file: &str,
+	page: i32,
+) -> ProcessFileResult<http::ImageResponse> {
+	let images = get_folder_images(Path::new(file))?;
+
+	if images.is_empty() {
+		log::error!("Image folder {} contains no images", file);
+		return Err(ProcessFileError::ArchiveEmptyError);
+	}
+
+	let image = images.get((page - 1) as usize).ok_or_else(|| {
+		log::error!("Could not find image for page {} in folder {}", page, file);
+		ProcessFileError::NoImageError
+	})?;
+
+	let mut contents = Vec::new();
+	File::open(image)?.read_to_end(&mut contents)?;
+
+	Ok((
+		media_file::guess_content_type(&image.to_string_lossy()),
+		contents,
+	))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn fixture() -> PathBuf {
+		PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+			.join("tests")
+			.join("fixtures")
+			.join("image_folder")
+	}
+
+	#[test]
+	fn reads_comic_info_of_any_case() {
+		let path = fixture();
+
+		assert!(is_image_folder(&path));
+
+		let comic_info = read_image_folder_comic_info(&path).unwrap();
+		assert!(comic_info.unwrap().contains("<Title>Fixture</Title>"));
+
+		let images = get_folder_images(&path).unwrap();
+		assert_eq!(images, vec![path.join("1.png"), path.join("2.png")]);
+	}
+}
@@ -1,5 +1,10 @@
 use rocket::http::ContentType;
-use std::{path::Path, str::FromStr};
+use std::{
+	fs::File,
+	io::{self, Read, Seek, SeekFrom},
+	path::Path,
+	str::FromStr,
+};
 
 use crate::types::{
 	alias::ProcessFileResult,
@@ -8,13 +13,23 @@ use crate::types::{
 	models::{
 		library::LibraryOptions,
 		media::{MediaMetadata, ProcessedMediaFile},
+		page::MediaPage,
 	},
 };
 
 use super::{
+	comic_info,
 	epub::{get_epub_cover, process_epub},
-	rar::{get_rar_image, process_rar},
-	zip::{get_zip_image, process_zip},
+	image_folder::{
+		get_image_folder_page, index_image_folder_pages, process_image_folder,
+		read_image_folder_comic_info, verify_image_folder,
+	},
+	page_order::COMIC_INFO_FILE,
+	pdf::{get_pdf_page, process_pdf, verify_pdf},
+	rar::{get_rar_image, index_rar_pages, process_rar, read_rar_entry, verify_rar},
+	seven_zip::{get_7z_image, index_7z_pages, process_7z, read_7z_entry, verify_7z},
+	tar::{get_tar_image, index_tar_pages, process_tar, read_tar_entry, verify_tar},
+	zip::{get_zip_image, index_zip_pages, process_zip, read_zip_entry, verify_zip},
 };
 
 // FIXME: this module does way too much. It should be cleaned up, way too many vaguely
@@ -30,14 +45,35 @@ pub trait IsImage {
 }
 
 pub fn process_comic_info(buffer: String) -> Option<MediaMetadata> {
-	if buffer.is_empty() {
-		return None;
+	comic_info::parse_comic_info(&buffer)
+}
+
+/// Reads the metadata of a media from its ComicInfo.xml file, without processing the rest
+/// of the media. Returns `None` for media without one, or formats which can't have one.
+pub fn read_comic_info(path: &Path) -> ProcessFileResult<Option<MediaMetadata>> {
+	if path.is_dir() {
+		return Ok(read_image_folder_comic_info(path)?.and_then(process_comic_info));
 	}
 
-	match serde_xml_rs::from_str(&buffer) {
-		Ok(info) => Some(info),
+	let mime = infer_mime_from_path(path);
+
+	let contents = match mime.as_deref() {
+		Some("application/zip") => read_zip_entry(path, COMIC_INFO_FILE)?,
+		Some("application/vnd.comicbook+zip") => read_zip_entry(path, COMIC_INFO_FILE)?,
+		Some("application/vnd.rar") => read_rar_entry(path, Path::new(COMIC_INFO_FILE))?,
+		Some("application/vnd.comicbook-rar") => {
+			read_rar_entry(path, Path::new(COMIC_INFO_FILE))?
+		},
+		Some("application/x-7z-compressed") => read_7z_entry(path, COMIC_INFO_FILE)?,
+		Some("application/x-cb7") => read_7z_entry(path, COMIC_INFO_FILE)?,
+		Some("application/x-tar") => read_tar_entry(path, COMIC_INFO_FILE)?,
+		Some("application/x-cbt") => read_tar_entry(path, COMIC_INFO_FILE)?,
 		_ => None,
-	}
+	};
+
+	Ok(contents
+		.and_then(|contents| String::from_utf8(contents).ok())
+		.and_then(process_comic_info))
 }
 
 // I am adding the required and currently missing types I need to Rocket
@@ -87,6 +123,24 @@ pub fn get_content_type_from_mime(mime: &str) -> ContentType {
 	})
 }
 
+/// Returns true if Stump is able to process files with the given MIME type, i.e. there
+/// is a handler for it in `process`.
+pub fn is_supported_mime(mime: &str) -> bool {
+	matches!(
+		mime,
+		"application/zip"
+			| "application/vnd.comicbook+zip"
+			| "application/vnd.rar"
+			| "application/vnd.comicbook-rar"
+			| "application/x-7z-compressed"
+			| "application/x-cb7"
+			| "application/x-tar"
+			| "application/x-cbt"
+			| "application/epub+zip"
+			| "application/pdf"
+	)
+}
+
 /// Guess the MIME type of a file based on its extension.
 pub fn guess_mime(path: &Path) -> Option<String> {
 	let extension = path.extension().and_then(|ext| ext.to_str());
@@ -115,6 +169,10 @@ pub fn guess_mime(path: &Path) -> Option<String> {
 		"cbz" => Some("application/vnd.comicbook+zip".to_string()),
 		"rar" => Some("application/vnd.rar".to_string()),
 		"cbr" => Some("application/vnd.comicbook-rar".to_string()),
+		"7z" => Some("application/x-7z-compressed".to_string()),
+		"cb7" => Some("application/x-cb7".to_string()),
+		"tar" => Some("application/x-tar".to_string()),
+		"cbt" => Some("application/x-cbt".to_string()),
 		"png" => Some("image/png".to_string()),
 		"jpg" => Some("image/jpeg".to_string()),
 		"jpeg" => Some("image/jpeg".to_string()),
@@ -145,6 +203,10 @@ pub fn infer_mime_from_path(path: &Path) -> Option<String> {
 }
 
 pub fn get_page(file: &str, page: i32) -> ProcessFileResult<http::ImageResponse> {
+	if Path::new(file).is_dir() {
+		return get_image_folder_page(file, page);
+	}
+
 	let mime = guess_mime(Path::new(file));
 
 	match mime.as_deref() {
@@ -152,6 +214,11 @@ pub fn get_page(file: &str, page: i32) -> ProcessFileResult<http::ImageResponse>
 		Some("application/vnd.comicbook+zip") => get_zip_image(file, page),
 		Some("application/vnd.rar") => get_rar_image(file, page),
 		Some("application/vnd.comicbook-rar") => get_rar_image(file, page),
+		Some("application/x-7z-compressed") => get_7z_image(file, page),
+		Some("application/x-cb7") => get_7z_image(file, page),
+		Some("application/x-tar") => get_tar_image(file, page),
+		Some("application/x-cbt") => get_tar_image(file, page),
+		Some("application/pdf") => get_pdf_page(file, page),
 		Some("application/epub+zip") => {
 			if page == 1 {
 				get_epub_cover(file)
@@ -169,12 +236,198 @@ pub fn get_page(file: &str, page: i32) -> ProcessFileResult<http::ImageResponse>
 	}
 }
 
+/// Creates the page index entry for an image within a media file.
+pub fn index_page(
+	page: usize,
+	name: &str,
+	offset: Option<u64>,
+	size: u64,
+	dimensions: Option<(u32, u32)>,
+) -> MediaPage {
+	MediaPage {
+		page: page as i32,
+		name: name.to_string(),
+		offset: offset.map(|offset| offset as i64),
+		size: size as i64,
+		mime: guess_content_type(name).to_string(),
+		width: dimensions.map(|(width, _)| width as i32),
+		height: dimensions.map(|(_, height)| height as i32),
+		// Note: whether a page is a spread depends on the other pages, see `mark_spreads`.
+		is_spread: false,
+	}
+}
+
+/// How much wider than the typical page of a media a page must be to be a spread. Spreads
+/// are twice as wide as a page, less whatever was trimmed when they were scanned.
+const SPREAD_RATIO: f64 = 1.8;
+
+fn get_aspect_ratio(page: &MediaPage) -> Option<f64> {
+	match (page.width, page.height) {
+		(Some(width), Some(height)) if height > 0 => Some(width as f64 / height as f64),
+		_ => None,
+	}
+}
+
+/// Flags the pages which are double-page spreads, i.e. those much wider than the median
+/// page of the media. Single pages (e.g. covers) are sometimes landscape themselves, so
+/// pages are compared against the rest of the media rather than a fixed ratio.
+fn mark_spreads(pages: &mut [MediaPage]) {
+	let mut aspect_ratios = pages
+		.iter()
+		.filter_map(get_aspect_ratio)
+		.collect::<Vec<f64>>();
+
+	if aspect_ratios.is_empty() {
+		return;
+	}
+
+	aspect_ratios.sort_by(|a, b| a.total_cmp(b));
+	let median = aspect_ratios[(aspect_ratios.len() - 1) / 2];
+
+	for page in pages.iter_mut() {
+		page.is_spread = get_aspect_ratio(page)
+			.map(|aspect_ratio| aspect_ratio >= median * SPREAD_RATIO)
+			.unwrap_or(false);
+	}
+}
+
+/// Builds the page index of a media file, so that pages can later be read without
+/// listing the entire file. Formats which aren't made up of images (i.e. pdf and epub)
+/// have no page index.
+pub fn index_pages(path: &Path) -> ProcessFileResult<Vec<MediaPage>> {
+	let mut pages = index_file_pages(path)?;
+
+	mark_spreads(&mut pages);
+
+	Ok(pages)
+}
+
+fn index_file_pages(path: &Path) -> ProcessFileResult<Vec<MediaPage>> {
+	if path.is_dir() {
+		return index_image_folder_pages(path);
+	}
+
+	let mime = guess_mime(path);
+
+	match mime.as_deref() {
+		Some("application/zip") => index_zip_pages(path),
+		Some("application/vnd.comicbook+zip") => index_zip_pages(path),
+		Some("application/vnd.rar") => index_rar_pages(path),
+		Some("application/vnd.comicbook-rar") => index_rar_pages(path),
+		Some("application/x-7z-compressed") => index_7z_pages(path),
+		Some("application/x-cb7") => index_7z_pages(path),
+		Some("application/x-tar") => index_tar_pages(path),
+		Some("application/x-cbt") => index_tar_pages(path),
+		_ => Ok(vec![]),
+	}
+}
+
+/// Reads `size` bytes starting at `offset` from the file. Returns `None` if the file
+/// isn't long enough, e.g. when it has changed since it was indexed.
+fn read_file_range(
+	path: &Path,
+	offset: u64,
+	size: u64,
+) -> ProcessFileResult<Option<Vec<u8>>> {
+	let mut file = File::open(path)?;
+	file.seek(SeekFrom::Start(offset))?;
+
+	let mut contents = vec![0; size as usize];
+
+	match file.read_exact(&mut contents) {
+		Ok(_) => Ok(Some(contents)),
+		Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(None),
+		Err(e) => Err(e.into()),
+	}
+}
+
+fn read_indexed_page(
+	path: &Path,
+	page: &MediaPage,
+) -> ProcessFileResult<Option<Vec<u8>>> {
+	if path.is_dir() {
+		return Ok(Some(std::fs::read(path.join(&page.name))?));
+	}
+
+	if let Some(offset) = page.offset {
+		// Note: the bytes at the offset are only trusted if they look like an image, since
+		// the file may have been modified since it was indexed.
+		return Ok(read_file_range(path, offset as u64, page.size as u64)?
+			.filter(|contents| infer::is_image(contents)));
+	}
+
+	let mime = guess_mime(path);
+
+	match mime.as_deref() {
+		Some("application/zip") => read_zip_entry(path, &page.name),
+		Some("application/vnd.comicbook+zip") => read_zip_entry(path, &page.name),
+		Some("application/vnd.rar") => read_rar_entry(path, Path::new(&page.name)),
+		Some("application/vnd.comicbook-rar") => {
+			read_rar_entry(path, Path::new(&page.name))
+		},
+		Some("application/x-7z-compressed") => read_7z_entry(path, &page.name),
+		Some("application/x-cb7") => read_7z_entry(path, &page.name),
+		Some("application/x-tar") => read_tar_entry(path, &page.name),
+		Some("application/x-cbt") => read_tar_entry(path, &page.name),
+		_ => Ok(None),
+	}
+}
+
+/// Get a page using its entry in the page index, which reads the single entry directly
+/// rather than listing and sorting the entire file. If the entry can't be read (e.g. the
+/// file has changed since it was indexed), this falls back to `get_page`.
+pub fn get_indexed_page(
+	file: &str,
+	page: &MediaPage,
+) -> ProcessFileResult<http::ImageResponse> {
+	match read_indexed_page(Path::new(file), page) {
+		Ok(Some(contents)) => Ok((get_content_type_from_mime(&page.mime), contents)),
+		Ok(None) => {
+			log::warn!(
+				"Page {} of {} does not match the page index, falling back to a full read",
+				page.page,
+				file
+			);
+			get_page(file, page.page)
+		},
+		Err(e) => {
+			log::warn!(
+				"Failed to read page {} of {} using the page index: {}",
+				page.page,
+				file,
+				e
+			);
+			get_page(file, page.page)
+		},
+	}
+}
+
+/// Get a page using its entry in the page index, if the media has one (see
+/// `get_indexed_page`). Media scanned before the page index existed won't have one, so
+/// those pages are still read by listing the file.
+pub fn get_page_with_index(
+	file: &str,
+	page: i32,
+	indexed_page: Option<&MediaPage>,
+) -> ProcessFileResult<http::ImageResponse> {
+	match indexed_page {
+		Some(indexed_page) => get_indexed_page(file, indexed_page),
+		None => get_page(file, page),
+	}
+}
+
 pub fn process(
 	path: &Path,
 	options: &LibraryOptions,
 ) -> ProcessFileResult<ProcessedMediaFile> {
 	log::debug!("Processing entry {:?} with options: {:?}", path, options);
 
+	// Note: the scanner will only provide directories when the library is configured
+	// to treat image folders as media.
+	if path.is_dir() {
+		return process_image_folder(path);
+	}
+
 	let mime = infer_mime_from_path(path);
 
 	match mime.as_deref() {
@@ -182,7 +435,12 @@ pub fn process(
 		Some("application/vnd.comicbook+zip") => process_zip(path),
 		Some("application/vnd.rar") => process_rar(path, options),
 		Some("application/vnd.comicbook-rar") => process_rar(path, options),
+		Some("application/x-7z-compressed") => process_7z(path),
+		Some("application/x-cb7") => process_7z(path),
+		Some("application/x-tar") => process_tar(path),
+		Some("application/x-cbt") => process_tar(path),
 		Some("application/epub+zip") => process_epub(path),
+		Some("application/pdf") => process_pdf(path),
 		None => Err(ProcessFileError::Unknown(format!(
 			"Unable to determine mime type for file: {:?}",
 			path
@@ -192,3 +450,117 @@ pub fn process(
 		)),
 	}
 }
+
+/// Verifies the integrity of a media file by reading every one of its entries, checking
+/// their crc where the format stores one. Epubs are zip files, so they are verified the
+/// same way.
+pub fn verify(path: &Path) -> ProcessFileResult<()> {
+	log::debug!("Verifying entry {:?}", path);
+
+	if path.is_dir() {
+		return verify_image_folder(path);
+	}
+
+	let mime = infer_mime_from_path(path);
+
+	match mime.as_deref() {
+		Some("application/zip") => verify_zip(path),
+		Some("application/vnd.comicbook+zip") => verify_zip(path),
+		Some("application/epub+zip") => verify_zip(path),
+		Some("application/vnd.rar") => verify_rar(path),
+		Some("application/vnd.comicbook-rar") => verify_rar(path),
+		Some("application/x-7z-compressed") => verify_7z(path),
+		Some("application/x-cb7") => verify_7z(path),
+		Some("application/x-tar") => verify_tar(path),
+		Some("application/x-cbt") => verify_tar(path),
+		Some("application/pdf") => verify_pdf(path),
+		None => Err(ProcessFileError::Unknown(format!(
+			"Unable to determine mime type for file: {:?}",
+			path
+		))),
+		_ => Err(ProcessFileError::UnsupportedFileType(
+			path.to_string_lossy().into_owned(),
+		)),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn page(width: u32, height: u32) -> MediaPage {
+		index_page(1, "page.jpg", None, 0, Some((width, height)))
+	}
+
+	#[test]
+	fn marks_pages_wider_than_most_as_spreads() {
+		// Note: the landscape cover of a landscape book isn't a spread.
+		let mut pages = vec![page(1600, 1200), page(1600, 1200), page(3200, 1200)];
+		mark_spreads(&mut pages);
+
+		assert!(!pages[0].is_spread);
+		assert!(!pages[1].is_spread);
+		assert!(pages[2].is_spread);
+
+		// Note: a landscape single page is only slightly wider than a portrait one.
+		let mut pages = vec![
+			page(1200, 1800),
+			page(1200, 1800),
+			page(1300, 1200),
+			page(2400, 1800),
+		];
+		mark_spreads(&mut pages);
+
+		assert!(!pages[0].is_spread);
+		assert!(!pages[2].is_spread);
+		assert!(pages[3].is_spread);
+	}
+
+	#[test]
+	fn can_process_full_comic_info() {
+		let comic_info = r#"<?xml version="1.0" encoding="utf-8"?>
+<ComicInfo xmlns:xsd="http://www.w3.org/2001/XMLSchema" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
+	<Title>The Chameleon Conspiracy</Title>
+	<Series>The Amazing Spider-Man</Series>
+	<Number>1.5</Number>
+	<Count>74</Count>
+	<Volume>2018</Volume>
+	<Year>2019</Year>
+	<Month>4</Month>
+	<Day>20</Day>
+	<Writer>Nick Spencer</Writer>
+	<Penciller>Ryan Ottley</Penciller>
+	<CoverArtist>Ryan Ottley</CoverArtist>
+	<StoryArc>The Chameleon Conspiracy</StoryArc>
+	<LanguageISO>en</LanguageISO>
+	<Manga>No</Manga>
+	<PageCount>2</PageCount>
+	<Pages>
+		<Page Image="0" Type="FrontCover" />
+		<Page Image="1" />
+	</Pages>
+</ComicInfo>"#;
+
+		let metadata = process_comic_info(comic_info.to_string())
+			.expect("Failed to process ComicInfo.xml");
+
+		assert_eq!(metadata.title.as_deref(), Some("The Chameleon Conspiracy"));
+		assert_eq!(metadata.number.as_deref(), Some("1.5"));
+		assert_eq!(metadata.count, Some(74));
+		assert_eq!(metadata.volume, Some(2018));
+		assert_eq!(
+			(metadata.year, metadata.month, metadata.day),
+			(Some(2019), Some(4), Some(20))
+		);
+		assert_eq!(metadata.writer.as_deref(), Some("Nick Spencer"));
+		assert_eq!(metadata.cover_artist.as_deref(), Some("Ryan Ottley"));
+		assert_eq!(
+			metadata.story_arc.as_deref(),
+			Some("The Chameleon Conspiracy")
+		);
+		assert_eq!(metadata.language_iso.as_deref(), Some("en"));
+		assert_eq!(metadata.manga.as_deref(), Some("No"));
+		assert_eq!(metadata.page_count, Some(2));
+		assert_eq!(metadata.inker, None);
+	}
+}
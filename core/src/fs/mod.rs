@@ -1,8 +1,15 @@
 pub mod checksum;
+pub mod comic_info;
 pub mod epub;
 pub mod image;
+pub mod image_folder;
 pub mod media_file;
+pub mod page_cache;
+pub mod page_order;
 pub mod pdf;
 pub mod rar;
 pub mod scanner;
+pub mod seven_zip;
+pub mod tar;
+pub mod watcher;
 pub mod zip;
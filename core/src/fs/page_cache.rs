@@ -0,0 +1,298 @@
+use std::{
+	fs::{self, Metadata},
+	path::{Path, PathBuf},
+	sync::atomic::{AtomicU64, Ordering},
+	time::SystemTime,
+};
+
+use filetime::FileTime;
+use rocket::tokio::task;
+use walkdir::WalkDir;
+
+use crate::{
+	config::{get_page_cache_dir, get_page_cache_size},
+	fs::image,
+	types::{
+		alias::ProcessFileResult, enums::ImageFormat, errors::ProcessFileError,
+		http::ImageResponse, query::ImageRequestParams,
+	},
+};
+
+// Note: the cache is a least recently used cache, where the modified time of each file
+// is used to track when it was last used. Cached pages are touched every time they are
+// read, and the oldest pages are evicted once the cache grows too large.
+
+/// The fraction of the maximum cache size the cache is shrunk to once it has grown too
+/// large, so that pages aren't evicted on every write.
+const EVICTION_TARGET: f64 = 0.9;
+
+const CACHE_FORMATS: [ImageFormat; 3] =
+	[ImageFormat::Webp, ImageFormat::Jpeg, ImageFormat::Png];
+
+/// The value of `CACHE_SIZE` before the cache has been measured.
+const UNKNOWN_SIZE: u64 = u64::MAX;
+
+/// The size of the cache in bytes, tracked as pages are written and removed so that the
+/// cache only has to be walked when it is first written to and when it is evicted.
+static CACHE_SIZE: AtomicU64 = AtomicU64::new(UNKNOWN_SIZE);
+
+/// Returns the paths, sizes and modified times of every cached page.
+fn get_cached_files(cache_dir: &Path) -> Vec<(PathBuf, u64, SystemTime)> {
+	WalkDir::new(cache_dir)
+		.into_iter()
+		.filter_map(|entry| entry.ok())
+		.filter_map(|entry| {
+			entry
+				.metadata()
+				.ok()
+				.filter(|metadata| metadata.is_file())
+				.map(|metadata| (entry.into_path(), metadata.len(), modified(&metadata)))
+		})
+		.collect()
+}
+
+/// Adds the given number of bytes to the tracked cache size, measuring the cache first if
+/// it hasn't been yet. Returns the new size.
+fn grow_cache_size(bytes: u64) -> u64 {
+	if CACHE_SIZE.load(Ordering::SeqCst) == UNKNOWN_SIZE {
+		let size = get_cached_files(&get_page_cache_dir())
+			.iter()
+			.map(|(_, size, _)| size)
+			.sum::<u64>();
+
+		// Note: the written page may or may not have been counted, depending on when the
+		// cache was walked. Either way, the size is corrected on the next eviction.
+		let _ = CACHE_SIZE.compare_exchange(
+			UNKNOWN_SIZE,
+			size.saturating_sub(bytes),
+			Ordering::SeqCst,
+			Ordering::SeqCst,
+		);
+	}
+
+	CACHE_SIZE.fetch_add(bytes, Ordering::SeqCst) + bytes
+}
+
+/// Removes the given number of bytes from the tracked cache size, if it is known.
+fn shrink_cache_size(bytes: u64) {
+	let _ = CACHE_SIZE.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |size| {
+		(size != UNKNOWN_SIZE).then(|| size.saturating_sub(bytes))
+	});
+}
+
+/// Returns the path of a cached page, without its extension. The extension depends on the
+/// format of the transformed page, which isn't always known before transforming it.
+fn get_variant_path(media_id: &str, page: i32, params: &ImageRequestParams) -> PathBuf {
+	let dimension = |value: Option<u32>| {
+		value
+			.map(|value| value.to_string())
+			.unwrap_or_else(|| "auto".to_string())
+	};
+
+	get_page_cache_dir().join(media_id).join(format!(
+		"{}_{}x{}_{}_q{}",
+		page,
+		dimension(params.width),
+		dimension(params.height),
+		params
+			.format
+			.map(|format| format.extension())
+			.unwrap_or("original"),
+		params
+			.quality
+			.map(|quality| quality.to_string())
+			.unwrap_or_else(|| "default".to_string()),
+	))
+}
+
+fn modified(metadata: &Metadata) -> SystemTime {
+	metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH)
+}
+
+/// Returns true if the media file has changed since the page was cached.
+fn is_stale(cached: &Metadata, media_path: &str) -> bool {
+	match Path::new(media_path).metadata() {
+		Ok(media_metadata) => modified(&media_metadata) > modified(cached),
+		Err(_) => true,
+	}
+}
+
+/// Gets a transformed page from the cache, if present. Stale pages are removed.
+pub fn get_cached_page(
+	media_id: &str,
+	media_path: &str,
+	page: i32,
+	params: &ImageRequestParams,
+) -> Option<ImageResponse> {
+	let variant_path = get_variant_path(media_id, page, params);
+
+	for format in CACHE_FORMATS {
+		let path = variant_path.with_extension(format.extension());
+
+		let metadata = match path.metadata() {
+			Ok(metadata) => metadata,
+			Err(_) => continue,
+		};
+
+		if is_stale(&metadata, media_path) {
+			log::trace!("Removing stale cached page: {:?}", path);
+			if fs::remove_file(&path).is_ok() {
+				shrink_cache_size(metadata.len());
+			}
+			return None;
+		}
+
+		if let Err(e) = filetime::set_file_mtime(&path, FileTime::now()) {
+			log::warn!("Failed to touch cached page {:?}: {}", path, e);
+		}
+
+		return match fs::read(&path) {
+			Ok(bytes) => Some((format.content_type(), bytes)),
+			Err(e) => {
+				log::error!("Failed to read cached page {:?}: {}", path, e);
+				None
+			},
+		};
+	}
+
+	None
+}
+
+/// Writes a transformed page to the cache, evicting the least recently used pages if the
+/// cache has grown too large. The size of the cache is tracked as pages are written, so
+/// the cache is only walked when it needs to be evicted.
+pub fn cache_page(
+	media_id: &str,
+	page: i32,
+	params: &ImageRequestParams,
+	image_response: &ImageResponse,
+) -> ProcessFileResult<()> {
+	let (content_type, bytes) = image_response;
+
+	let format = match ImageFormat::from_content_type(content_type) {
+		Some(format) => format,
+		None => return Ok(()),
+	};
+
+	let path =
+		get_variant_path(media_id, page, params).with_extension(format.extension());
+
+	if let Some(parent) = path.parent() {
+		fs::create_dir_all(parent)?;
+	}
+
+	// Note: a page may be written again, e.g. after it went stale, so its previous size
+	// is no longer part of the cache.
+	if let Ok(metadata) = path.metadata() {
+		shrink_cache_size(metadata.len());
+	}
+
+	fs::write(&path, bytes)?;
+
+	let max_size = get_page_cache_size();
+
+	if grow_cache_size(bytes.len() as u64) > max_size {
+		evict_pages(max_size);
+	}
+
+	Ok(())
+}
+
+/// Removes the least recently used pages from the cache until it is within the given
+/// size, in bytes.
+pub fn evict_pages(max_size: u64) {
+	let cache_dir = get_page_cache_dir();
+
+	let mut entries = get_cached_files(&cache_dir);
+
+	let mut total_size = entries.iter().map(|(_, size, _)| size).sum::<u64>();
+
+	if total_size <= max_size {
+		CACHE_SIZE.store(total_size, Ordering::SeqCst);
+		return;
+	}
+
+	let target_size = (max_size as f64 * EVICTION_TARGET) as u64;
+
+	entries.sort_by_key(|(_, _, modified)| *modified);
+
+	for (path, size, _) in entries {
+		if total_size <= target_size {
+			break;
+		}
+
+		match fs::remove_file(&path) {
+			Ok(_) => {
+				total_size -= size;
+
+				// Note: this only succeeds once the directory of the media is empty.
+				if let Some(parent) = path.parent().filter(|parent| *parent != cache_dir)
+				{
+					let _ = fs::remove_dir(parent);
+				}
+			},
+			Err(e) => log::error!("Failed to evict cached page {:?}: {}", path, e),
+		}
+	}
+
+	CACHE_SIZE.store(total_size, Ordering::SeqCst);
+
+	log::debug!(
+		"Evicted pages from the page cache, now {} bytes",
+		total_size
+	);
+}
+
+/// Gets a page transformed according to the request params, using the page cache when
+/// possible. When no transformation is requested, the page is returned as is and is not
+/// cached. Pages are read and transformed on a blocking thread, since both can take a
+/// while for large pages.
+pub async fn get_transformed_page<F>(
+	media_id: String,
+	media_path: String,
+	page: i32,
+	params: ImageRequestParams,
+	get_page: F,
+) -> ProcessFileResult<ImageResponse>
+where
+	F: FnOnce() -> ProcessFileResult<ImageResponse> + Send + 'static,
+{
+	task::spawn_blocking(move || {
+		transform_page(&media_id, &media_path, page, &params, get_page)
+	})
+	.await
+	.map_err(|e| ProcessFileError::Unknown(e.to_string()))?
+}
+
+fn transform_page<F>(
+	media_id: &str,
+	media_path: &str,
+	page: i32,
+	params: &ImageRequestParams,
+	get_page: F,
+) -> ProcessFileResult<ImageResponse>
+where
+	F: FnOnce() -> ProcessFileResult<ImageResponse>,
+{
+	if params.is_empty() {
+		return get_page();
+	}
+
+	if let Some(cached) = get_cached_page(media_id, media_path, page, params) {
+		log::trace!("Found cached page {} for media {}", page, media_id);
+		return Ok(cached);
+	}
+
+	let transformed = image::transform_image(get_page()?, params)?;
+
+	if let Err(e) = cache_page(media_id, page, params, &transformed) {
+		log::error!(
+			"Failed to cache page {} for media {}: {}",
+			page,
+			media_id,
+			e
+		);
+	}
+
+	Ok(transformed)
+}
@@ -0,0 +1,207 @@
+// Note: every archive handler should defer to this module to determine the order of its
+// pages, rather than sorting entry names itself, so that a page number always refers to
+// the same image regardless of the file format.
+
+use std::cmp::Ordering;
+
+use serde::Deserialize;
+
+pub const COMIC_INFO_FILE: &str = "ComicInfo.xml";
+
+/// A `<Page>` element from the `<Pages>` list of a ComicInfo.xml file.
+#[derive(Debug, Deserialize, PartialEq)]
+pub struct ComicInfoPage {
+	/// The index of the image this page refers to, relative to the naturally sorted images.
+	#[serde(rename = "Image")]
+	pub image: usize,
+	/// The type of the page. ex: "FrontCover", "Story" or "Deleted"
+	#[serde(rename = "Type")]
+	pub page_type: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ComicInfoPageList {
+	#[serde(rename = "Page", default)]
+	pages: Vec<ComicInfoPage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ComicInfoPages {
+	#[serde(rename = "Pages")]
+	pages: Option<ComicInfoPageList>,
+}
+
+/// Parses the `<Pages>` element of a ComicInfo.xml file, if present.
+pub fn parse_comic_info_pages(comic_info: &str) -> Option<Vec<ComicInfoPage>> {
+	if comic_info.is_empty() {
+		return None;
+	}
+
+	match serde_xml_rs::from_str::<ComicInfoPages>(comic_info) {
+		Ok(info) => info
+			.pages
+			.map(|list| list.pages)
+			.filter(|pages| !pages.is_empty()),
+		Err(e) => {
+			log::debug!("Failed to parse pages from ComicInfo.xml: {}", e);
+			None
+		},
+	}
+}
+
+/// Returns true if the archive entry is an OS artifact rather than actual content, e.g.
+/// resource forks in `__MACOSX` directories or hidden `._` files.
+pub fn is_hidden_entry(name: &str) -> bool {
+	name.split(['/', '\\'])
+		.any(|component| component == "__MACOSX" || component.starts_with('.'))
+}
+
+/// Compares two entry names naturally (e.g. `page2.jpg` comes before `page10.jpg`). Names
+/// are compared component by component, case insensitively, so that the nesting of
+/// directories within the archive does not affect the order of their contents.
+pub fn compare_page_names(a: &str, b: &str) -> Ordering {
+	let a = a.to_lowercase();
+	let b = b.to_lowercase();
+
+	let mut a_components = a.split(['/', '\\']).filter(|c| !c.is_empty());
+	let mut b_components = b.split(['/', '\\']).filter(|c| !c.is_empty());
+
+	loop {
+		match (a_components.next(), b_components.next()) {
+			(Some(a_component), Some(b_component)) => {
+				match alphanumeric_sort::compare_str(a_component, b_component) {
+					Ordering::Equal => continue,
+					ordering => return ordering,
+				}
+			},
+			(None, Some(_)) => return Ordering::Less,
+			(Some(_), None) => return Ordering::Greater,
+			(None, None) => return Ordering::Equal,
+		}
+	}
+}
+
+/// Orders the image entries of a media file into pages. Hidden entries are removed, the
+/// remaining entries are sorted naturally and, when a ComicInfo.xml file is provided
+/// with a `<Pages>` element, its ordering is applied on top of that. Deleted pages are
+/// dropped, and any images not referenced by ComicInfo.xml are kept at the end.
+pub fn order_pages(image_names: Vec<String>, comic_info: Option<&str>) -> Vec<String> {
+	let mut names = image_names
+		.into_iter()
+		.filter(|name| !is_hidden_entry(name))
+		.collect::<Vec<String>>();
+
+	names.sort_by(|a, b| compare_page_names(a, b));
+
+	let comic_info_pages = match comic_info.and_then(parse_comic_info_pages) {
+		Some(pages) => pages,
+		None => return names,
+	};
+
+	let mut referenced = vec![false; names.len()];
+	let mut ordered = Vec::with_capacity(names.len());
+
+	for page in comic_info_pages {
+		if page.image >= names.len() || referenced[page.image] {
+			log::debug!("Ignoring invalid or duplicate ComicInfo page: {:?}", page);
+			continue;
+		}
+
+		referenced[page.image] = true;
+
+		let is_deleted = page
+			.page_type
+			.map(|page_type| page_type.eq_ignore_ascii_case("Deleted"))
+			.unwrap_or(false);
+
+		if !is_deleted {
+			ordered.push(names[page.image].clone());
+		}
+	}
+
+	ordered.extend(
+		names
+			.into_iter()
+			.zip(referenced)
+			.filter(|(_, referenced)| !referenced)
+			.map(|(name, _)| name),
+	);
+
+	ordered
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn to_strings(names: &[&str]) -> Vec<String> {
+		names.iter().map(|name| name.to_string()).collect()
+	}
+
+	#[test]
+	fn sorts_pages_naturally() {
+		let ordered = order_pages(
+			to_strings(&["page10.jpg", "page2.jpg", "Page1.jpg", "page11.jpg"]),
+			None,
+		);
+
+		assert_eq!(
+			ordered,
+			to_strings(&["Page1.jpg", "page2.jpg", "page10.jpg", "page11.jpg"])
+		);
+	}
+
+	#[test]
+	fn sorts_nested_directories() {
+		let ordered = order_pages(
+			to_strings(&[
+				"Chapter 10/001.jpg",
+				"Chapter 2/002.jpg",
+				"Chapter 2/001.jpg",
+				"__MACOSX/Chapter 2/._001.jpg",
+				".DS_Store",
+			]),
+			None,
+		);
+
+		assert_eq!(
+			ordered,
+			to_strings(&[
+				"Chapter 2/001.jpg",
+				"Chapter 2/002.jpg",
+				"Chapter 10/001.jpg"
+			])
+		);
+	}
+
+	#[test]
+	fn honours_comic_info_pages() {
+		let comic_info = r#"<?xml version="1.0"?>
+<ComicInfo>
+	<Series>Test</Series>
+	<Pages>
+		<Page Image="2" Type="FrontCover" />
+		<Page Image="0" />
+		<Page Image="1" Type="Deleted" />
+	</Pages>
+</ComicInfo>"#;
+
+		let ordered = order_pages(
+			to_strings(&["3.jpg", "1.jpg", "2.jpg", "4.jpg"]),
+			Some(comic_info),
+		);
+
+		assert_eq!(ordered, to_strings(&["3.jpg", "1.jpg", "4.jpg"]));
+	}
+
+	#[test]
+	fn ignores_comic_info_without_pages() {
+		let comic_info = "<ComicInfo><Series>Test</Series></ComicInfo>";
+
+		assert!(parse_comic_info_pages(comic_info).is_none());
+		assert_eq!(
+			order_pages(to_strings(&["2.jpg", "1.jpg"]), Some(comic_info)),
+			to_strings(&["1.jpg", "2.jpg"])
+		);
+	}
+}
@@ -1,5 +1,559 @@
-use crate::types::{alias::ProcessFileResult, http};
+use std::{
+	io::{self, Read},
+	path::Path,
+};
 
-pub fn get_pdf_page(_file: &str, _page: usize) -> ProcessFileResult<http::ImageResponse> {
-	unimplemented!()
+use flate2::read::ZlibDecoder;
+use image::{DynamicImage, GrayImage, ImageOutputFormat, RgbImage};
+use lopdf::{Dictionary, Document, Object, ObjectId};
+use rocket::http::ContentType;
+
+use crate::types::{
+	alias::ProcessFileResult,
+	errors::ProcessFileError,
+	http,
+	models::media::{MediaMetadata, ProcessedMediaFile},
+};
+
+use super::checksum;
+
+fn load_pdf(path: &Path) -> ProcessFileResult<Document> {
+	Document::load(path).map_err(|e| {
+		log::error!("Failed to open pdf {:?}: {}", path, e);
+		ProcessFileError::PdfReadError(e)
+	})
+}
+
+/// Verifies the integrity of a pdf file by loading the entire document, then decoding the
+/// content streams and images of every page. PDFs don't store checksums, so this ensures
+/// each stream can be decoded. Only FlateDecode streams and DCTDecode (jpeg) images are
+/// decoded, streams using any other filter are only checked to be parsable.
+pub fn verify_pdf(path: &Path) -> ProcessFileResult<()> {
+	let document = load_pdf(path)?;
+	let pages = document.get_pages();
+
+	if pages.is_empty() {
+		return Err(ProcessFileError::NoImageError);
+	}
+
+	for (page, page_id) in pages {
+		let content_streams = document
+			.get_page_contents(page_id)
+			.into_iter()
+			.map(|id| document.get_object(id).and_then(Object::as_stream))
+			.collect::<Result<Vec<&lopdf::Stream>, lopdf::Error>>()?;
+
+		for stream in content_streams
+			.into_iter()
+			.chain(get_page_images(&document, page_id))
+		{
+			verify_stream(stream).map_err(|e| {
+				ProcessFileError::Unknown(format!(
+					"Page {} of {:?} could not be decoded: {}",
+					page, path, e
+				))
+			})?;
+		}
+	}
+
+	Ok(())
+}
+
+/// Decodes a stream of a pdf file, discarding the output.
+fn verify_stream(stream: &lopdf::Stream) -> ProcessFileResult<()> {
+	let filters = stream.filters().unwrap_or_default();
+
+	match filters
+		.iter()
+		.map(String::as_str)
+		.collect::<Vec<&str>>()
+		.as_slice()
+	{
+		["FlateDecode"] => {
+			io::copy(
+				&mut ZlibDecoder::new(stream.content.as_slice()),
+				&mut io::sink(),
+			)?;
+		},
+		["DCTDecode"] => {
+			image::load_from_memory_with_format(
+				&stream.content,
+				image::ImageFormat::Jpeg,
+			)?;
+		},
+		_ => {},
+	}
+
+	Ok(())
+}
+
+/// Calls `checksum::digest` to attempt generating a checksum for the pdf file. PDFs
+/// don't have entries to sample like archives do, so the sample size is based on the
+/// size of the file itself.
+pub fn digest_pdf(path: &str) -> Option<String> {
+	let size = match checksum::sample_size(path) {
+		Ok(size) => size,
+		Err(e) => {
+			log::error!(
+				"Failed to calculate checksum sample size for pdf {}: {}",
+				path,
+				e
+			);
+			return None;
+		},
+	};
+
+	match checksum::digest(path, size) {
+		Ok(digest) => Some(digest),
+		Err(e) => {
+			log::error!(
+				"Failed to digest pdf {}, unable to create checksum: {}",
+				path,
+				e
+			);
+			None
+		},
+	}
+}
+
+/// Reads a text entry from the Info dictionary of a PDF. PDF text strings are either
+/// PDFDocEncoded or UTF-16BE with a BOM, so both are handled here.
+fn get_info_string(document: &Document, info: &Dictionary, key: &[u8]) -> Option<String> {
+	let bytes = info
+		.get(key)
+		.and_then(|obj| document.dereference(obj))
+		.and_then(|(_, obj)| obj.as_str())
+		.ok()?;
+
+	let value = if bytes.starts_with(&[0xFE, 0xFF]) {
+		let utf16 = bytes[2..]
+			.chunks(2)
+			.filter(|chunk| chunk.len() == 2)
+			.map(|chunk| u16::from_be_bytes([chunk[0], chunk[1]]))
+			.collect::<Vec<u16>>();
+
+		String::from_utf16_lossy(&utf16)
+	} else {
+		String::from_utf8_lossy(bytes).to_string()
+	};
+
+	let value = value.trim().to_string();
+
+	if value.is_empty() {
+		None
+	} else {
+		Some(value)
+	}
+}
+
+/// Builds a `MediaMetadata` from the Info dictionary of the PDF, if one is present.
+fn get_pdf_metadata(document: &Document, pages: usize) -> Option<MediaMetadata> {
+	let info = document
+		.trailer
+		.get(b"Info")
+		.and_then(|obj| document.dereference(obj))
+		.and_then(|(_, obj)| obj.as_dict())
+		.ok()?;
+
+	Some(MediaMetadata {
+		title: get_info_string(document, info, b"Title"),
+		writer: get_info_string(document, info, b"Author"),
+		summary: get_info_string(document, info, b"Subject"),
+		genre: get_info_string(document, info, b"Keywords"),
+		page_count: Some(pages),
+		..MediaMetadata::default()
+	})
+}
+
+/// Processes a pdf file in its entirety, includes: metadata (from the Info dictionary),
+/// page count, and the generated checksum for the file.
+pub fn process_pdf(path: &Path) -> ProcessFileResult<ProcessedMediaFile> {
+	log::info!("Processing PDF: {}", path.display());
+
+	let document = load_pdf(path)?;
+
+	let pages = document.get_pages().len();
+
+	Ok(ProcessedMediaFile {
+		thumbnail_path: None,
+		path: path.to_path_buf(),
+		checksum: digest_pdf(&path.to_string_lossy()),
+		metadata: get_pdf_metadata(&document, pages),
+		pages: pages as i32,
+	})
+}
+
+/// Collects the image XObjects referenced by the resources of a page. Resources may
+/// be inherited from a parent node in the page tree, so the referenced resource
+/// dictionaries are checked as well.
+fn get_page_images(document: &Document, page_id: ObjectId) -> Vec<&lopdf::Stream> {
+	let (resource_dict, resource_ids) = document.get_page_resources(page_id);
+
+	let mut resources = resource_dict.into_iter().collect::<Vec<&Dictionary>>();
+	resources.extend(
+		resource_ids
+			.into_iter()
+			.filter_map(|id| document.get_dictionary(id).ok()),
+	);
+
+	resources
+		.into_iter()
+		.filter_map(|resource| {
+			resource
+				.get(b"XObject")
+				.and_then(|obj| document.dereference(obj))
+				.and_then(|(_, obj)| obj.as_dict())
+				.ok()
+		})
+		.flat_map(|xobjects| xobjects.iter())
+		.filter_map(|(_, obj)| document.dereference(obj).ok())
+		.filter_map(|(_, obj)| obj.as_stream().ok())
+		.filter(|stream| {
+			stream
+				.dict
+				.get(b"Subtype")
+				.and_then(Object::as_name_str)
+				.map(|subtype| subtype == "Image")
+				.unwrap_or(false)
+		})
+		.collect()
+}
+
+fn get_image_dimension(stream: &lopdf::Stream, key: &[u8]) -> u32 {
+	stream.dict.get(key).and_then(Object::as_i64).unwrap_or(0) as u32
+}
+
+/// Returns the number of color components of a color space, resolving indirect color
+/// spaces and the component count (`/N`) of ICC based ones. Returns `None` for color
+/// spaces which aren't supported, e.g. indexed or CMYK color spaces.
+fn get_color_components(document: &Document, color_space: &Object) -> Option<u32> {
+	let (_, color_space) = document.dereference(color_space).ok()?;
+
+	if let Ok(name) = color_space.as_name_str() {
+		return match name {
+			"DeviceRGB" | "CalRGB" => Some(3),
+			"DeviceGray" | "CalGray" => Some(1),
+			_ => None,
+		};
+	}
+
+	let array = color_space.as_array().ok()?;
+	let family = array.first().and_then(|obj| obj.as_name_str().ok())?;
+
+	match family {
+		"CalRGB" => Some(3),
+		"CalGray" => Some(1),
+		"ICCBased" => {
+			let (_, profile) = document.dereference(array.get(1)?).ok()?;
+			let components = profile.as_stream().ok()?.dict.get(b"N").ok()?;
+
+			match components.as_i64().ok()? {
+				1 => Some(1),
+				3 => Some(3),
+				_ => None,
+			}
+		},
+		_ => None,
+	}
+}
+
+/// Returns the `/DecodeParms` of the FlateDecode filter of a stream. When the stream has
+/// several filters, the parameters are an array holding those of each filter.
+fn get_flate_params<'a>(
+	document: &'a Document,
+	stream: &'a lopdf::Stream,
+) -> Option<&'a Dictionary> {
+	let (_, params) = document
+		.dereference(stream.dict.get(b"DecodeParms").ok()?)
+		.ok()?;
+
+	match params {
+		Object::Array(params) => params
+			.iter()
+			.filter_map(|obj| document.dereference(obj).ok())
+			.find_map(|(_, obj)| obj.as_dict().ok()),
+		params => params.as_dict().ok(),
+	}
+}
+
+/// Reverses the PNG predictors (`/Predictor` 10 to 15) applied to the rows of an 8 bit
+/// image before it was compressed. Each row starts with a byte holding the type of
+/// filter used for that row.
+fn apply_png_predictor(
+	data: &[u8],
+	colors: usize,
+	columns: usize,
+) -> ProcessFileResult<Vec<u8>> {
+	let bytes_per_pixel = colors.max(1);
+	let row_len = bytes_per_pixel * columns;
+
+	let mut output = Vec::with_capacity(data.len());
+	let mut previous = vec![0u8; row_len];
+
+	for chunk in data.chunks(row_len + 1) {
+		if chunk.len() != row_len + 1 {
+			return Err(ProcessFileError::NoImageError);
+		}
+
+		let (filter, row) = (chunk[0], &chunk[1..]);
+		let mut current = vec![0u8; row_len];
+
+		for i in 0..row_len {
+			let left = if i >= bytes_per_pixel {
+				current[i - bytes_per_pixel]
+			} else {
+				0
+			};
+			let up = previous[i];
+			let up_left = if i >= bytes_per_pixel {
+				previous[i - bytes_per_pixel]
+			} else {
+				0
+			};
+
+			let prediction = match filter {
+				0 => 0,
+				1 => left,
+				2 => up,
+				3 => ((left as u16 + up as u16) / 2) as u8,
+				4 => {
+					let estimate = left as i16 + up as i16 - up_left as i16;
+					let (a, b, c) = (
+						(estimate - left as i16).abs(),
+						(estimate - up as i16).abs(),
+						(estimate - up_left as i16).abs(),
+					);
+
+					if a <= b && a <= c {
+						left
+					} else if b <= c {
+						up
+					} else {
+						up_left
+					}
+				},
+				_ => {
+					return Err(ProcessFileError::UnsupportedFileType(format!(
+						"PDF images using the PNG filter {} are not supported",
+						filter
+					)))
+				},
+			};
+
+			current[i] = row[i].wrapping_add(prediction);
+		}
+
+		output.extend_from_slice(&current);
+		previous = current;
+	}
+
+	Ok(output)
+}
+
+/// Reverses the TIFF predictor (`/Predictor` 2) applied to an 8 bit image, where each
+/// component is stored as the difference with the same component of the previous pixel.
+fn apply_tiff_predictor(data: &mut [u8], colors: usize, columns: usize) {
+	let row_len = colors * columns;
+
+	for row in data.chunks_mut(row_len) {
+		for i in colors..row.len() {
+			row[i] = row[i].wrapping_add(row[i - colors]);
+		}
+	}
+}
+
+/// Decodes a raw (FlateDecode) image stream into a PNG, reversing the predictor it was
+/// encoded with, if any. Only 8 bit RGB and grayscale color spaces (including ICC based
+/// ones) are supported, which covers the vast majority of scanned PDFs.
+fn flate_image_to_png(
+	document: &Document,
+	stream: &lopdf::Stream,
+) -> ProcessFileResult<Vec<u8>> {
+	let width = get_image_dimension(stream, b"Width");
+	let height = get_image_dimension(stream, b"Height");
+	let bits_per_component = stream
+		.dict
+		.get(b"BitsPerComponent")
+		.and_then(Object::as_i64)
+		.unwrap_or(8);
+
+	if bits_per_component != 8 {
+		return Err(ProcessFileError::UnsupportedFileType(format!(
+			"PDF images with {} bits per component are not supported",
+			bits_per_component
+		)));
+	}
+
+	let color_space = stream.dict.get(b"ColorSpace").map_err(|_| {
+		ProcessFileError::UnsupportedFileType(
+			"PDF images without a color space are not supported".to_string(),
+		)
+	})?;
+	let colors = get_color_components(document, color_space).ok_or_else(|| {
+		ProcessFileError::UnsupportedFileType(format!(
+			"PDF images with the color space {:?} are not supported",
+			color_space
+		))
+	})?;
+
+	let mut raw = Vec::new();
+	ZlibDecoder::new(stream.content.as_slice()).read_to_end(&mut raw)?;
+
+	if let Some(params) = get_flate_params(document, stream) {
+		let predictor = params
+			.get(b"Predictor")
+			.and_then(Object::as_i64)
+			.unwrap_or(1);
+		let columns = params
+			.get(b"Columns")
+			.and_then(Object::as_i64)
+			.unwrap_or(width as i64) as usize;
+
+		match predictor {
+			1 => {},
+			2 => apply_tiff_predictor(&mut raw, colors as usize, columns),
+			10..=15 => raw = apply_png_predictor(&raw, colors as usize, columns)?,
+			_ => {
+				return Err(ProcessFileError::UnsupportedFileType(format!(
+					"PDF images using the predictor {} are not supported",
+					predictor
+				)))
+			},
+		}
+	}
+
+	let image = match colors {
+		3 => RgbImage::from_raw(width, height, raw).map(DynamicImage::ImageRgb8),
+		_ => GrayImage::from_raw(width, height, raw).map(DynamicImage::ImageLuma8),
+	}
+	.ok_or(ProcessFileError::NoImageError)?;
+
+	let mut buf = Vec::new();
+	image.write_to(&mut std::io::Cursor::new(&mut buf), ImageOutputFormat::Png)?;
+
+	Ok(buf)
+}
+
+// TODO: pages which are not image-only (i.e. text or vector content) require an actual
+// PDF renderer. For now, only the embedded image of each page is extracted, which covers
+// scanned PDFs.
+/// Get an image from a pdf file by index (page). The largest image embedded in the page
+/// is returned, since scans will sometimes contain smaller overlays (e.g. OCR artifacts).
+pub fn get_pdf_page(file: &str, page: i32) -> ProcessFileResult<http::ImageResponse> {
+	let document = load_pdf(Path::new(file))?;
+
+	let page_id = document
+		.get_pages()
+		.get(&(page as u32))
+		.cloned()
+		.ok_or_else(|| {
+			log::error!("Page {} does not exist in pdf file {}", page, file);
+			ProcessFileError::NoImageError
+		})?;
+
+	let image = get_page_images(&document, page_id)
+		.into_iter()
+		.max_by_key(|stream| {
+			get_image_dimension(stream, b"Width") as u64
+				* get_image_dimension(stream, b"Height") as u64
+		})
+		.ok_or_else(|| {
+			log::error!(
+				"Could not find image for page {} in pdf file {}",
+				page,
+				file
+			);
+			ProcessFileError::NoImageError
+		})?;
+
+	let filters = image.filters().unwrap_or_default();
+
+	match filters
+		.iter()
+		.map(String::as_str)
+		.collect::<Vec<&str>>()
+		.as_slice()
+	{
+		["DCTDecode"] => Ok((ContentType::JPEG, image.content.clone())),
+		["FlateDecode"] => Ok((ContentType::PNG, flate_image_to_png(&document, image)?)),
+		other => Err(ProcessFileError::UnsupportedFileType(format!(
+			"PDF images encoded with {:?} are not supported",
+			other
+		))),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use std::path::PathBuf;
+
+	fn fixture() -> PathBuf {
+		PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+			.join("tests")
+			.join("fixtures")
+			.join("fixture.pdf")
+	}
+
+	#[test]
+	fn reverses_png_predictors() {
+		// Note: two rows of two RGB pixels, using the Sub and Up filters.
+		let encoded = [
+			1, 10, 20, 30, 5, 5, 5, //
+			2, 1, 1, 1, 1, 1, 1,
+		];
+
+		assert_eq!(
+			apply_png_predictor(&encoded, 3, 2).unwrap(),
+			vec![10, 20, 30, 15, 25, 35, 11, 21, 31, 16, 26, 36]
+		);
+		assert!(apply_png_predictor(&encoded[..6], 3, 2).is_err());
+	}
+
+	#[test]
+	fn reverses_tiff_predictor() {
+		let mut data = [10, 5, 5, 20, 1, 1];
+
+		apply_tiff_predictor(&mut data, 1, 3);
+
+		assert_eq!(data, [10, 15, 20, 20, 21, 22]);
+	}
+
+	#[test]
+	fn can_process_pdf() {
+		let processed = process_pdf(&fixture()).unwrap();
+
+		assert_eq!(processed.pages, 2);
+		assert!(processed.checksum.is_some());
+		assert_eq!(
+			processed.metadata.and_then(|metadata| metadata.title),
+			Some("Fixture".to_string())
+		);
+		assert!(verify_pdf(&fixture()).is_ok());
+	}
+
+	#[test]
+	fn can_get_pdf_page() {
+		let file = fixture().to_string_lossy().to_string();
+
+		// Note: the first page embeds a jpeg (DCTDecode) image, which is served as is.
+		let (content_type, bytes) = get_pdf_page(&file, 1).unwrap();
+		let page = image::load_from_memory(&bytes).unwrap();
+
+		assert_eq!(content_type, ContentType::JPEG);
+		assert_eq!((page.width(), page.height()), (8, 6));
+
+		// Note: the second page embeds a raw (FlateDecode) image using a PNG predictor,
+		// which is decoded into a png.
+		let (content_type, bytes) = get_pdf_page(&file, 2).unwrap();
+		let page = image::load_from_memory(&bytes).unwrap().to_rgb8();
+
+		assert_eq!(content_type, ContentType::PNG);
+		assert_eq!(page.dimensions(), (6, 8));
+		assert_eq!(page.get_pixel(0, 0).0, [200, 30, 30]);
+		assert_eq!(page.get_pixel(5, 7).0, [200, 65, 30]);
+
+		assert!(get_pdf_page(&file, 3).is_err());
+	}
 }
@@ -1,83 +1,193 @@
-use std::path::{Path, PathBuf};
+use std::{
+	collections::HashMap,
+	path::{Path, PathBuf},
+	time::{SystemTime, UNIX_EPOCH},
+};
 
-use rocket::http::ContentType;
 use unrar::archive::Entry;
 
 use crate::{
 	config::{self, stump_in_docker},
-	fs::{
-		checksum::{DIGEST_SAMPLE_COUNT, DIGEST_SAMPLE_SIZE},
-		media_file::{self, IsImage},
-	},
+	fs::media_file::{self, IsImage},
 	types::{
 		alias::ProcessFileResult,
 		errors::ProcessFileError,
 		http,
-		models::{library::LibraryOptions, media::ProcessedMediaFile},
+		models::{library::LibraryOptions, media::ProcessedMediaFile, page::MediaPage},
 	},
 };
 
 // FIXME: terrible error handling in this file... needs a total rework honestly.
 
-use super::{checksum, zip};
+use super::{
+	checksum, image,
+	page_order::{self, COMIC_INFO_FILE},
+	watcher, zip,
+};
 
 impl IsImage for Entry {
 	fn is_image(&self) -> bool {
 		if self.is_file() {
-			let file_name = self.filename.as_path().to_string_lossy().to_lowercase();
-			return file_name.ends_with(".jpg")
-				|| file_name.ends_with(".jpeg")
-				|| file_name.ends_with(".png");
+			let content_type =
+				media_file::guess_content_type(&self.filename.to_string_lossy());
+
+			// Note: this must match the other archive formats, so that converted files
+			// keep the same pages.
+			return content_type.is_jpeg()
+				|| content_type.is_png()
+				|| content_type.is_webp()
+				|| content_type.is_svg()
+				|| content_type.is_tiff();
 		}
 
 		false
 	}
 }
 
+/// Removes the original file once it has been converted. Unless `hard_delete` is true, the
+/// file is moved to the trash directory instead, so that it may be recovered.
+pub fn remove_converted_file(path: &Path, hard_delete: bool) -> ProcessFileResult<()> {
+	if hard_delete {
+		log::debug!("Deleting converted file {:?}", path);
+		std::fs::remove_file(path)?;
+
+		return Ok(());
+	}
+
+	let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+	let trash_dir = config::get_trash_dir();
+
+	let mut trash_path = trash_dir.join(file_name.as_ref());
+	if trash_path.exists() {
+		let timestamp = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.map(|duration| duration.as_secs())
+			.unwrap_or_default();
+
+		trash_path = trash_dir.join(format!("{}-{}", timestamp, file_name));
+	}
+
+	log::debug!("Moving converted file {:?} to {:?}", path, trash_path);
+
+	// Note: a rename fails when the trash directory is on another file system (e.g. a
+	// docker volume), in which case the file is copied over instead.
+	if std::fs::rename(path, &trash_path).is_err() {
+		std::fs::copy(path, &trash_path)?;
+		std::fs::remove_file(path)?;
+	}
+
+	Ok(())
+}
+
+/// Checks that the zip file converted from a rar file opens, and that it has the same
+/// number of images as the rar file.
+fn verify_conversion(zip_path: &Path, expected_images: usize) -> ProcessFileResult<()> {
+	let images = zip::count_zip_images(zip_path).map_err(|e| {
+		ProcessFileError::ConversionVerificationError(format!(
+			"{:?} could not be read: {}",
+			zip_path, e
+		))
+	})?;
+
+	if images != expected_images {
+		return Err(ProcessFileError::ConversionVerificationError(format!(
+			"{:?} has {} images, expected {}",
+			zip_path, images, expected_images
+		)));
+	}
+
+	Ok(())
+}
+
+/// Returns the paths a conversion of the rar file writes to within its library, i.e. the
+/// rar file itself and the zip file created alongside it. See `watcher::suppress_changes`.
+pub fn get_conversion_paths(path: &Path) -> Vec<PathBuf> {
+	let zip_path = match (
+		path.file_stem().and_then(|stem| stem.to_str()),
+		path.extension().and_then(|ext| ext.to_str()),
+	) {
+		(Some(name), Some(original_ext)) => Some(zip::get_zip_path(
+			name,
+			original_ext,
+			path.parent().unwrap_or(Path::new("/")),
+		)),
+		_ => None,
+	};
+
+	std::iter::once(path.to_path_buf())
+		.chain(zip_path)
+		.collect()
+}
+
+/// Converts a rar file to a zip file alongside it, and verifies the zip file. The rar file
+/// is kept, so that the caller may remove it with [`remove_converted_file`] once nothing
+/// refers to it anymore. If anything fails, the zip file is removed.
 pub fn convert_rar_to_zip(path: &Path) -> Result<PathBuf, ProcessFileError> {
 	log::debug!("Converting {:?} to zip format.", &path);
 
 	let archive = unrar::Archive::new(path)?;
 
 	let parent = path.parent().unwrap_or(Path::new("/"));
-	// TODO: remove *unsafe* unwraps
-	let dir_name = path.file_stem().unwrap().to_str().unwrap();
-	let original_ext = path.extension().unwrap().to_str().unwrap();
-	let unpacked_path = config::get_cache_dir().join(dir_name);
+	let (dir_name, original_ext) = match (
+		path.file_stem().and_then(|stem| stem.to_str()),
+		path.extension().and_then(|ext| ext.to_str()),
+	) {
+		(Some(dir_name), Some(original_ext)) => (dir_name, original_ext),
+		_ => {
+			return Err(ProcessFileError::UnsupportedFileType(format!(
+				"Cannot convert {:?}, its name is not valid",
+				path
+			)))
+		},
+	};
+	// Note: conversions may run concurrently, and files in different series often share a
+	// name, so each conversion unpacks into its own directory.
+	let id = cuid::cuid().map_err(|e| ProcessFileError::Unknown(e.to_string()))?;
+	let unpacked_path = config::get_cache_dir().join(format!("rar-{}", id));
+
+	// Note: the zip file is created alongside the rar file, so an existing file with the
+	// same name would otherwise be overwritten.
+	let zip_path = zip::get_zip_path(dir_name, original_ext, parent);
+	if zip_path.exists() {
+		return Err(ProcessFileError::FileIoError(std::io::Error::new(
+			std::io::ErrorKind::AlreadyExists,
+			format!("Cannot convert {:?}, {:?} already exists", path, zip_path),
+		)));
+	}
+
+	// Note: only the names of the entries are needed to verify the zip file, so no entry
+	// is read here.
+	let (image_names, _) = list_rar_entries(path)?;
 
 	log::trace!("Extracting rar contents to: {:?}", &unpacked_path);
 
 	// TODO: fix this mess...
-	archive
+	let extracted = archive
 		.extract_to(&unpacked_path)
 		.map_err(|e| {
 			log::error!("Failed to open archive: {:?}", e.to_string());
 
 			ProcessFileError::RarOpenError
-		})?
-		.process()
-		.map_err(|e| {
-			log::error!("Failed to extract archive: {:?}", e.to_string());
-
-			ProcessFileError::RarExtractError(e.to_string())
-		})?;
+		})
+		.and_then(|archive| {
+			archive.process().map_err(|e| {
+				log::error!("Failed to extract archive: {:?}", e.to_string());
+
+				ProcessFileError::RarExtractError(e.to_string())
+			})
+		});
+
+	let result = extracted
+		.and_then(|_| {
+			zip::create_zip(&unpacked_path, dir_name, original_ext, parent)
+				.map_err(ProcessFileError::from)
+		})
+		.and_then(|zip_path| {
+			verify_conversion(&zip_path, image_names.len())?;
+
+			Ok(zip_path)
+		});
 
-	let zip_path = zip::create_zip(&unpacked_path, dir_name, original_ext, parent)?;
-
-	// Note: this will put the file in the 'trash' according to the user's platform.
-	// Rather than hard deleting it, I figured this would be desirable.
-	// This error won't be 'fatal' in that it won't cause an error to be returned.
-	// TODO: maybe persist a log here? Or make more compliacated return?
-	// something like ConvertResult { ConvertedMoveFailed, etc. }
-	if let Err(err) = trash::delete(path) {
-		log::warn!(
-			"Failed to delete converted rar file {:?}: {:?}",
-			path,
-			err.to_string()
-		);
-	}
-
-	// TODO: same as above, except this is a HARD delete
 	// TODO: maybe check that this path isn't in a pre-defined list of important paths?
 	if let Err(err) = std::fs::remove_dir_all(&unpacked_path) {
 		log::warn!(
@@ -87,7 +197,15 @@ pub fn convert_rar_to_zip(path: &Path) -> Result<PathBuf, ProcessFileError> {
 		);
 	}
 
-	Ok(zip_path)
+	if result.is_err() && zip_path.exists() {
+		log::debug!("Removing unverified zip file {:?}", zip_path);
+
+		if let Err(err) = std::fs::remove_file(&zip_path) {
+			log::error!("Failed to remove zip file {:?}: {}", zip_path, err);
+		}
+	}
+
+	result
 }
 
 // TODO: fix error handling after rar changes
@@ -99,11 +217,26 @@ pub fn process_rar(
 	options: &LibraryOptions,
 ) -> ProcessFileResult<ProcessedMediaFile> {
 	if options.convert_rar_to_zip {
+		let _suppressed = watcher::suppress_changes(get_conversion_paths(path));
 		let new_path = convert_rar_to_zip(path)?;
 
 		log::trace!("Using `process_zip` with converted rar.");
 
-		return zip::process_zip(&new_path);
+		let processed = match zip::process_zip(&new_path) {
+			Ok(processed) => processed,
+			Err(e) => {
+				// Note: the rar file is kept, so the zip file would otherwise be picked up
+				// as a duplicate on the next scan.
+				if let Err(err) = std::fs::remove_file(&new_path) {
+					log::error!("Failed to remove zip file {:?}: {}", new_path, err);
+				}
+
+				return Err(e);
+			},
+		};
+		remove_converted_file(path, options.hard_delete_conversions)?;
+
+		return Ok(processed);
 	}
 
 	if stump_in_docker() {
@@ -115,79 +248,242 @@ pub fn process_rar(
 	info!("Processing Rar (new): {}", path.display());
 
 	let path_str = path.to_string_lossy().to_string();
-	let archive = unrar::Archive::new(&path)?;
-
-	let mut pages = 0;
-
-	#[allow(unused_mut)]
-	let mut metadata_buf = Vec::<u8>::new();
 
 	let checksum = digest_rar(&path_str);
 
-	match archive.list_extract() {
-		Ok(open_archive) => {
-			for entry in open_archive {
-				match entry {
-					Ok(e) => {
-						// https://github.com/aaronleopold/unrar.rs/tree/aleopold--read-bytes
-						let filename = e.filename.to_string_lossy().to_string();
-
-						if filename.eq("ComicInfo.xml") {
-							// FIXME: This was segfaulting in Docker. I have a feeling it is because
-							// of my subpar implementation of the `read_bytes`.
-							// metadata_buf = match e.read_bytes() {
-							// 	Ok(b) => b,
-							// 	Err(_e) => {
-							// 		// error!("Error reading metadata: {}", e);
-							// 		// todo!()
-							// 		vec![]
-							// 	},
-							// }
-						} else {
-							pages += 1;
-						}
-					},
-					Err(_e) => return Err(ProcessFileError::RarReadError),
-				}
-			}
-		},
-		Err(_e) => return Err(ProcessFileError::RarOpenError),
-	};
+	let (image_names, comic_info) = get_rar_entries(path)?;
+
+	let pages = page_order::order_pages(image_names, comic_info.as_deref()).len() as i32;
 
 	Ok(ProcessedMediaFile {
 		thumbnail_path: None,
 		path: path.to_path_buf(),
 		checksum,
-		metadata: media_file::process_comic_info(
-			std::str::from_utf8(&metadata_buf)?.to_owned(),
-		),
+		metadata: comic_info.and_then(media_file::process_comic_info),
 		pages,
 	})
 }
 
-// FIXME: this is a temporary work around for the issue wonderful people on Discord
-// discovered.
-pub fn rar_sample(file: &str) -> Result<u64, ProcessFileError> {
-	log::debug!("Calculating checksum sample size for: {}", file);
+/// Lists the names of the image entries of a rar file, along with whether it contains a
+/// ComicInfo.xml file. No entry is read.
+fn list_rar_entries(path: &Path) -> ProcessFileResult<(Vec<String>, bool)> {
+	let archive = unrar::Archive::new(path)?;
+
+	let mut image_names = Vec::new();
+	let mut has_comic_info = false;
+
+	for entry in archive.list().map_err(|e| {
+		log::error!("Failed to open rar archive: {:?}", e);
 
-	let file = std::fs::File::open(file)?;
+		ProcessFileError::RarOpenError
+	})? {
+		let entry = entry.map_err(|e| {
+			log::error!("Failed to read rar entry: {:?}", e);
 
-	let file_size = file.metadata()?.len();
-	let threshold = DIGEST_SAMPLE_SIZE * DIGEST_SAMPLE_COUNT;
+			ProcessFileError::RarReadError
+		})?;
 
-	if file_size < threshold {
-		return Ok(file_size);
+		if entry.filename.as_path() == Path::new(COMIC_INFO_FILE) {
+			has_comic_info = true;
+		} else if entry.is_image() {
+			image_names.push(entry.filename.to_string_lossy().to_string());
+		}
 	}
 
-	let division = file_size / threshold;
+	Ok((image_names, has_comic_info))
+}
+
+/// Collects the names of the image entries of a rar file, along with the contents of its
+/// ComicInfo.xml file, if present.
+fn get_rar_entries(path: &Path) -> ProcessFileResult<(Vec<String>, Option<String>)> {
+	let (image_names, has_comic_info) = list_rar_entries(path)?;
 
-	// if the file size is 4x the threshold, we'll take up to the threshold.
-	if division > 4 {
-		Ok(threshold)
+	// Note: the contents are read separately via `read_rar_entry` once the listing is
+	// complete.
+	let comic_info = if has_comic_info {
+		read_rar_entry(path, Path::new(COMIC_INFO_FILE))?
+			.and_then(|buf| String::from_utf8(buf).ok())
 	} else {
-		Ok(file_size / 2)
+		None
+	};
+
+	Ok((image_names, comic_info))
+}
+
+/// Verifies the integrity of a rar file by testing every entry, which checks the crc of
+/// each entry without extracting it. Rar files can't be tested in docker containers, in
+/// which case `ProcessFileError::VerificationUnsupported` is returned.
+pub fn verify_rar(path: &Path) -> ProcessFileResult<()> {
+	if stump_in_docker() {
+		return Err(ProcessFileError::VerificationUnsupported(
+			"Stump cannot verify cbr/rar files in docker containers for now.".into(),
+		));
+	}
+
+	let archive = unrar::Archive::new(path)?;
+
+	for entry in archive.test().map_err(|e| {
+		log::error!("Failed to open rar archive: {:?}", e);
+
+		ProcessFileError::RarOpenError
+	})? {
+		entry.map_err(|e| {
+			log::error!("Failed to test rar entry: {:?}", e);
+
+			ProcessFileError::RarReadError
+		})?;
 	}
 
+	Ok(())
+}
+
+/// Reads an entry of a rar file by extracting the archive to a temporary directory within
+/// the cache directory, which is removed afterwards. This is much slower than reading the
+/// entry directly, but doesn't rely on `Entry::read_bytes`, which segfaults in docker.
+fn extract_rar_entry(path: &Path, name: &Path) -> ProcessFileResult<Option<Vec<u8>>> {
+	let id = cuid::cuid().map_err(|e| ProcessFileError::Unknown(e.to_string()))?;
+	let unpacked_path = config::get_cache_dir().join(format!("rar-{}", id));
+
+	log::trace!("Extracting {:?} to read {:?}", path, name);
+
+	let result = unrar::Archive::new(path)?
+		.extract_to(&unpacked_path)
+		.map_err(|e| {
+			log::error!("Failed to open archive: {:?}", e.to_string());
+
+			ProcessFileError::RarOpenError
+		})
+		.and_then(|archive| {
+			archive.process().map_err(|e| {
+				log::error!("Failed to extract archive: {:?}", e.to_string());
+
+				ProcessFileError::RarExtractError(e.to_string())
+			})
+		})
+		.and_then(|_| {
+			let entry_path = unpacked_path.join(name);
+
+			if entry_path.is_file() {
+				Ok(Some(std::fs::read(entry_path)?))
+			} else {
+				Ok(None)
+			}
+		});
+
+	if let Err(err) = std::fs::remove_dir_all(&unpacked_path) {
+		log::warn!(
+			"Failed to delete unpacked rar contents in cache {:?}: {:?}",
+			unpacked_path,
+			err.to_string()
+		);
+	}
+
+	result
+}
+
+/// Reads the bytes of a single entry from a rar archive by name. The bytes must be read
+/// while the archive is still open, i.e. before the entry iterator is exhausted, so a new
+/// archive handle is opened for every call and nothing else is read from it. In docker,
+/// the entry is extracted instead, see `extract_rar_entry`.
+pub fn read_rar_entry(path: &Path, name: &Path) -> ProcessFileResult<Option<Vec<u8>>> {
+	if stump_in_docker() {
+		return extract_rar_entry(path, name);
+	}
+
+	let archive = unrar::Archive::new(path)?;
+
+	let open_archive = archive.list_extract().map_err(|e| {
+		log::error!("Failed to open rar archive: {:?}", e);
+
+		ProcessFileError::RarOpenError
+	})?;
+
+	for entry in open_archive {
+		let entry = entry.map_err(|e| {
+			log::error!("Failed to read rar entry: {:?}", e);
+
+			ProcessFileError::RarReadError
+		})?;
+
+		if entry.filename.as_path() == name {
+			let bytes = entry.read_bytes().map_err(|_e| {
+				log::error!("Failed to read bytes of rar entry {:?}", name);
+
+				ProcessFileError::RarReadError
+			})?;
+
+			return Ok(Some(bytes));
+		}
+	}
+
+	Ok(None)
+}
+
+/// Builds the page index of a rar file. Every image is read in a single pass over the
+/// archive, and is read by name later on since its data can't be read directly. Unlike
+/// the other formats, unrar can only read an entry in full, so the dimensions of each
+/// page can't be read from its header alone.
+pub fn index_rar_pages(path: &Path) -> ProcessFileResult<Vec<MediaPage>> {
+	if stump_in_docker() {
+		return Err(ProcessFileError::UnsupportedFileType(
+			"Stump cannot support cbr/rar files in docker containers for now.".into(),
+		));
+	}
+
+	let (image_names, comic_info) = get_rar_entries(path)?;
+	let pages = page_order::order_pages(image_names, comic_info.as_deref());
+
+	let positions = pages
+		.iter()
+		.enumerate()
+		.map(|(i, name)| (name.as_str(), i))
+		.collect::<HashMap<&str, usize>>();
+	let mut index: Vec<Option<MediaPage>> = vec![None; pages.len()];
+
+	let archive = unrar::Archive::new(path)?;
+
+	let open_archive = archive.list_extract().map_err(|e| {
+		log::error!("Failed to open rar archive: {:?}", e);
+
+		ProcessFileError::RarOpenError
+	})?;
+
+	for entry in open_archive {
+		let entry = entry.map_err(|e| {
+			log::error!("Failed to read rar entry: {:?}", e);
+
+			ProcessFileError::RarReadError
+		})?;
+
+		let name = entry.filename.to_string_lossy().to_string();
+
+		if let Some(&i) = positions.get(name.as_str()) {
+			let bytes = entry.read_bytes().map_err(|_e| {
+				log::error!("Failed to read bytes of rar entry {:?}", name);
+
+				ProcessFileError::RarReadError
+			})?;
+
+			index[i] = Some(media_file::index_page(
+				i + 1,
+				&name,
+				None,
+				bytes.len() as u64,
+				image::get_image_dimensions(&bytes),
+			));
+		}
+	}
+
+	Ok(index.into_iter().flatten().collect())
+}
+
+// FIXME: this is a temporary work around for the issue wonderful people on Discord
+// discovered.
+pub fn rar_sample(file: &str) -> Result<u64, ProcessFileError> {
+	log::debug!("Calculating checksum sample size for: {}", file);
+
+	Ok(checksum::sample_size(file)?)
+
 	// let entries: Vec<_> = archive
 	// 	.list()
 	// 	.map_err(|e| {
@@ -209,14 +505,14 @@ pub fn rar_sample(file: &str) -> Result<u64, ProcessFileError> {
 pub fn digest_rar(file: &str) -> Option<String> {
 	log::debug!("Attempting to generate checksum for: {}", file);
 
-	let sample = rar_sample(file);
-
-	// Error handled in `rar_sample`
-	if let Err(_) = sample {
-		return None;
-	}
+	let size = match rar_sample(file) {
+		Ok(size) => size,
+		Err(e) => {
+			log::debug!("Failed to calculate sample size for {}: {}", file, e);
 
-	let size = sample.unwrap();
+			return None;
+		},
+	};
 
 	log::debug!(
 		"Calculated sample size (in bytes) for generating checksum: {}",
@@ -238,13 +534,9 @@ pub fn digest_rar(file: &str) -> Option<String> {
 }
 
 // FIXME: the unrar library completely breaks on Docker... AGH!!
-// Note: I am sorting by filename after opening, *however* this really is *not* ideal.
-// If the files were to have any other naming scheme that would be a problem. Is this a problem?
-// TODO: I have to solve the `read_bytes` issue on my unrar fork. For now, I am leaving this very unideal
-// solution in place. OpenArchive gets consumed by the iterator, and so when the iterator is done, the
-// OpenArchive handle stored in Entry is no more. That's why I create another archive to grab what I want before
-// the iterator is done. At least, I *think* that is what is happening.
-// Fix location: https://github.com/aaronleopold/unrar.rs/tree/aleopold--read-bytes
+// Note: OpenArchive gets consumed by the iterator, and so when the iterator is done, the OpenArchive
+// handle stored in Entry is no more. That's why the entries are listed first, and the bytes of the
+// target entry are read with a separate archive handle in `read_rar_entry`.
 pub fn get_rar_image(file: &str, page: i32) -> ProcessFileResult<http::ImageResponse> {
 	if stump_in_docker() {
 		return Err(ProcessFileError::UnsupportedFileType(
@@ -252,64 +544,22 @@ pub fn get_rar_image(file: &str, page: i32) -> ProcessFileResult<http::ImageResp
 		));
 	}
 
-	let archive = unrar::Archive::new(file)?;
+	let (image_names, comic_info) = get_rar_entries(Path::new(file))?;
+	let pages = page_order::order_pages(image_names, comic_info.as_deref());
 
-	let mut entries: Vec<_> = archive
-		.list_extract()
-		.map_err(|e| {
-			log::error!("Failed to read rar archive: {:?}", e);
-
-			ProcessFileError::RarReadError
-		})?
-		.filter_map(|e| e.ok())
-		.filter(|e| e.is_image())
-		.collect();
-
-	entries.sort_by(|a, b| a.filename.cmp(&b.filename));
-
-	let entry = entries.into_iter().nth((page - 1) as usize).unwrap();
-
-	let archive = unrar::Archive::new(file)?;
+	let name = pages.get((page - 1) as usize).ok_or_else(|| {
+		log::error!(
+			"Could not find image for page {} in rar file {}",
+			page,
+			file
+		);
+		ProcessFileError::NoImageError
+	})?;
 
-	let bytes = archive
-		.list_extract()
-		.map_err(|e| {
-			log::error!("Failed to read rar archive: {:?}", e);
+	let bytes = read_rar_entry(Path::new(file), Path::new(name))?
+		.ok_or(ProcessFileError::NoImageError)?;
 
-			ProcessFileError::RarReadError
-		})?
-		.filter_map(|e| e.ok())
-		.filter(|e| e.filename == entry.filename)
-		.nth(0)
-		// FIXME: remove this unwrap...
-		.unwrap()
-		.read_bytes()
-		.map_err(|_e| ProcessFileError::RarReadError)?;
-
-	Ok((ContentType::JPEG, bytes))
-
-	// if let Some(entry) = entries.into_iter().nth((page - 1) as usize) {
-	// 	let archive = unrar::Archive::new(file)?;
-
-	// 	let bytes = archive
-	// 		.list_extract()
-	// 		.map_err(|e| {
-	// 			log::error!("Failed to read rar archive: {:?}", e);
-
-	// 			ProcessFileError::RarReadError
-	// 		})?
-	// 		.filter_map(|e| e.ok())
-	// 		.filter(|e| e.filename == entry.filename)
-	// 		.nth(0)
-	// 		// FIXME: remove this unwrap...
-	// 		.unwrap()
-	// 		.read_bytes()
-	// 		.map_err(|_e| ProcessFileError::RarReadError)?;
-
-	// 	return Ok((ContentType::JPEG, bytes));
-	// }
-
-	// Err(ProcessFileError::RarReadError)
+	Ok((media_file::guess_content_type(name), bytes))
 }
 
 #[cfg(test)]
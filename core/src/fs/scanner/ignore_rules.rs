@@ -0,0 +1,205 @@
+use std::{
+	collections::HashMap,
+	path::{Path, PathBuf},
+	sync::{Arc, RwLock},
+};
+
+use ignore::{
+	gitignore::{Gitignore, GitignoreBuilder},
+	Match,
+};
+
+use crate::{fs::scanner::ScannedFileTrait, types::models::library::LibraryOptions};
+
+/// The name of the files listing the paths to ignore within a directory (and its
+/// subdirectories), using the syntax of .gitignore files.
+pub const IGNORE_FILE: &str = ".stumpignore";
+
+/// Escapes an ignore glob of the library options, so that it is always read as a pattern.
+/// A leading `#` would otherwise start a comment, silently dropping the glob.
+pub fn escape_ignore_glob(glob: &str) -> String {
+	if glob.starts_with('#') {
+		format!("\\{}", glob)
+	} else {
+		glob.to_string()
+	}
+}
+
+/// The rules deciding which paths of a library are ignored when scanning. These are made
+/// of the ignore globs configured in the library options, along with the `.stumpignore`
+/// files found at any level of the library. Rules of deeper `.stumpignore` files take
+/// precedence, so that a pattern may be negated (e.g. `!keep.cbz`) for a subdirectory.
+pub struct IgnoreRules {
+	library_path: PathBuf,
+	globs: Gitignore,
+	/// The parsed `.stumpignore` files, keyed by the directory they are in. Directories
+	/// without one are kept as `None`, so they are only read once.
+	ignore_files: RwLock<HashMap<PathBuf, Option<Arc<Gitignore>>>>,
+}
+
+impl IgnoreRules {
+	pub fn new(library_path: &Path, library_options: &LibraryOptions) -> Self {
+		let mut builder = GitignoreBuilder::new(library_path);
+
+		for glob in &library_options.ignore_globs {
+			if let Err(e) = builder.add_line(None, glob) {
+				log::warn!("Invalid ignore glob {}: {}", glob, e);
+			}
+		}
+
+		let globs = builder.build().unwrap_or_else(|e| {
+			log::error!("Failed to build ignore globs of {:?}: {}", library_path, e);
+			Gitignore::empty()
+		});
+
+		Self {
+			library_path: library_path.to_path_buf(),
+			globs,
+			ignore_files: RwLock::new(HashMap::new()),
+		}
+	}
+
+	/// Reads the `.stumpignore` file within the directory, if there is one.
+	fn get_ignore_file(&self, dir: &Path) -> Option<Arc<Gitignore>> {
+		if let Some(ignore_file) = self
+			.ignore_files
+			.read()
+			.unwrap_or_else(|e| e.into_inner())
+			.get(dir)
+		{
+			return ignore_file.clone();
+		}
+
+		let ignore_file_path = dir.join(IGNORE_FILE);
+
+		let ignore_file = if ignore_file_path.is_file() {
+			let (ignore_file, error) = Gitignore::new(&ignore_file_path);
+
+			if let Some(e) = error {
+				log::warn!("Invalid rules in {:?}: {}", ignore_file_path, e);
+			}
+
+			Some(Arc::new(ignore_file))
+		} else {
+			None
+		};
+
+		self.ignore_files
+			.write()
+			.unwrap_or_else(|e| e.into_inner())
+			.insert(dir.to_path_buf(), ignore_file.clone());
+
+		ignore_file
+	}
+
+	/// Returns true if the path, or any of its parent directories, is ignored.
+	pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+		if !path.starts_with(&self.library_path) {
+			return false;
+		}
+
+		// Note: the deepest directories are checked first, since their rules take
+		// precedence. `.stumpignore` files outside of the library are not considered.
+		for dir in path
+			.ancestors()
+			.skip(1)
+			.take_while(|dir| dir.starts_with(&self.library_path))
+		{
+			if let Some(ignore_file) = self.get_ignore_file(dir) {
+				match ignore_file.matched_path_or_any_parents(path, is_dir) {
+					Match::Ignore(_) => return true,
+					Match::Whitelist(_) => return false,
+					Match::None => {},
+				}
+			}
+		}
+
+		self.globs
+			.matched_path_or_any_parents(path, is_dir)
+			.is_ignore()
+	}
+
+	/// Returns true if the directory directly contains media which are not ignored. See
+	/// `ScannedFileTrait::dir_has_media`.
+	pub fn dir_has_media(&self, path: &Path) -> bool {
+		match std::fs::read_dir(path) {
+			Ok(items) => items
+				.filter_map(|item| item.ok())
+				.map(|item| item.path())
+				.any(|item_path| {
+					!item_path.is_dir()
+						&& !self.is_ignored(&item_path, false)
+						&& !item_path.should_ignore()
+				}),
+			Err(_) => false,
+		}
+	}
+
+	/// Returns true if the directory directly contains image folders which are not
+	/// ignored. See `ScannedFileTrait::dir_has_image_folders`.
+	pub fn dir_has_image_folders(&self, path: &Path) -> bool {
+		match std::fs::read_dir(path) {
+			Ok(items) => items
+				.filter_map(|item| item.ok())
+				.map(|item| item.path())
+				.any(|item_path| {
+					item_path.is_image_folder() && !self.is_ignored(&item_path, true)
+				}),
+			Err(_) => false,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn get_library_options(ignore_globs: &[&str]) -> LibraryOptions {
+		LibraryOptions {
+			ignore_globs: ignore_globs.iter().map(|glob| glob.to_string()).collect(),
+			..Default::default()
+		}
+	}
+
+	#[test]
+	fn ignores_globs_of_library_options() {
+		let library_path = Path::new("/nonexistent/library");
+		let rules = IgnoreRules::new(
+			library_path,
+			// Note: a leading # starts a comment, so it has to be escaped.
+			&get_library_options(&["@eaDir/", "\\#recycle", "*_extras"]),
+		);
+
+		assert!(rules.is_ignored(&library_path.join("Batman/@eaDir"), true));
+		assert!(rules.is_ignored(&library_path.join("Batman/@eaDir/thumb.jpg"), false));
+		assert!(rules.is_ignored(&library_path.join("#recycle/Batman 01.cbz"), false));
+		assert!(rules.is_ignored(&library_path.join("Batman_extras"), true));
+		assert!(!rules.is_ignored(&library_path.join("Batman/Batman 01.cbz"), false));
+		// Note: a file named like an ignored directory isn't ignored.
+		assert!(!rules.is_ignored(&library_path.join("@eaDir"), false));
+	}
+
+	#[test]
+	fn escapes_leading_hash() {
+		assert_eq!(escape_ignore_glob("#recycle"), "\\#recycle");
+		assert_eq!(escape_ignore_glob("\\#recycle"), "\\#recycle");
+		assert_eq!(escape_ignore_glob("Batman #1"), "Batman #1");
+
+		let library_path = Path::new("/nonexistent/library");
+		let rules = IgnoreRules::new(
+			library_path,
+			&get_library_options(&[&escape_ignore_glob("#recycle")]),
+		);
+
+		assert!(rules.is_ignored(&library_path.join("#recycle/Batman 01.cbz"), false));
+	}
+
+	#[test]
+	fn ignores_nothing_outside_of_library() {
+		let library_path = Path::new("/nonexistent/library");
+		let rules = IgnoreRules::new(library_path, &get_library_options(&["*"]));
+
+		assert!(rules.is_ignored(&library_path.join("Batman"), true));
+		assert!(!rules.is_ignored(Path::new("/nonexistent/other/Batman"), true));
+	}
+}
@@ -1,15 +1,13 @@
-use rayon::prelude::{ParallelBridge, ParallelIterator};
 use rocket::tokio::{self, task::JoinHandle};
 use std::{
 	collections::HashMap,
-	path::Path,
+	path::{Path, PathBuf},
 	sync::{
 		atomic::{AtomicU64, Ordering},
 		Arc,
 	},
 	time::Duration,
 };
-use walkdir::{DirEntry, WalkDir};
 
 use crate::{
 	config::context::Ctx,
@@ -17,63 +15,180 @@ use crate::{
 	fs::{
 		image,
 		scanner::{
-			utils::{insert_series_batch, mark_media_missing},
+			ignore_rules::IgnoreRules,
+			series_detection::{
+				detect_series, get_series_dir, get_series_files, is_series_dir,
+				DetectedSeries,
+			},
+			utils::{
+				has_media_changed, insert_failed_media, insert_series,
+				insert_series_batch, mark_media_missing, reprocess_media,
+				set_media_modified_at, set_media_sort_key, InsertedMedia,
+			},
 			ScannedFileTrait,
 		},
 	},
-	job::persist_job_start,
+	job::{control::JobControl, persist_job_start},
 	prisma::{library, media, series},
-	types::{enums::FileStatus, errors::ApiError, models::library::LibraryOptions},
+	types::{
+		enums::{FileStatus, SeriesStrategy},
+		errors::ApiError,
+		models::library::LibraryOptions,
+	},
 };
 
 use super::{
 	utils::{batch_media_operations, mark_library_missing},
-	BatchScanOperation,
+	BatchScanOperation, ScanSummary,
 };
 
+/// Matches the series detected in a library against its existing series. Returns the
+/// existing series along with the series they were detected as, the existing series which
+/// are no longer detected, and the new series.
 fn check_series(
-	library_path: &str,
 	series: Vec<series::Data>,
-) -> (Vec<series::Data>, Vec<String>, Vec<DirEntry>) {
-	let series_map = series
-		.iter()
-		.map(|data| (data.path.as_str(), false).into())
-		.collect::<HashMap<&str, bool>>();
-
-	let missing_series = series
-		.iter()
-		.filter(|s| {
-			let path = Path::new(&s.path);
-			!path.exists()
+	detected_series: Vec<DetectedSeries>,
+	library_options: &LibraryOptions,
+) -> (
+	Vec<(series::Data, DetectedSeries)>,
+	Vec<series::Data>,
+	Vec<DetectedSeries>,
+) {
+	// Note: series grouped by their ComicInfo.xml files are matched by name, since their
+	// directory depends on where their media are.
+	let get_key = |name: &str, path: &str| match library_options.series_strategy {
+		SeriesStrategy::ComicInfo => name.to_string(),
+		_ => path.to_string(),
+	};
+
+	let mut detected_map = detected_series
+		.into_iter()
+		.map(|detected| {
+			let key = get_key(&detected.name, &detected.path.to_string_lossy());
+			(key, detected)
 		})
-		.map(|s| s.id.clone())
-		.collect::<Vec<String>>();
+		.collect::<HashMap<String, DetectedSeries>>();
 
-	let new_entries = WalkDir::new(library_path)
+	let mut existing_series = vec![];
+	let mut missing_series = vec![];
+
+	for data in series {
+		match detected_map.remove(&get_key(&data.name, &data.path)) {
+			Some(detected) => existing_series.push((data, detected)),
+			None => missing_series.push(data),
+		}
+	}
+
+	let new_series = detected_map.into_values().collect();
+
+	(existing_series, missing_series, new_series)
+}
+
+/// Marks the series which are no longer detected in a library as MISSING, along with
+/// their media. Media which are still in the library are moved to the series they now
+/// belong to when that series is scanned.
+async fn mark_series_missing(
+	ctx: &Ctx,
+	missing_series: Vec<series::Data>,
+) -> Result<(), ApiError> {
+	let (removed_series, undetected_series): (Vec<_>, Vec<_>) = missing_series
 		.into_iter()
-		.filter_entry(|e| e.path().is_dir())
-		.filter_map(|e| e.ok())
-		.par_bridge()
-		.filter(|entry| {
-			let path = entry.path();
+		.partition(|data| !Path::new(&data.path).exists());
+
+	for (series, reason) in [
+		(removed_series, "The series directory no longer exists"),
+		(
+			undetected_series,
+			"The series was not detected during the last scan",
+		),
+	] {
+		if series.is_empty() {
+			continue;
+		}
 
-			let path_str = path.as_os_str().to_string_lossy().to_string();
+		let series_ids = series.into_iter().map(|data| data.id).collect::<Vec<_>>();
 
-			path.dir_has_media() && !series_map.contains_key(path_str.as_str())
-		})
-		.collect::<Vec<DirEntry>>();
+		ctx.db
+			.series()
+			.update_many(
+				vec![series::id::in_vec(series_ids.clone())],
+				vec![
+					series::status::set(FileStatus::Missing.to_string()),
+					series::status_reason::set(Some(reason.to_string())),
+				],
+			)
+			.exec()
+			.await?;
+
+		ctx.db
+			.media()
+			.update_many(
+				vec![media::series_id::in_vec(series_ids)],
+				vec![
+					media::status::set(FileStatus::Missing.to_string()),
+					media::status_reason::set(Some(
+						"The file could not be found during the last scan".to_string(),
+					)),
+				],
+			)
+			.exec()
+			.await?;
+	}
+
+	Ok(())
+}
+
+/// Updates the existing series which were detected again, so that series previously
+/// marked as MISSING are READY again, and series grouped by their ComicInfo.xml files
+/// follow their media.
+async fn sync_detected_series(
+	ctx: &Ctx,
+	existing_series: Vec<(series::Data, DetectedSeries)>,
+) -> Vec<(series::Data, Vec<PathBuf>)> {
+	let mut series = Vec::with_capacity(existing_series.len());
+
+	for (data, detected) in existing_series {
+		let path = detected.path.to_string_lossy().to_string();
+
+		if data.status == FileStatus::Missing.to_string() || data.path != path {
+			let result = ctx
+				.db
+				.series()
+				.update(
+					series::id::equals(data.id.clone()),
+					vec![
+						series::path::set(path),
+						series::status::set(FileStatus::Ready.to_string()),
+						series::status_reason::set(None),
+					],
+				)
+				.exec()
+				.await;
+
+			match result {
+				Ok(updated) => {
+					series.push((updated, detected.files));
+					continue;
+				},
+				Err(e) => log::error!("Failed to update series {}: {:?}", data.id, e),
+			}
+		}
 
-	(series, missing_series, new_entries)
+		series.push((data, detected.files));
+	}
+
+	series
 }
 
 /// Queries the database for the library by the given `path` and performs basic
 /// checks to ensure the library is in a valid state for scanning. Returns the
-/// library itself, its series, and the number of files that will be processed.
+/// library itself, its series along with their files, and the number of files that will
+/// be processed.
 async fn precheck(
 	ctx: &Ctx,
 	path: String,
 	runner_id: &str,
-) -> Result<(library::Data, Vec<series::Data>, u64), ApiError> {
+) -> Result<(library::Data, Vec<(series::Data, Vec<PathBuf>)>, u64), ApiError> {
 	let db = ctx.get_db();
 
 	let library = db
@@ -101,21 +216,35 @@ async fn precheck(
 
 	let series = library.series()?.to_owned();
 
-	let (mut series, missing_series_ids, new_entries) = check_series(&path, series);
+	let library_options: LibraryOptions = library
+		.library_options()
+		.map(|opt| opt.to_owned().into())
+		.unwrap_or_default();
 
-	if !missing_series_ids.is_empty() {
-		ctx.db
-			.series()
-			.update_many(
-				vec![series::id::in_vec(missing_series_ids)],
-				vec![series::status::set(FileStatus::Missing.to_string())],
-			)
-			.exec()
-			.await?;
+	let start = std::time::Instant::now();
+
+	let detection_path = path.clone();
+	let detection_options = library_options.clone();
+	let detected_series: Vec<DetectedSeries> = tokio::task::spawn_blocking(move || {
+		let library_path = Path::new(&detection_path);
+		let ignore_rules = IgnoreRules::new(library_path, &detection_options);
+
+		detect_series(library_path, &detection_options, &ignore_rules)
+	})
+	.await
+	.map_err(|e| ApiError::InternalServerError(e.to_string()))?;
+
+	let (existing_series, missing_series, new_series) =
+		check_series(series, detected_series, &library_options);
+
+	if !missing_series.is_empty() {
+		mark_series_missing(ctx, missing_series).await?;
 	}
 
+	let mut series = sync_detected_series(ctx, existing_series).await;
+
 	let insertion_result =
-		insert_series_batch(ctx, new_entries, library.id.clone()).await;
+		insert_series_batch(ctx, &new_series, library.id.clone()).await;
 
 	if let Err(e) = insertion_result {
 		log::error!("Failed to batch insert series: {}", e);
@@ -126,40 +255,21 @@ async fn precheck(
 			path: path.clone(),
 		});
 	} else {
-		let mut inserted_series = insertion_result.unwrap();
+		let inserted_series = insertion_result.unwrap();
 
 		ctx.emit_client_event(ClientEvent::CreatedSeriesBatch(
 			inserted_series.len() as u64
 		));
 
-		series.append(&mut inserted_series);
+		// Note: the series are created in the same order they were detected in.
+		series.extend(
+			inserted_series
+				.into_iter()
+				.zip(new_series.into_iter().map(|detected| detected.files)),
+		);
 	}
 
-	let start = std::time::Instant::now();
-
-	// TODO: perhaps use rayon instead...
-	let files_to_process: u64 = futures::future::join_all(
-		series
-			.iter()
-			.map(|data| {
-				let path = data.path.clone();
-
-				tokio::task::spawn_blocking(move || {
-					WalkDir::new(&path)
-						.into_iter()
-						// FIXME: why won't this work??
-						// .filter_entry(|e| e.path().is_file())
-						.filter_map(|e| e.ok())
-						.filter(|e| e.path().is_file())
-						.count() as u64
-				})
-			})
-			.collect::<Vec<JoinHandle<u64>>>(),
-	)
-	.await
-	.into_iter()
-	.filter_map(|res| res.ok())
-	.sum();
+	let files_to_process: u64 = series.iter().map(|(_, files)| files.len() as u64).sum();
 
 	let duration = start.elapsed();
 
@@ -177,9 +287,11 @@ async fn scan_series(
 	ctx: Ctx,
 	runner_id: String,
 	series: series::Data,
+	files: Vec<PathBuf>,
 	library_options: LibraryOptions,
+	control: JobControl,
 	mut on_progress: impl FnMut(String) + Send + Sync + 'static,
-) {
+) -> u64 {
 	let db = ctx.get_db();
 
 	let media = db
@@ -194,12 +306,21 @@ async fn scan_series(
 		.map(|data| (data.path.clone(), false).into())
 		.collect::<HashMap<String, bool>>();
 
-	for entry in WalkDir::new(&series.path)
-		.into_iter()
-		.filter_map(|e| e.ok())
-		.filter(|e| e.path().is_file())
-	{
-		let path = entry.path();
+	let existing_media = media
+		.iter()
+		.map(|data| (data.path.as_str(), data))
+		.collect::<HashMap<&str, &media::Data>>();
+
+	let mut found_cover: Option<PathBuf> = None;
+	let mut moved_media = 0;
+
+	for entry in files {
+		if !control.checkpoint().await {
+			log::debug!("Scan of series {} cancelled", series.path);
+			break;
+		}
+
+		let path = entry.as_path();
 		let path_str = path.to_str().unwrap_or("");
 
 		log::debug!("Currently scanning: {:?}", path);
@@ -208,32 +329,95 @@ async fn scan_series(
 		// callback, as well.
 		on_progress(format!("Analyzing {:?}", path));
 
-		if path.should_ignore() {
+		// Note: image folders are the only directories among the files of a series, which
+		// are never ignored.
+		if !path.is_dir() && path.should_ignore() {
 			log::trace!("Skipping ignored file: {:?}", path);
 			continue;
 		} else if path.is_thumbnail_img() {
-			// TODO: these will *eventually* be supported, but not priority right now.
-			log::debug!(
-				"Stump does not support thumbnail image overrides yet ({:?}). Stay tuned!",
-				path
-			);
+			// Note: only images directly within the series directory are covers of the
+			// series, others are left to the series (or image folder) they belong to.
+			if path.parent() == Some(Path::new(&series.path)) {
+				log::debug!("Found cover image override: {:?}", path);
+				// Note: the file names are compared so that the same image is chosen on
+				// every scan when there are several candidates.
+				if found_cover
+					.as_ref()
+					.map(|cover| path < cover.as_path())
+					.unwrap_or(true)
+				{
+					found_cover = Some(path.to_path_buf());
+				}
+			}
 			continue;
-		} else if let Some(_) = visited_media.get(path_str) {
+		} else if let Some(existing_media) = existing_media.get(path_str) {
 			log::debug!("Existing media found: {:?}", path);
 			*visited_media.entry(path_str.to_string()).or_insert(true) = true;
+
+			if has_media_changed(existing_media, path) {
+				log::debug!("Media has changed since the last scan: {:?}", path);
+
+				match reprocess_media(&ctx, existing_media, &library_options).await {
+					Ok(media) => {
+						ctx.emit_client_event(ClientEvent::UpdatedMedia(media));
+					},
+					Err(e) => log::error!("Failed to update media: {:?}", e),
+				}
+			} else {
+				if existing_media.modified_at.is_none() {
+					if let Err(e) = set_media_modified_at(&ctx, existing_media).await {
+						log::error!("Failed to set modified time of media: {:?}", e);
+					}
+				}
+
+				// Note: media processed before sort keys were tracked are given one, so
+				// they are ordered along with the media processed since.
+				if existing_media.sort_key.is_none() {
+					if let Err(e) = set_media_sort_key(&ctx, existing_media).await {
+						log::error!("Failed to set sort key of media: {:?}", e);
+					}
+				}
+			}
+
 			continue;
 		}
 
 		log::debug!("New media found at {:?} in series {:?}", &path, &series.id);
 
-		match super::utils::insert_media(&ctx, path, series.id.clone(), &library_options)
-			.await
+		match super::utils::insert_media(
+			&ctx,
+			path,
+			series.library_id.as_deref().unwrap_or_default(),
+			series.id.clone(),
+			&library_options,
+		)
+		.await
 		{
-			Ok(media) => {
+			Ok(InsertedMedia::Created(media)) => {
 				visited_media.insert(media.path.clone(), true);
 
 				ctx.emit_client_event(ClientEvent::CreatedMedia(media.clone()));
 			},
+			Ok(InsertedMedia::Regrouped(media)) => {
+				log::debug!("Media {} was moved to series {}", media.path, series.id);
+
+				visited_media.insert(media.path.clone(), true);
+
+				ctx.emit_client_event(ClientEvent::UpdatedMedia(media));
+			},
+			Ok(InsertedMedia::Moved { from, media }) => {
+				log::debug!("Media {} was moved from {}", media.path, from);
+
+				visited_media.insert(media.path.clone(), true);
+				moved_media += 1;
+
+				ctx.handle_log_event(ClientEvent::MovedMedia {
+					runner_id: Some(runner_id.clone()),
+					from,
+					to: media.path,
+				})
+				.await;
+			},
 			Err(e) => {
 				log::error!("Failed to insert media: {:?}", e);
 
@@ -243,10 +427,30 @@ async fn scan_series(
 					message: e.to_string(),
 				})
 				.await;
+
+				match insert_failed_media(&ctx, path, series.id.clone(), &e).await {
+					Ok(media) => {
+						visited_media.insert(media.path, true);
+					},
+					Err(e) => log::error!("Failed to persist failed media: {:?}", e),
+				}
 			},
 		}
 	}
 
+	// Note: the files left unvisited by a cancelled scan aren't missing, so the series is
+	// left as it is until it is scanned in full.
+	if control.is_cancelled() {
+		return moved_media;
+	}
+
+	if let Err(e) =
+		super::utils::sync_series_cover(&ctx, &series, found_cover, &library_options)
+			.await
+	{
+		log::error!("Failed to update cover of series {}: {:?}", series.id, e);
+	}
+
 	let missing_media = visited_media
 		.into_iter()
 		.filter(|(_, visited)| !visited)
@@ -289,11 +493,16 @@ async fn scan_series(
 		// 	log::debug!("Marked missing media as MISSING.");
 		// }
 	}
+
+	moved_media
 }
 
 async fn scan_series_batch(
 	ctx: Ctx,
 	series: series::Data,
+	files: Vec<PathBuf>,
+	library_options: LibraryOptions,
+	control: JobControl,
 	mut on_progress: impl FnMut(String) + Send + Sync + 'static,
 ) -> Vec<BatchScanOperation> {
 	let db = ctx.get_db();
@@ -310,14 +519,22 @@ async fn scan_series_batch(
 		.map(|data| (data.path.clone(), false).into())
 		.collect::<HashMap<String, bool>>();
 
+	let existing_media = media
+		.iter()
+		.map(|data| (data.path.as_str(), data))
+		.collect::<HashMap<&str, &media::Data>>();
+
 	let mut operations = vec![];
 
-	for entry in WalkDir::new(&series.path)
-		.into_iter()
-		.filter_map(|e| e.ok())
-		.filter(|e| e.path().is_file())
-	{
-		let path = entry.path();
+	let mut found_cover: Option<PathBuf> = None;
+
+	for entry in files {
+		if !control.checkpoint().await {
+			log::debug!("Scan of series {} cancelled", series.path);
+			break;
+		}
+
+		let path = entry.as_path();
 		let path_str = path.to_str().unwrap_or("");
 
 		log::debug!("Currently scanning: {:?}", path);
@@ -326,19 +543,53 @@ async fn scan_series_batch(
 		// callback, as well.
 		on_progress(format!("Analyzing {:?}", path));
 
-		if path.should_ignore() {
+		// Note: image folders are the only directories among the files of a series, which
+		// are never ignored.
+		if !path.is_dir() && path.should_ignore() {
 			log::trace!("Skipping ignored file: {:?}", path);
 			continue;
 		} else if path.is_thumbnail_img() {
-			// TODO: these will *eventually* be supported, but not priority right now.
-			log::debug!(
-				"Stump does not support thumbnail image overrides yet ({:?}). Stay tuned!",
-				path
-			);
+			// Note: only images directly within the series directory are covers of the
+			// series, others are left to the series (or image folder) they belong to.
+			if path.parent() == Some(Path::new(&series.path)) {
+				log::debug!("Found cover image override: {:?}", path);
+				// Note: the file names are compared so that the same image is chosen on
+				// every scan when there are several candidates.
+				if found_cover
+					.as_ref()
+					.map(|cover| path < cover.as_path())
+					.unwrap_or(true)
+				{
+					found_cover = Some(path.to_path_buf());
+				}
+			}
 			continue;
-		} else if let Some(_) = visited_media.get(path_str) {
+		} else if let Some(existing_media) = existing_media.get(path_str) {
 			log::debug!("Existing media found: {:?}", path);
 			*visited_media.entry(path_str.to_string()).or_insert(true) = true;
+
+			if has_media_changed(existing_media, path) {
+				log::debug!("Media has changed since the last scan: {:?}", path);
+
+				operations.push(BatchScanOperation::UpdateMedia {
+					media: (*existing_media).clone(),
+				});
+			} else {
+				if existing_media.modified_at.is_none() {
+					if let Err(e) = set_media_modified_at(&ctx, existing_media).await {
+						log::error!("Failed to set modified time of media: {:?}", e);
+					}
+				}
+
+				// Note: media processed before sort keys were tracked are given one, so
+				// they are ordered along with the media processed since.
+				if existing_media.sort_key.is_none() {
+					if let Err(e) = set_media_sort_key(&ctx, existing_media).await {
+						log::error!("Failed to set sort key of media: {:?}", e);
+					}
+				}
+			}
+
 			continue;
 		}
 
@@ -350,6 +601,19 @@ async fn scan_series_batch(
 		});
 	}
 
+	// Note: the files left unvisited by a cancelled scan aren't missing, so only the
+	// operations for the files scanned so far are kept.
+	if control.is_cancelled() {
+		return operations;
+	}
+
+	if let Err(e) =
+		super::utils::sync_series_cover(&ctx, &series, found_cover, &library_options)
+			.await
+	{
+		log::error!("Failed to update cover of series {}: {:?}", series.id, e);
+	}
+
 	visited_media
 		.into_iter()
 		.filter(|(_, visited)| !visited)
@@ -364,7 +628,8 @@ pub async fn scan_batch(
 	ctx: Ctx,
 	path: String,
 	runner_id: String,
-) -> Result<u64, ApiError> {
+	control: JobControl,
+) -> Result<ScanSummary, ApiError> {
 	log::trace!("Enter scan_batch");
 
 	let (library, series, files_to_process) = precheck(&ctx, path, &runner_id).await?;
@@ -374,7 +639,8 @@ pub async fn scan_batch(
 		.map(|opt| (*opt).into())
 		.unwrap_or_default();
 
-	let _job = persist_job_start(&ctx, runner_id.clone(), files_to_process).await?;
+	let _job =
+		persist_job_start(&ctx, runner_id.clone(), files_to_process, &control).await?;
 
 	ctx.emit_client_event(ClientEvent::job_started(
 		runner_id.clone(),
@@ -387,22 +653,31 @@ pub async fn scan_batch(
 
 	let tasks: Vec<JoinHandle<Vec<BatchScanOperation>>> = series
 		.into_iter()
-		.map(|s| {
+		.map(|(s, files)| {
 			let ctx_cpy = ctx.get_ctx();
 			let r_id = runner_id.clone();
 			let counter_ref = counter.clone();
+			let library_options = library_options.clone();
+			let control = control.clone();
 
 			tokio::spawn(async move {
-				scan_series_batch(ctx_cpy.get_ctx(), s, move |msg| {
-					let previous = counter_ref.fetch_add(1, Ordering::SeqCst);
-
-					ctx_cpy.emit_client_event(ClientEvent::job_progress(
-						r_id.to_owned(),
-						Some(previous + 1),
-						files_to_process,
-						Some(msg),
-					));
-				})
+				scan_series_batch(
+					ctx_cpy.get_ctx(),
+					s,
+					files,
+					library_options,
+					control,
+					move |msg| {
+						let previous = counter_ref.fetch_add(1, Ordering::SeqCst);
+
+						ctx_cpy.emit_client_event(ClientEvent::job_progress(
+							r_id.to_owned(),
+							Some(previous + 1),
+							files_to_process,
+							Some(msg),
+						));
+					},
+				)
 				.await
 			})
 		})
@@ -418,25 +693,43 @@ pub async fn scan_batch(
 
 	let final_count = counter.load(Ordering::SeqCst);
 
-	let created_media = batch_media_operations(&ctx, operations, &library_options)
-		.await
-		.map_err(|e| {
-			log::error!("Failed to batch media operations: {:?}", e);
-			ApiError::InternalServerError(e.to_string())
-		})?;
+	// Note: files which needed no create or update were completed while walking the
+	// library, the others are only completed once their operation has been applied.
+	let pending_tasks = operations
+		.iter()
+		.filter(|operation| {
+			!matches!(operation, BatchScanOperation::MarkMediaMissing { .. })
+		})
+		.count() as u64;
+
+	let (created_media, summary) = batch_media_operations(
+		&ctx,
+		&runner_id,
+		&library.id,
+		operations,
+		&library_options,
+		&control,
+	)
+	.await
+	.map_err(|e| {
+		log::error!("Failed to batch media operations: {:?}", e);
+		ApiError::InternalServerError(e.to_string())
+	})?;
 
 	ctx.emit_client_event(ClientEvent::CreatedMediaBatch(created_media.len() as u64));
 
 	// TODO: change task_count and send progress?
-	if library_options.create_webp_thumbnails {
-		log::trace!("Library configured to create WEBP thumbnails.");
+	// Note: thumbnails are skipped for cancelled scans, they can be generated later on
+	// with a ThumbnailGenerationJob.
+	if library_options.create_webp_thumbnails && !control.is_cancelled() {
+		log::trace!("Library configured to create thumbnails.");
 
 		ctx.emit_client_event(ClientEvent::job_progress(
 			runner_id.clone(),
 			Some(final_count),
 			files_to_process,
 			Some(format!(
-				"Creating {} WEBP thumbnails (this can take some time)",
+				"Creating {} thumbnails (this can take some time)",
 				created_media.len()
 			)),
 		));
@@ -444,19 +737,27 @@ pub async fn scan_batch(
 		// sleep for a bit to let client catch up
 		tokio::time::sleep(Duration::from_millis(50)).await;
 
-		if let Err(err) = image::generate_thumbnails(created_media) {
+		if let Err(err) = image::generate_thumbnails(
+			created_media,
+			&library_options.get_thumbnail_options(),
+		) {
 			log::error!("Failed to generate thumbnails: {:?}", err);
 		}
 	}
 
-	Ok(final_count)
+	Ok(ScanSummary {
+		completed_tasks: final_count.saturating_sub(pending_tasks)
+			+ summary.completed_tasks,
+		moved_media: summary.moved_media,
+	})
 }
 
 pub async fn scan_sync(
 	ctx: Ctx,
 	path: String,
 	runner_id: String,
-) -> Result<u64, ApiError> {
+	control: JobControl,
+) -> Result<ScanSummary, ApiError> {
 	let (library, series, files_to_process) = precheck(&ctx, path, &runner_id).await?;
 
 	let library_options: LibraryOptions = library
@@ -465,7 +766,8 @@ pub async fn scan_sync(
 		.unwrap_or_default();
 
 	// TODO: I am not sure if jobs should fail when the job fails to persist to DB.
-	let _job = persist_job_start(&ctx, runner_id.clone(), files_to_process).await?;
+	let _job =
+		persist_job_start(&ctx, runner_id.clone(), files_to_process, &control).await?;
 
 	ctx.emit_client_event(ClientEvent::job_started(
 		runner_id.clone(),
@@ -475,8 +777,13 @@ pub async fn scan_sync(
 	));
 
 	let counter = Arc::new(AtomicU64::new(0));
+	let mut moved_media = 0;
+
+	for (s, files) in series {
+		if control.is_cancelled() {
+			break;
+		}
 
-	for s in series {
 		let progress_ctx = ctx.get_ctx();
 		let r_id = runner_id.clone();
 
@@ -486,7 +793,197 @@ pub async fn scan_sync(
 		// considering it consists of just a few booleans.
 		let library_options = library_options.clone();
 
-		scan_series(ctx.get_ctx(), runner_id, s, library_options, move |msg| {
+		moved_media += scan_series(
+			ctx.get_ctx(),
+			runner_id,
+			s,
+			files,
+			library_options,
+			control.clone(),
+			move |msg| {
+				let previous = counter_ref.fetch_add(1, Ordering::SeqCst);
+
+				progress_ctx.emit_client_event(ClientEvent::job_progress(
+					r_id.to_owned(),
+					Some(previous + 1),
+					files_to_process,
+					Some(msg),
+				));
+			},
+		)
+		.await;
+	}
+
+	Ok(ScanSummary {
+		completed_tasks: counter.load(Ordering::SeqCst),
+		moved_media,
+	})
+}
+
+/// Scans the series of a single directory of a library, e.g. one which changed while the
+/// library was being watched. A series is created for the directory if it is new, and an
+/// existing series (along with its media) is marked as MISSING if the directory no longer
+/// exists. Series grouped by their ComicInfo.xml files may span any directory, so the
+/// entire library is scanned for them instead.
+pub async fn scan_series_path(
+	ctx: Ctx,
+	library_id: String,
+	path: String,
+	runner_id: String,
+	control: JobControl,
+) -> Result<ScanSummary, ApiError> {
+	let db = ctx.get_db();
+
+	let library = db
+		.library()
+		.find_unique(library::id::equals(library_id.clone()))
+		.with(library::library_options::fetch())
+		.exec()
+		.await?;
+
+	if library.is_none() {
+		return Err(ApiError::NotFound(format!(
+			"Library with id {} not found",
+			library_id
+		)));
+	}
+
+	let library = library.unwrap();
+
+	let library_options: LibraryOptions = library
+		.library_options()
+		.map(|opt| opt.to_owned().into())
+		.unwrap_or_default();
+
+	if library_options.series_strategy == SeriesStrategy::ComicInfo {
+		return scan_sync(ctx, library.path, runner_id, control).await;
+	}
+
+	let library_path = Path::new(&library.path);
+
+	let series_path =
+		match get_series_dir(library_path, Path::new(&path), &library_options) {
+			Some(series_path) => series_path,
+			None => {
+				persist_job_start(&ctx, runner_id, 0, &control).await?;
+
+				return Ok(ScanSummary {
+					completed_tasks: 0,
+					moved_media: 0,
+				});
+			},
+		};
+
+	let series_path_str = series_path.to_string_lossy().to_string();
+
+	let ignore_rules = IgnoreRules::new(library_path, &library_options);
+
+	let existing_series = db
+		.series()
+		.find_first(vec![series::path::equals(series_path_str.clone())])
+		.exec()
+		.await?;
+
+	let series = match existing_series {
+		Some(series) if !series_path.exists() => {
+			log::debug!("Series directory was removed: {}", series.path);
+
+			db.series()
+				.update(
+					series::id::equals(series.id.clone()),
+					vec![
+						series::status::set(FileStatus::Missing.to_string()),
+						series::status_reason::set(Some(
+							"The series directory no longer exists".to_string(),
+						)),
+					],
+				)
+				.exec()
+				.await?;
+
+			db.media()
+				.update_many(
+					vec![media::series_id::equals(Some(series.id))],
+					vec![
+						media::status::set(FileStatus::Missing.to_string()),
+						media::status_reason::set(Some(
+							"The file could not be found during the last scan"
+								.to_string(),
+						)),
+					],
+				)
+				.exec()
+				.await?;
+
+			None
+		},
+		Some(series) if series.status == FileStatus::Missing.to_string() => Some(
+			db.series()
+				.update(
+					series::id::equals(series.id),
+					vec![
+						series::status::set(FileStatus::Ready.to_string()),
+						series::status_reason::set(None),
+					],
+				)
+				.exec()
+				.await?,
+		),
+		Some(series) => Some(series),
+		None if is_series_dir(
+			library_path,
+			&series_path,
+			&library_options,
+			&ignore_rules,
+		) =>
+		{
+			let series = insert_series(&ctx, &series_path, library.id.clone()).await?;
+
+			ctx.emit_client_event(ClientEvent::CreatedSeries(series.clone()));
+
+			Some(series)
+		},
+		None => None,
+	};
+
+	let series = match series {
+		Some(series) => series,
+		None => {
+			persist_job_start(&ctx, runner_id, 0, &control).await?;
+
+			return Ok(ScanSummary {
+				completed_tasks: 0,
+				moved_media: 0,
+			});
+		},
+	};
+
+	let files =
+		get_series_files(Path::new(&series.path), &library_options, &ignore_rules);
+	let files_to_process = files.len() as u64;
+
+	persist_job_start(&ctx, runner_id.clone(), files_to_process, &control).await?;
+
+	ctx.emit_client_event(ClientEvent::job_started(
+		runner_id.clone(),
+		0,
+		files_to_process,
+		Some(format!("Starting series scan at {}", &series.path)),
+	));
+
+	let counter = Arc::new(AtomicU64::new(0));
+	let counter_ref = counter.clone();
+	let progress_ctx = ctx.get_ctx();
+	let r_id = runner_id.clone();
+
+	let moved_media = scan_series(
+		ctx.get_ctx(),
+		runner_id,
+		series,
+		files,
+		library_options,
+		control,
+		move |msg| {
 			let previous = counter_ref.fetch_add(1, Ordering::SeqCst);
 
 			progress_ctx.emit_client_event(ClientEvent::job_progress(
@@ -495,11 +992,14 @@ pub async fn scan_sync(
 				files_to_process,
 				Some(msg),
 			));
-		})
-		.await;
-	}
+		},
+	)
+	.await;
 
-	Ok(counter.load(Ordering::SeqCst))
+	Ok(ScanSummary {
+		completed_tasks: counter.load(Ordering::SeqCst),
+		moved_media,
+	})
 }
 
 // TODO: add a 'scan all' for scanning all libraries...
@@ -513,6 +1013,7 @@ mod tests {
 	use rocket::tokio;
 
 	use crate::config::context::*;
+	use crate::job::control::JobControl;
 
 	use crate::types::errors::ApiError;
 
@@ -526,6 +1027,7 @@ mod tests {
 			ctx,
 			"/Users/aaronleopold/Documents/Stump/Demo".to_string(),
 			"runner_id_batch".to_string(),
+			JobControl::new(),
 		)
 		.await?;
 		let duration = start.elapsed();
@@ -549,6 +1051,7 @@ mod tests {
 			ctx,
 			"/Users/aaronleopold/Documents/Stump/Demo".to_string(),
 			"runner_id_sync".to_string(),
+			JobControl::new(),
 		)
 		.await?;
 		let duration = start.elapsed();
@@ -1,11 +1,15 @@
 use std::path::{Path, PathBuf};
 
+pub mod ignore_rules;
 pub mod library;
+pub mod series_detection;
+pub mod sort_key;
 pub mod utils;
 
-use rocket::http::ContentType;
-
-use crate::fs::media_file;
+use crate::{
+	fs::{image_folder, media_file},
+	prisma::media,
+};
 
 // TODO: refactor this trait?
 pub trait ScannedFileTrait {
@@ -15,6 +19,8 @@ pub trait ScannedFileTrait {
 	fn is_img(&self) -> bool;
 	fn is_thumbnail_img(&self) -> bool;
 	fn dir_has_media(&self) -> bool;
+	fn is_image_folder(&self) -> bool;
+	fn dir_has_image_folders(&self) -> bool;
 }
 
 impl ScannedFileTrait for Path {
@@ -53,13 +59,16 @@ impl ScannedFileTrait for Path {
 			Some(k) => {
 				let mime = k.mime_type();
 
-				let content_type = media_file::get_content_type_from_mime(mime);
+				// Note: images are not ignored here, since they may be thumbnail overrides.
+				if mime.starts_with("image/") {
+					return false;
+				}
 
-				// ContentType::Any is basically Stump's fallback. No media should be added that
-				// isn't explicitly supported.
-				if content_type == ContentType::Any {
+				// No media should be added that isn't explicitly supported.
+				if !media_file::is_supported_mime(mime) {
 					log::debug!(
-						"Ignoring file with unknown mime type {}",
+						"Ignoring file with unsupported mime type {}: {}",
+						mime,
 						self.display()
 					);
 					return true;
@@ -136,9 +145,36 @@ impl ScannedFileTrait for Path {
 			.filter(|item| item.path() != self)
 			.any(|f| !f.path().should_ignore())
 	}
+
+	fn is_image_folder(&self) -> bool {
+		image_folder::is_image_folder(self)
+	}
+
+	fn dir_has_image_folders(&self) -> bool {
+		if !self.is_dir() {
+			return false;
+		}
+
+		match std::fs::read_dir(self) {
+			Ok(items) => items
+				.filter_map(|item| item.ok())
+				.any(|item| item.path().is_image_folder()),
+			Err(_) => false,
+		}
+	}
+}
+
+/// The outcome of a library scan.
+pub struct ScanSummary {
+	/// The number of files which were processed.
+	pub completed_tasks: u64,
+	/// The number of media which were moved (or renamed), and reattached to their new
+	/// file rather than created again.
+	pub moved_media: u64,
 }
 
 pub enum BatchScanOperation {
 	CreateMedia { path: PathBuf, series_id: String },
+	UpdateMedia { media: media::Data },
 	MarkMediaMissing { path: String },
 }
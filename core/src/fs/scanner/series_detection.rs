@@ -0,0 +1,472 @@
+use std::{
+	collections::HashMap,
+	path::{Path, PathBuf},
+};
+
+use rayon::prelude::{IntoParallelIterator, ParallelBridge, ParallelIterator};
+use walkdir::WalkDir;
+
+use crate::{
+	fs::{
+		media_file,
+		scanner::{ignore_rules::IgnoreRules, ScannedFileTrait},
+	},
+	types::{enums::SeriesStrategy, models::library::LibraryOptions},
+};
+
+/// A series found while scanning a library, according to the series strategy of the
+/// library.
+#[derive(Debug)]
+pub struct DetectedSeries {
+	pub name: String,
+	/// The directory of the series. For series grouped by their ComicInfo.xml files, this
+	/// is the deepest directory containing all of their media.
+	pub path: PathBuf,
+	/// The files (and image folders) belonging to the series.
+	pub files: Vec<PathBuf>,
+}
+
+impl DetectedSeries {
+	fn from_dir(
+		path: PathBuf,
+		library_options: &LibraryOptions,
+		ignore_rules: &IgnoreRules,
+	) -> Self {
+		Self {
+			name: get_dir_name(&path),
+			files: get_series_files(&path, library_options, ignore_rules),
+			path,
+		}
+	}
+}
+
+/// Returns the name of a directory, which is the name of the series it holds.
+pub fn get_dir_name(path: &Path) -> String {
+	path.file_name()
+		.unwrap_or_default()
+		.to_string_lossy()
+		.to_string()
+}
+
+/// Walks a directory, returning the path of every file within it which isn't ignored.
+/// When the library is configured to treat image folders as media, the image folders
+/// themselves are returned instead of the images within them. Only the files directly
+/// within the directory are returned, unless `recursive` is true.
+fn walk_dir(
+	path: &Path,
+	library_options: &LibraryOptions,
+	ignore_rules: &IgnoreRules,
+	recursive: bool,
+) -> Vec<PathBuf> {
+	let mut paths = vec![];
+	let mut walker = WalkDir::new(path);
+
+	if !recursive {
+		walker = walker.max_depth(1);
+	}
+
+	let mut walker = walker.into_iter();
+
+	while let Some(entry) = walker.next() {
+		let entry = match entry {
+			Ok(entry) => entry,
+			Err(_) => continue,
+		};
+
+		let entry_path = entry.path();
+		let is_dir = entry.file_type().is_dir();
+
+		if ignore_rules.is_ignored(entry_path, is_dir) {
+			log::trace!("Skipping ignored path: {:?}", entry_path);
+
+			if is_dir {
+				walker.skip_current_dir();
+			}
+			continue;
+		} else if entry_path.is_file() {
+			paths.push(entry_path.to_path_buf());
+		} else if library_options.treat_image_folders_as_media
+			&& entry_path.is_image_folder()
+		{
+			paths.push(entry_path.to_path_buf());
+			walker.skip_current_dir();
+		}
+	}
+
+	paths
+}
+
+/// Returns the files of the series in the given directory. Series of top-level
+/// directories hold every file nested within them, while other series only hold the files
+/// directly within their directory, since nested directories are series of their own.
+pub fn get_series_files(
+	path: &Path,
+	library_options: &LibraryOptions,
+	ignore_rules: &IgnoreRules,
+) -> Vec<PathBuf> {
+	let recursive = library_options.series_strategy == SeriesStrategy::TopLevel;
+
+	walk_dir(path, library_options, ignore_rules, recursive)
+}
+
+/// Returns true if the path returned by `walk_dir` is a media, rather than a cover image
+/// or a file which isn't supported.
+fn is_media(path: &Path) -> bool {
+	path.is_dir() || (!path.should_ignore() && !path.is_thumbnail_img())
+}
+
+/// Returns true if the directory directly contains media which aren't ignored. Image
+/// folders are media belonging to the directory they are in, unless they sit at the root
+/// of the library, in which case they hold themselves.
+fn is_media_dir(
+	library_path: &Path,
+	path: &Path,
+	library_options: &LibraryOptions,
+	ignore_rules: &IgnoreRules,
+) -> bool {
+	if library_options.treat_image_folders_as_media {
+		if path.is_image_folder() {
+			return path.parent() == Some(library_path);
+		}
+
+		return ignore_rules.dir_has_media(path)
+			|| ignore_rules.dir_has_image_folders(path);
+	}
+
+	ignore_rules.dir_has_media(path)
+}
+
+/// Returns every directory of the library which directly contains media, sorted by path.
+fn find_media_dirs(
+	library_path: &Path,
+	library_options: &LibraryOptions,
+	ignore_rules: &IgnoreRules,
+) -> Vec<PathBuf> {
+	let mut dirs = WalkDir::new(library_path)
+		.into_iter()
+		.filter_entry(|e| e.path().is_dir() && !ignore_rules.is_ignored(e.path(), true))
+		.filter_map(|e| e.ok())
+		.par_bridge()
+		.filter(|entry| {
+			is_media_dir(library_path, entry.path(), library_options, ignore_rules)
+		})
+		.map(|entry| entry.path().to_path_buf())
+		.collect::<Vec<PathBuf>>();
+
+	dirs.sort();
+
+	dirs
+}
+
+/// Returns the deepest directory containing all of the files.
+fn get_common_dir(files: &[PathBuf], library_path: &Path) -> PathBuf {
+	let mut common = files
+		.first()
+		.and_then(|file| file.parent())
+		.unwrap_or(library_path)
+		.to_path_buf();
+
+	for file in files.iter().skip(1) {
+		while !file.starts_with(&common) {
+			if !common.pop() {
+				break;
+			}
+		}
+	}
+
+	common
+}
+
+/// The group of a media when grouping media by their ComicInfo.xml files.
+#[derive(Debug, PartialEq, Eq, Hash)]
+enum SeriesGroup {
+	/// The media names its series in the `<Series>` element of its ComicInfo.xml file.
+	Named(String),
+	/// The media doesn't name its series, so it belongs to the series of its directory.
+	/// Directories are grouped by their full path, since directories of the same name
+	/// (e.g. `Extras`) may hold unrelated media.
+	Directory(PathBuf),
+}
+
+impl SeriesGroup {
+	fn new(series_name: Option<String>, path: &Path, library_path: &Path) -> Self {
+		series_name
+			.map(|name| name.trim().to_string())
+			.filter(|name| !name.is_empty())
+			.map(SeriesGroup::Named)
+			.unwrap_or_else(|| {
+				SeriesGroup::Directory(
+					path.parent().unwrap_or(library_path).to_path_buf(),
+				)
+			})
+	}
+
+	/// Returns the name of the series of the group.
+	fn name(&self) -> String {
+		match self {
+			SeriesGroup::Named(name) => name.clone(),
+			SeriesGroup::Directory(dir) => get_dir_name(dir),
+		}
+	}
+}
+
+/// Groups the media of a library by the `<Series>` element of their ComicInfo.xml file.
+/// Media without one are grouped by their directory, and named after it. Cover images are
+/// given to the series whose directory they are in.
+fn detect_comic_info_series(
+	library_path: &Path,
+	library_options: &LibraryOptions,
+	ignore_rules: &IgnoreRules,
+) -> Vec<DetectedSeries> {
+	let (covers, files): (Vec<PathBuf>, Vec<PathBuf>) =
+		walk_dir(library_path, library_options, ignore_rules, true)
+			.into_iter()
+			.partition(|path| !path.is_dir() && path.is_thumbnail_img());
+
+	let grouped_media = files
+		.into_par_iter()
+		.filter(|path| is_media(path))
+		.map(|path| {
+			let series_name = media_file::read_comic_info(&path)
+				.unwrap_or_else(|e| {
+					log::debug!("Failed to read ComicInfo.xml of {:?}: {}", path, e);
+					None
+				})
+				.and_then(|metadata| metadata.series);
+
+			(SeriesGroup::new(series_name, &path, library_path), path)
+		})
+		.collect::<Vec<(SeriesGroup, PathBuf)>>();
+
+	let mut groups = HashMap::<SeriesGroup, Vec<PathBuf>>::new();
+
+	for (group, path) in grouped_media {
+		groups.entry(group).or_default().push(path);
+	}
+
+	groups
+		.into_iter()
+		.map(|(group, mut files)| {
+			let name = group.name();
+			let path = get_common_dir(&files, library_path);
+
+			files.extend(
+				covers
+					.iter()
+					.filter(|cover| cover.parent() == Some(path.as_path()))
+					.cloned(),
+			);
+
+			DetectedSeries { name, path, files }
+		})
+		.collect()
+}
+
+/// Detects the series of a library according to its series strategy.
+pub fn detect_series(
+	library_path: &Path,
+	library_options: &LibraryOptions,
+	ignore_rules: &IgnoreRules,
+) -> Vec<DetectedSeries> {
+	match library_options.series_strategy {
+		SeriesStrategy::Directory => {
+			find_media_dirs(library_path, library_options, ignore_rules)
+				.into_par_iter()
+				.map(|dir| DetectedSeries::from_dir(dir, library_options, ignore_rules))
+				.collect()
+		},
+		SeriesStrategy::Leaf => {
+			let dirs = find_media_dirs(library_path, library_options, ignore_rules);
+
+			// Note: the directories are sorted, so the nested directories of a directory
+			// always directly follow it.
+			let leaf_dirs = dirs
+				.iter()
+				.enumerate()
+				.filter(|(i, dir)| {
+					dirs.get(i + 1)
+						.map(|next| !next.starts_with(dir))
+						.unwrap_or(true)
+				})
+				.map(|(_, dir)| dir.clone())
+				.collect::<Vec<PathBuf>>();
+
+			leaf_dirs
+				.into_par_iter()
+				.map(|dir| DetectedSeries::from_dir(dir, library_options, ignore_rules))
+				.collect()
+		},
+		SeriesStrategy::TopLevel => {
+			let dirs = match std::fs::read_dir(library_path) {
+				Ok(items) => items
+					.filter_map(|item| item.ok())
+					.map(|item| item.path())
+					.filter(|path| path.is_dir() && !ignore_rules.is_ignored(path, true))
+					.collect::<Vec<PathBuf>>(),
+				Err(e) => {
+					log::error!("Failed to read directory {:?}: {}", library_path, e);
+					vec![]
+				},
+			};
+
+			dirs.into_par_iter()
+				.map(|dir| DetectedSeries::from_dir(dir, library_options, ignore_rules))
+				.filter(|series| series.files.iter().any(|file| is_media(file)))
+				.collect()
+		},
+		SeriesStrategy::ComicInfo => {
+			detect_comic_info_series(library_path, library_options, ignore_rules)
+		},
+	}
+}
+
+/// Returns the directory of the series a changed directory of the library belongs to,
+/// e.g. the top-level directory it is nested in. Series grouped by their ComicInfo.xml
+/// files don't belong to a single directory, so `None` is returned for them.
+pub fn get_series_dir(
+	library_path: &Path,
+	path: &Path,
+	library_options: &LibraryOptions,
+) -> Option<PathBuf> {
+	match library_options.series_strategy {
+		SeriesStrategy::ComicInfo => None,
+		SeriesStrategy::TopLevel => path
+			.strip_prefix(library_path)
+			.ok()?
+			.components()
+			.next()
+			.map(|component| library_path.join(component)),
+		// Note: image folders belong to the series of their parent directory, unless they
+		// sit at the root of the library.
+		_ if library_options.treat_image_folders_as_media
+			&& path.is_image_folder()
+			&& path.parent() != Some(library_path) =>
+		{
+			path.parent().map(|parent| parent.to_path_buf())
+		},
+		_ => Some(path.to_path_buf()),
+	}
+}
+
+/// Returns the directory a scan of a changed directory of the library actually covers:
+/// the directory of the series it belongs to, or the entire library for series grouped by
+/// their ComicInfo.xml files. See `get_series_dir`.
+pub fn get_scan_root(
+	library_path: &Path,
+	path: &Path,
+	library_options: &LibraryOptions,
+) -> PathBuf {
+	get_series_dir(library_path, path, library_options)
+		.unwrap_or_else(|| library_path.to_path_buf())
+}
+
+/// Returns true if the directory holds a series of the library, according to its series
+/// strategy. See `detect_series`.
+pub fn is_series_dir(
+	library_path: &Path,
+	path: &Path,
+	library_options: &LibraryOptions,
+	ignore_rules: &IgnoreRules,
+) -> bool {
+	if ignore_rules.is_ignored(path, true) {
+		return false;
+	}
+
+	match library_options.series_strategy {
+		SeriesStrategy::Directory => {
+			is_media_dir(library_path, path, library_options, ignore_rules)
+		},
+		SeriesStrategy::Leaf => {
+			is_media_dir(library_path, path, library_options, ignore_rules)
+				&& !find_media_dirs(path, library_options, ignore_rules)
+					.iter()
+					.any(|dir| dir != path)
+		},
+		SeriesStrategy::TopLevel => {
+			path.parent() == Some(library_path)
+				&& get_series_files(path, library_options, ignore_rules)
+					.iter()
+					.any(|file| is_media(file))
+		},
+		SeriesStrategy::ComicInfo => false,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn common_dir_of_files() {
+		let library_path = Path::new("/library");
+
+		assert_eq!(
+			get_common_dir(
+				&[
+					PathBuf::from("/library/Batman/Vol 1/Batman 01.cbz"),
+					PathBuf::from("/library/Batman/Vol 2/Batman 07.cbz"),
+				],
+				library_path
+			),
+			PathBuf::from("/library/Batman")
+		);
+		assert_eq!(
+			get_common_dir(
+				&[PathBuf::from("/library/Batman/Vol 1/Batman 01.cbz")],
+				library_path
+			),
+			PathBuf::from("/library/Batman/Vol 1")
+		);
+	}
+
+	#[test]
+	fn groups_unnamed_media_by_full_dir() {
+		let library_path = Path::new("/library");
+
+		let batman_extras = SeriesGroup::new(
+			None,
+			Path::new("/library/Batman/Extras/Sketches.cbz"),
+			library_path,
+		);
+		let superman_extras = SeriesGroup::new(
+			Some(" ".to_string()),
+			Path::new("/library/Superman/Extras/Sketches.cbz"),
+			library_path,
+		);
+
+		assert_ne!(batman_extras, superman_extras);
+		assert_eq!(batman_extras.name(), "Extras");
+		assert_eq!(superman_extras.name(), "Extras");
+
+		assert_eq!(
+			SeriesGroup::new(
+				Some(" Batman ".to_string()),
+				Path::new("/library/Batman/Extras/Sketches.cbz"),
+				library_path,
+			),
+			SeriesGroup::Named("Batman".to_string())
+		);
+	}
+
+	#[test]
+	fn series_dir_of_top_level_strategy() {
+		let library_path = Path::new("/library");
+		let library_options = LibraryOptions {
+			series_strategy: SeriesStrategy::TopLevel,
+			..Default::default()
+		};
+
+		assert_eq!(
+			get_series_dir(
+				library_path,
+				Path::new("/library/Batman/Vol 1"),
+				&library_options
+			),
+			Some(PathBuf::from("/library/Batman"))
+		);
+		assert_eq!(
+			get_series_dir(library_path, library_path, &library_options),
+			None
+		);
+	}
+}
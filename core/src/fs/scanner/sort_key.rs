@@ -0,0 +1,182 @@
+use crate::types::models::media::MediaMetadata;
+
+/// The range of sort keys covered by a single volume, i.e. the key of a media is its
+/// volume multiplied by this, plus its number.
+const VOLUME_RANGE: f64 = 10_000.0;
+
+/// Added to the key of annuals, so that they are keyed within their own range past every
+/// regular issue, rather than between them.
+const ANNUAL_OFFSET: f64 = 1_000_000_000_000.0;
+
+/// The key of media whose volume and number can't be derived, past every other key so
+/// that they sort last (and by name among themselves) rather than before the first issue.
+pub const UNKEYED_SORT_KEY: f64 = 2_000_000_000_000.0;
+
+/// The prefixes marking the volume of a media within its file name. Longer prefixes come
+/// first, since they would otherwise be matched by the shorter ones.
+const VOLUME_MARKERS: [&str; 4] = ["volume", "vol.", "vol", "v"];
+
+/// The prefixes marking the number (issue or chapter) of a media within its file name.
+const NUMBER_MARKERS: [&str; 8] =
+	["chapter", "issue", "ch.", "no.", "ch", "no", "c", "#"];
+
+/// Parses a volume or issue number, e.g. "012" or "12.5". Anything else, including
+/// numbers with a sign or an exponent, is rejected.
+fn parse_number(value: &str) -> Option<f64> {
+	let value = value.trim();
+
+	if value.is_empty()
+		|| !value.starts_with(|c: char| c.is_ascii_digit())
+		|| !value.chars().all(|c| c.is_ascii_digit() || c == '.')
+	{
+		return None;
+	}
+
+	value.parse::<f64>().ok()
+}
+
+/// Removes the parts of a name within parentheses or brackets, e.g. "(2016)" or
+/// "[Digital]", since the numbers they contain are never the issue number.
+fn strip_brackets(name: &str) -> String {
+	let mut depth = 0;
+
+	name.chars()
+		.filter(|c| match c {
+			'(' | '[' | '{' => {
+				depth += 1;
+				false
+			},
+			')' | ']' | '}' => {
+				depth = (depth - 1).max(0);
+				false
+			},
+			_ => depth == 0,
+		})
+		.collect()
+}
+
+/// Returns the number following a marker within the words of a name, which is either
+/// part of the word itself (e.g. "v03") or the next word (e.g. "Vol. 3").
+fn find_marked_number(words: &[&str], markers: &[&str]) -> Option<f64> {
+	words.iter().enumerate().find_map(|(index, word)| {
+		let rest = markers
+			.iter()
+			.find_map(|marker| word.strip_prefix(marker))?;
+
+		if rest.is_empty() {
+			words.get(index + 1).and_then(|next| parse_number(next))
+		} else {
+			parse_number(rest)
+		}
+	})
+}
+
+/// Parses the volume and number of a media from its name, e.g. "Saga v03 c045" or
+/// "Batman #012 (2016)". Without an explicit number marker, the last number of the name
+/// is used, e.g. "Batman 012".
+fn parse_name(name: &str) -> (Option<f64>, Option<f64>) {
+	let name = strip_brackets(&name.to_lowercase());
+	let words = name
+		.split(|c: char| c.is_whitespace() || c == '_' || c == '-')
+		.filter(|word| !word.is_empty())
+		.collect::<Vec<&str>>();
+
+	let volume = find_marked_number(&words, &VOLUME_MARKERS);
+	let number = find_marked_number(&words, &NUMBER_MARKERS).or_else(|| {
+		words
+			.iter()
+			.rev()
+			.find_map(|word| parse_number(word))
+			// Note: a lone volume (e.g. "Saga v03") isn't a number as well.
+			.filter(|_| volume.is_none())
+	});
+
+	(volume, number)
+}
+
+/// Returns true if the media is an annual, based on its name or ComicInfo.xml title.
+fn is_annual(name: &str, metadata: Option<&MediaMetadata>) -> bool {
+	let title = metadata
+		.and_then(|m| m.title.as_deref())
+		.unwrap_or_default();
+
+	[name, title].iter().any(|value| {
+		value
+			.to_lowercase()
+			.split(|c: char| !c.is_alphanumeric())
+			.any(|word| word == "annual")
+	})
+}
+
+/// Returns the key used to order a media within its series, derived from its volume and
+/// number. The `<Number>` and `<Volume>` elements of its ComicInfo.xml take precedence
+/// over the ones parsed from its name. Media without either have no key, and are stored
+/// with `UNKEYED_SORT_KEY` instead.
+pub fn get_sort_key(name: &str, metadata: Option<&MediaMetadata>) -> Option<f64> {
+	let (name_volume, name_number) = parse_name(name);
+
+	let volume = metadata
+		.and_then(|m| m.volume)
+		.map(f64::from)
+		.or(name_volume);
+	let number = metadata
+		.and_then(|m| m.number.as_deref())
+		.and_then(parse_number)
+		.or(name_number);
+
+	if volume.is_none() && number.is_none() {
+		return None;
+	}
+
+	let offset = if is_annual(name, metadata) {
+		ANNUAL_OFFSET
+	} else {
+		0.0
+	};
+
+	Some(volume.unwrap_or_default() * VOLUME_RANGE + offset + number.unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn sort_key_from_name() {
+		assert_eq!(get_sort_key("Batman #012 (2016)", None), Some(12.0));
+		assert_eq!(get_sort_key("Batman 010", None), Some(10.0));
+		assert_eq!(get_sort_key("Saga v03", None), Some(30_000.0));
+		assert_eq!(get_sort_key("Berserk_v03_c045", None), Some(30_045.0));
+		assert_eq!(
+			get_sort_key("Berserk Vol. 3 Ch. 12.5", None),
+			Some(30_012.5)
+		);
+		assert_eq!(
+			get_sort_key("Batman Annual 2", None),
+			Some(1_000_000_000_002.0)
+		);
+		assert!(
+			get_sort_key("Batman 5001", None) < get_sort_key("Batman Annual 1", None)
+		);
+		assert_eq!(get_sort_key("Batman - The Killing Joke", None), None);
+		assert_eq!(get_sort_key("Batman vs Superman (2016)", None), None);
+	}
+
+	#[test]
+	fn sort_key_prefers_comic_info() {
+		let metadata = MediaMetadata {
+			number: Some("2".to_string()),
+			volume: Some(1),
+			..Default::default()
+		};
+
+		assert_eq!(get_sort_key("Batman 010", Some(&metadata)), Some(10_002.0));
+		// Note: numbers which can't be parsed fall back to the name.
+		let metadata = MediaMetadata {
+			number: Some("½".to_string()),
+			..Default::default()
+		};
+
+		assert_eq!(get_sort_key("Batman 010", Some(&metadata)), Some(10.0));
+	}
+}
@@ -1,16 +1,24 @@
-use std::path::Path;
+use std::{
+	collections::{HashMap, HashSet},
+	path::{Path, PathBuf},
+};
 
-use prisma_client_rust::{raw, PrismaValue, QueryError};
+use prisma_client_rust::{
+	chrono::{DateTime, FixedOffset, Utc},
+	raw, PrismaValue, QueryError,
+};
+use rocket::tokio;
 use walkdir::DirEntry;
 
 use crate::{
 	config::context::Ctx,
 	event::ClientEvent,
-	fs::{image, media_file},
-	prisma::{library, media, series},
+	fs::{image, image_folder, media_file},
+	job::control::JobControl,
+	prisma::{library, media, media_metadata, media_page, series},
 	types::{
 		enums::FileStatus,
-		errors::{ApiError, ScanError},
+		errors::{ApiError, ProcessFileError, ScanError},
 		models::{
 			library::LibraryOptions,
 			media::{MediaMetadata, TentativeMedia},
@@ -18,7 +26,11 @@ use crate::{
 	},
 };
 
-use super::BatchScanOperation;
+use super::{
+	series_detection::DetectedSeries,
+	sort_key::{get_sort_key, UNKEYED_SORT_KEY},
+	BatchScanOperation, ScanSummary,
+};
 
 /// Will mark all series and media within the library as MISSING. Requires the
 /// series and series.media relations to have been loaded to function properly.
@@ -59,6 +71,54 @@ pub async fn mark_library_missing(
 	Ok(())
 }
 
+/// Returns the name, extension and size (in bytes) of a media file.
+fn get_media_file_details(path: &Path) -> (String, String, i32) {
+	// EW, I hate that I need to do this over and over lol time to make a trait for Path.
+	// Note: image folders don't have an extension, so the entire name is used.
+	let name = if path.is_dir() {
+		path.file_name()
+	} else {
+		path.file_stem()
+	}
+	.unwrap_or_default()
+	.to_str()
+	.unwrap_or_default()
+	.to_string();
+
+	let ext = if path.is_dir() {
+		String::default()
+	} else {
+		path.extension()
+			.unwrap_or_default()
+			.to_str()
+			.unwrap_or_default()
+			.to_string()
+	};
+
+	// Note: make this return a tuple if I need to grab anything else from metadata.
+	let size = match path.metadata() {
+		Ok(metadata) if metadata.is_dir() => image_folder::get_folder_size(path),
+		Ok(metadata) => metadata.len(),
+		_ => 0,
+	};
+
+	let size = size.try_into().unwrap_or_else(|e| {
+		log::error!("Failed to calculate file size: {:?}", e);
+
+		0
+	});
+
+	(name, ext, size)
+}
+
+/// Returns the last modified time of a media file, if available.
+pub fn get_modified_at(path: &Path) -> Option<DateTime<FixedOffset>> {
+	path.metadata()
+		.and_then(|metadata| metadata.modified())
+		.map(|modified| DateTime::<Utc>::from(modified).into())
+		.ok()
+}
+
 pub fn get_tentative_media(
 	path: &Path,
 	series_id: String,
@@ -71,80 +131,579 @@ pub fn get_tentative_media(
 
 	let path_str = path.to_str().unwrap_or_default().to_string();
 
-	// EW, I hate that I need to do this over and over lol time to make a trait for Path.
-	let name = path
-		.file_stem()
-		.unwrap_or_default()
-		.to_str()
-		.unwrap_or_default()
-		.to_string();
-
-	let ext = path
-		.extension()
-		.unwrap_or_default()
-		.to_str()
-		.unwrap_or_default()
-		.to_string();
-
-	// Note: make this return a tuple if I need to grab anything else from metadata.
-	let size = match path.metadata() {
-		Ok(metadata) => metadata.len(),
-		_ => 0,
-	};
+	let (name, ext, size) = get_media_file_details(path);
 
 	let comic_info = processed_entry.metadata.unwrap_or(MediaMetadata::default());
+	let sort_key = get_sort_key(&name, Some(&comic_info)).unwrap_or(UNKEYED_SORT_KEY);
+
+	// Note: a missing page index isn't fatal, pages are read from the file without it.
+	let page_index = media_file::index_pages(path).unwrap_or_else(|e| {
+		log::warn!("Failed to index pages of {:?}: {:?}", path, e);
+
+		vec![]
+	});
 
 	Ok(TentativeMedia {
 		name,
-		description: comic_info.summary,
-		size: size.try_into().unwrap_or_else(|e| {
-			log::error!("Failed to calculate file size: {:?}", e);
-
-			0
-		}),
+		description: comic_info.summary.clone(),
+		size,
 		extension: ext,
 		pages: match comic_info.page_count {
 			Some(count) => count as i32,
 			None => processed_entry.pages,
 		},
 		checksum: processed_entry.checksum,
+		modified_at: get_modified_at(path),
 		path: path_str,
 		series_id,
+		sort_key: Some(sort_key),
+		metadata: if comic_info == MediaMetadata::default() {
+			None
+		} else {
+			Some(comic_info)
+		},
+		page_index,
 	})
 }
 
+/// The outcome of inserting a new file found during a scan.
+pub enum InsertedMedia {
+	/// A new media was created for the file.
+	Created(media::Data),
+	/// The file belongs to an existing media which was moved (or renamed), so the media
+	/// was reattached to the file instead. `from` is the previous path of the media.
+	Moved { from: String, media: media::Data },
+	/// The file belongs to an existing media of another series, so the media was moved to
+	/// the series of the file instead.
+	Regrouped(media::Data),
+}
+
+/// Finds the media of a file which belongs to another series, e.g. after the series
+/// strategy of the library changed, or the ComicInfo.xml of the file now names another
+/// series.
+pub async fn find_regrouped_media(
+	ctx: &Ctx,
+	path: &str,
+	series_id: &str,
+) -> Result<Option<media::Data>, ScanError> {
+	Ok(ctx
+		.db
+		.media()
+		.find_many(vec![media::path::equals(path.to_string())])
+		.exec()
+		.await?
+		.into_iter()
+		.find(|media| media.series_id.as_deref() != Some(series_id)))
+}
+
+/// Moves a media to another series, keeping its id so that read progress, tags and
+/// thumbnails are kept. Media previously marked as MISSING (e.g. along with their
+/// previous series) are READY again.
+pub async fn regroup_media(
+	ctx: &Ctx,
+	media: &media::Data,
+	series_id: String,
+) -> Result<media::Data, ScanError> {
+	let media = ctx
+		.db
+		.media()
+		.update(
+			media::id::equals(media.id.clone()),
+			vec![
+				media::status::set(FileStatus::Ready.to_string()),
+				media::status_reason::set(None),
+				media::series::connect(series::id::equals(series_id)),
+			],
+		)
+		.exec()
+		.await?;
+
+	log::debug!(
+		"Media {} regrouped to series {:?}",
+		media.id,
+		media.series_id
+	);
+
+	Ok(media)
+}
+
+/// Finds the media a new file was moved (or renamed) from, i.e. a media of the same
+/// library with the same checksum and size whose own file no longer exists. Files without
+/// a checksum can't be matched reliably, so they are never considered moved.
+pub async fn find_moved_media(
+	ctx: &Ctx,
+	library_id: &str,
+	tentative_media: &TentativeMedia,
+) -> Result<Option<media::Data>, ScanError> {
+	let checksum = match tentative_media.checksum.as_ref() {
+		Some(checksum) => checksum,
+		None => return Ok(None),
+	};
+
+	Ok(ctx
+		.db
+		.media()
+		.find_many(vec![
+			media::checksum::equals(Some(checksum.clone())),
+			media::size::equals(tentative_media.size),
+			media::series::is(vec![series::library_id::equals(Some(
+				library_id.to_string(),
+			))]),
+		])
+		.exec()
+		.await?
+		.into_iter()
+		.find(|media| {
+			media.path != tentative_media.path && !Path::new(&media.path).exists()
+		}))
+}
+
+/// Reattaches a moved media to its new file, keeping its id so that read progress, tags
+/// and thumbnails are kept. The content of the file is unchanged, so its metadata and
+/// page index are kept as well. Media previously marked as MISSING are READY again.
+pub async fn reattach_media(
+	ctx: &Ctx,
+	media: &media::Data,
+	tentative_media: TentativeMedia,
+) -> Result<media::Data, ScanError> {
+	let media = ctx
+		.db
+		.media()
+		.update(
+			media::id::equals(media.id.clone()),
+			vec![
+				media::name::set(tentative_media.name),
+				media::path::set(tentative_media.path),
+				media::extension::set(tentative_media.extension),
+				media::modified_at::set(tentative_media.modified_at),
+				media::sort_key::set(tentative_media.sort_key),
+				media::status::set(FileStatus::Ready.to_string()),
+				media::status_reason::set(None),
+				media::series::connect(series::id::equals(tentative_media.series_id)),
+			],
+		)
+		.exec()
+		.await?;
+
+	log::debug!("Media {} reattached to {}", media.id, media.path);
+
+	Ok(media)
+}
+
+/// Inserts a new file found during a scan. If the file belongs to an existing media of
+/// another series (see `find_regrouped_media`), that media is moved to the series. If the
+/// file belongs to an existing media which was moved (see `find_moved_media`), that media
+/// is reattached to the file. Otherwise, a new media is created.
 pub async fn insert_media(
 	ctx: &Ctx,
 	path: &Path,
+	library_id: &str,
 	series_id: String,
 	library_options: &LibraryOptions,
-) -> Result<media::Data, ScanError> {
+) -> Result<InsertedMedia, ScanError> {
 	let path_str = path.to_str().unwrap_or_default().to_string();
 
-	let tentative_media = get_tentative_media(path, series_id, library_options)?;
+	if let Some(media) = find_regrouped_media(ctx, &path_str, &series_id).await? {
+		let media = regroup_media(ctx, &media, series_id).await?;
+
+		return Ok(InsertedMedia::Regrouped(media));
+	}
+
+	let mut tentative_media = get_tentative_media(path, series_id, library_options)?;
+
+	if let Some(moved_media) = find_moved_media(ctx, library_id, &tentative_media).await?
+	{
+		let media = reattach_media(ctx, &moved_media, tentative_media).await?;
+
+		return Ok(InsertedMedia::Moved {
+			from: moved_media.path,
+			media,
+		});
+	}
+
+	let metadata = tentative_media.metadata.take();
+	let page_index = std::mem::take(&mut tentative_media.page_index);
 	let create_action = tentative_media.into_action(ctx);
-	let media = create_action.exec().await?;
+	let mut media = create_action.exec().await?;
 
 	log::trace!("Media entity created: {:?}", media);
 
+	if let Some(metadata) = metadata {
+		let metadata = metadata.into_action(ctx, media.id.clone()).exec().await?;
+		log::trace!("Media metadata created: {:?}", metadata);
+	}
+
+	if !page_index.is_empty() {
+		let page_creates = page_index
+			.into_iter()
+			.map(|page| page.into_action(ctx, media.id.clone()));
+
+		let pages = ctx.db._batch(page_creates).await?;
+		log::trace!("Created page index with {} pages", pages.len());
+
+		media.page_index = Some(pages);
+	}
+
+	// Note: the media is usable without a thumbnail, so failing to create one shouldn't
+	// fail the media itself.
 	if library_options.create_webp_thumbnails {
-		log::debug!("Attempting to create WEBP thumbnail");
-		let thumbnail_path = image::generate_thumbnail(&media.id, &path_str)?;
-		log::debug!("Created WEBP thumbnail: {:?}", thumbnail_path);
+		log::debug!("Attempting to create thumbnail");
+		match image::generate_thumbnail(
+			&media.id,
+			&path_str,
+			image::find_indexed_page(&media, 1).as_ref(),
+			&library_options.get_thumbnail_options(),
+			false,
+		) {
+			Ok(thumbnail_path) => log::debug!("Created thumbnail: {:?}", thumbnail_path),
+			Err(e) => log::error!("Failed to create thumbnail for {}: {}", path_str, e),
+		}
 	}
 
 	log::debug!("Media for {} created successfully", path_str);
 
+	Ok(InsertedMedia::Created(media))
+}
+
+/// Returns the status a media should have when it failed to be processed.
+fn get_failure_status(error: &ScanError) -> FileStatus {
+	match error {
+		ScanError::UnsupportedFile(_) => FileStatus::Unsupported,
+		_ => FileStatus::Error,
+	}
+}
+
+/// Builds the create action for a media which failed to be processed. The media is still
+/// persisted, with the reason it failed, so that it can be listed and retried rather than
+/// silently left out of the library.
+pub fn failed_media_action<'a>(
+	ctx: &'a Ctx,
+	path: &Path,
+	series_id: String,
+	error: &ScanError,
+) -> media::Create<'a> {
+	let (name, ext, size) = get_media_file_details(path);
+	let sort_key = get_sort_key(&name, None).unwrap_or(UNKEYED_SORT_KEY);
+
+	ctx.db.media().create(
+		name,
+		size,
+		ext,
+		0,
+		path.to_str().unwrap_or_default().to_string(),
+		vec![
+			media::status::set(get_failure_status(error).to_string()),
+			media::status_reason::set(Some(error.to_string())),
+			media::modified_at::set(get_modified_at(path)),
+			media::sort_key::set(Some(sort_key)),
+			media::series::connect(series::id::equals(series_id)),
+		],
+	)
+}
+
+/// Persists a media which failed to be processed, see `failed_media_action`. If the media
+/// was already created before failing, it is flagged instead.
+pub async fn insert_failed_media(
+	ctx: &Ctx,
+	path: &Path,
+	series_id: String,
+	error: &ScanError,
+) -> Result<media::Data, ScanError> {
+	let path_str = path.to_str().unwrap_or_default().to_string();
+
+	let existing_media = ctx
+		.db
+		.media()
+		.find_first(vec![media::path::equals(path_str)])
+		.exec()
+		.await?;
+
+	let media = match existing_media {
+		Some(existing_media) => {
+			// Note: the size and modified time are updated so that the media is only
+			// processed again once its file changes.
+			let (_, _, size) = get_media_file_details(path);
+
+			ctx.db
+				.media()
+				.update(
+					media::id::equals(existing_media.id),
+					vec![
+						media::status::set(get_failure_status(error).to_string()),
+						media::status_reason::set(Some(error.to_string())),
+						media::size::set(size),
+						media::modified_at::set(get_modified_at(path)),
+					],
+				)
+				.exec()
+				.await?
+		},
+		None => {
+			failed_media_action(ctx, path, series_id, error)
+				.exec()
+				.await?
+		},
+	};
+
+	log::debug!("Persisted failed media: {:?}", media);
+
 	Ok(media)
 }
 
+/// Returns true if the file of a media has changed since it was last processed, based on
+/// its size and last modified time. Media without a stored modified time are only
+/// compared by size.
+pub fn has_media_changed(media: &media::Data, path: &Path) -> bool {
+	let (_, _, size) = get_media_file_details(path);
+
+	if size != media.size {
+		return true;
+	}
+
+	match (media.modified_at, get_modified_at(path)) {
+		// Note: the modified time is compared to the second, since that is all some
+		// filesystems (and the database) can be relied upon to store.
+		(Some(stored), Some(current)) => stored.timestamp() != current.timestamp(),
+		_ => false,
+	}
+}
+
+/// Records the last modified time of a media file, without processing it again. Used for
+/// media processed before the modified time was tracked, so that later changes to their
+/// file can be detected.
+pub async fn set_media_modified_at(
+	ctx: &Ctx,
+	media: &media::Data,
+) -> Result<media::Data, ScanError> {
+	Ok(ctx
+		.db
+		.media()
+		.update(
+			media::id::equals(media.id.clone()),
+			vec![media::modified_at::set(get_modified_at(Path::new(
+				&media.path,
+			)))],
+		)
+		.exec()
+		.await?)
+}
+
+/// Derives the sort key of a media processed before sort keys were tracked, from its name
+/// and stored metadata, without processing it again. See `get_sort_key`. Media without a
+/// derivable key are given `UNKEYED_SORT_KEY`, so that they aren't derived again on every
+/// scan.
+pub async fn set_media_sort_key(
+	ctx: &Ctx,
+	media: &media::Data,
+) -> Result<media::Data, ScanError> {
+	let metadata: Option<MediaMetadata> = ctx
+		.db
+		.media_metadata()
+		.find_unique(media_metadata::media_id::equals(media.id.clone()))
+		.exec()
+		.await?
+		.map(|metadata| metadata.into());
+
+	let sort_key =
+		get_sort_key(&media.name, metadata.as_ref()).unwrap_or(UNKEYED_SORT_KEY);
+
+	Ok(ctx
+		.db
+		.media()
+		.update(
+			media::id::equals(media.id.clone()),
+			vec![media::sort_key::set(Some(sort_key))],
+		)
+		.exec()
+		.await?)
+}
+
+/// Processes a media again, e.g. once its file has changed, updating it in place so that
+/// any read progress and tags are kept. The metadata and page index of the media are
+/// rebuilt, and its thumbnail is regenerated (or removed, to be generated on demand). If
+/// processing fails, the media is flagged with the reason instead.
+pub async fn reprocess_media(
+	ctx: &Ctx,
+	media: &media::Data,
+	library_options: &LibraryOptions,
+) -> Result<media::Data, ScanError> {
+	let db = ctx.get_db();
+	let path = Path::new(&media.path);
+
+	// Note: processing reads the whole file, so it shouldn't block the async runtime.
+	let processing_path = path.to_path_buf();
+	let series_id = media.series_id.clone().unwrap_or_default();
+	let processing_options = library_options.clone();
+	let result = tokio::task::spawn_blocking(move || {
+		get_tentative_media(&processing_path, series_id, &processing_options)
+	})
+	.await
+	.map_err(|e| ScanError::Unknown(e.to_string()))?;
+
+	let tentative_media = match result {
+		Ok(tentative_media) => tentative_media,
+		Err(e) => {
+			log::debug!("Failed to process {} again: {}", media.path, e);
+
+			return insert_failed_media(
+				ctx,
+				path,
+				media.series_id.clone().unwrap_or_default(),
+				&e,
+			)
+			.await;
+		},
+	};
+
+	let mut updated_media = db
+		.media()
+		.update(
+			media::id::equals(media.id.clone()),
+			vec![
+				media::name::set(tentative_media.name),
+				media::description::set(tentative_media.description),
+				media::size::set(tentative_media.size),
+				media::extension::set(tentative_media.extension),
+				media::pages::set(tentative_media.pages),
+				media::checksum::set(tentative_media.checksum),
+				media::modified_at::set(tentative_media.modified_at),
+				media::sort_key::set(tentative_media.sort_key),
+				media::status::set(FileStatus::Ready.to_string()),
+				media::status_reason::set(None),
+			],
+		)
+		.exec()
+		.await?;
+
+	db.media_metadata()
+		.delete_many(vec![media_metadata::media_id::equals(media.id.clone())])
+		.exec()
+		.await?;
+
+	if let Some(metadata) = tentative_media.metadata {
+		metadata.into_action(ctx, media.id.clone()).exec().await?;
+	}
+
+	db.media_page()
+		.delete_many(vec![media_page::media_id::equals(media.id.clone())])
+		.exec()
+		.await?;
+
+	if !tentative_media.page_index.is_empty() {
+		let page_creates = tentative_media
+			.page_index
+			.into_iter()
+			.map(|page| page.into_action(ctx, media.id.clone()));
+
+		updated_media.page_index = Some(db._batch(page_creates).await?);
+	}
+
+	let thumbnail_media = updated_media.clone();
+	let create_webp_thumbnails = library_options.create_webp_thumbnails;
+	let thumbnail_options = library_options.get_thumbnail_options();
+	let thumbnail_result = tokio::task::spawn_blocking(move || {
+		if create_webp_thumbnails {
+			image::generate_media_thumbnail(&thumbnail_media, &thumbnail_options)
+				.map(|_| ())
+		} else {
+			image::remove_thumbnail(&thumbnail_media.id)
+		}
+	})
+	.await
+	.unwrap_or_else(|e| Err(ProcessFileError::Unknown(e.to_string())));
+
+	if let Err(e) = thumbnail_result {
+		log::error!("Failed to update thumbnail for {}: {}", media.path, e);
+	}
+
+	log::debug!("Media for {} processed successfully", media.path);
+
+	Ok(updated_media)
+}
+
+/// Processes a media which previously failed again, see `reprocess_media`. The file is
+/// verified first, since a media may have been flagged by the `LibraryVerificationJob`
+/// rather than during a scan. If it fails again, the media keeps its failed status along
+/// with the new reason.
+pub async fn retry_failed_media(
+	ctx: &Ctx,
+	media: &media::Data,
+	library_options: &LibraryOptions,
+) -> Result<media::Data, ScanError> {
+	let path = Path::new(&media.path);
+
+	// Note: verifying reads every entry of the file, so it shouldn't block the async
+	// runtime.
+	let verify_path = path.to_path_buf();
+	let verified = tokio::task::spawn_blocking(move || media_file::verify(&verify_path))
+		.await
+		.map_err(|e| ScanError::Unknown(e.to_string()))?;
+
+	if let Err(e) = verified {
+		log::debug!("Failed to verify {} again: {}", media.path, e);
+
+		return insert_failed_media(
+			ctx,
+			path,
+			media.series_id.clone().unwrap_or_default(),
+			&ScanError::from(e),
+		)
+		.await;
+	}
+
+	reprocess_media(ctx, media, library_options).await
+}
+
+/// Records the override image found within the directory of a series (ex: cover.jpg) as
+/// the cover of the series, and regenerates the thumbnail of the series when it changes.
+/// Covers uploaded through the API take precedence, so they are never replaced by a scan.
+pub async fn sync_series_cover(
+	ctx: &Ctx,
+	series: &series::Data,
+	found_cover: Option<PathBuf>,
+	library_options: &LibraryOptions,
+) -> Result<(), ScanError> {
+	if let Some(cover_path) = series.cover_path.as_deref() {
+		if image::is_uploaded_cover(cover_path) && Path::new(cover_path).exists() {
+			log::trace!("Keeping uploaded cover for series {}", series.id);
+			return Ok(());
+		}
+	}
+
+	let found_cover = found_cover.map(|path| path.to_string_lossy().to_string());
+
+	if found_cover == series.cover_path
+		&& (found_cover.is_none() || image::get_thumbnail_path(&series.id).is_some())
+	{
+		return Ok(());
+	}
+
+	log::debug!(
+		"Updating cover of series {} to {:?}",
+		series.id,
+		found_cover
+	);
+
+	let series = ctx
+		.db
+		.series()
+		.update(
+			series::id::equals(series.id.clone()),
+			vec![series::cover_path::set(found_cover)],
+		)
+		.exec()
+		.await?;
+
+	image::generate_series_thumbnail(&series, &library_options.get_thumbnail_options())?;
+
+	Ok(())
+}
+
 pub async fn insert_series(
 	ctx: &Ctx,
-	entry: &DirEntry,
+	path: &Path,
 	library_id: String,
 ) -> Result<series::Data, ScanError> {
-	let path = entry.path();
-
 	// TODO: use this??
 	// let metadata = match path.metadata() {
 	// 	Ok(metadata) => Some(metadata),
@@ -193,7 +752,7 @@ pub async fn insert_series_many(
 	let mut inserted_series = vec![];
 
 	for entry in entries {
-		match insert_series(&ctx, &entry, library_id.clone()).await {
+		match insert_series(&ctx, entry.path(), library_id.clone()).await {
 			Ok(series) => {
 				ctx.emit_client_event(ClientEvent::CreatedSeries(series.clone()));
 
@@ -210,25 +769,13 @@ pub async fn insert_series_many(
 
 pub async fn insert_series_batch(
 	ctx: &Ctx,
-	entries: Vec<DirEntry>,
+	detected_series: &[DetectedSeries],
 	library_id: String,
 ) -> Result<Vec<series::Data>, ApiError> {
-	let series_creates = entries.into_iter().map(|entry| {
-		let path = entry.path();
-
-		// TODO: figure out how to do this in the safest way possible...
-		let name = path
-			.file_name()
-			.unwrap_or_default()
-			.to_str()
-			.unwrap_or_default()
-			.to_string();
-
-		// TODO: change this to a Result, then filter map on the iterator and
-		// log the Err values...
+	let series_creates = detected_series.iter().map(|detected| {
 		ctx.db.series().create(
-			name,
-			path.to_str().unwrap_or_default().to_string(),
+			detected.name.clone(),
+			detected.path.to_string_lossy().to_string(),
 			vec![series::library::connect(library::id::equals(
 				library_id.clone(),
 			))],
@@ -249,64 +796,225 @@ pub async fn mark_media_missing(
 	db.media()
 		.update_many(
 			vec![media::path::in_vec(paths)],
-			vec![media::status::set(FileStatus::Missing.to_string())],
+			vec![
+				media::status::set(FileStatus::Missing.to_string()),
+				media::status_reason::set(Some(
+					"The file could not be found during the last scan".to_string(),
+				)),
+			],
 		)
 		.exec()
 		.await
 }
 
+/// Applies the operations collected while scanning a library in batch mode. The job is
+/// checked for a pause or cancellation between each media, and once cancelled, only the
+/// media processed so far are persisted. Returns the created media, along with a summary
+/// of the create and update operations which were completed.
 pub async fn batch_media_operations(
 	ctx: &Ctx,
+	runner_id: &str,
+	library_id: &str,
 	operations: Vec<BatchScanOperation>,
 	library_options: &LibraryOptions,
-) -> Result<Vec<media::Data>, ScanError> {
-	// Note: this won't work if I add any other operations...
-	let (create_operations, mark_missing_operations): (Vec<_>, Vec<_>) =
-		operations.into_iter().partition(|operation| {
-			matches!(
-				operation,
-				BatchScanOperation::CreateMedia {
-					path: _,
-					series_id: __
-				}
-			)
-		});
+	control: &JobControl,
+) -> Result<(Vec<media::Data>, ScanSummary), ScanError> {
+	let mut create_operations = vec![];
+	let mut updated_media = vec![];
+	let mut missing_paths = vec![];
+
+	for operation in operations {
+		match operation {
+			BatchScanOperation::CreateMedia { path, series_id } => {
+				create_operations.push((path, series_id))
+			},
+			BatchScanOperation::UpdateMedia { media } => updated_media.push(media),
+			BatchScanOperation::MarkMediaMissing { path } => missing_paths.push(path),
+		}
+	}
 
-	let media_creates = create_operations
-		.into_iter()
-		.map(|operation| {
-			match operation {
-				BatchScanOperation::CreateMedia { path, series_id } => {
-					// let result = insert_media(&ctx, &path, series_id, &library_options).await;
-					get_tentative_media(&path, series_id, library_options)
+	// Note: files of media which moved to another series are both created in their new
+	// series and missing from their previous one, so they are regrouped before either.
+	let mut regrouped_paths = HashSet::new();
+	let mut new_files = vec![];
+	let mut completed_tasks = 0;
+
+	for (path, series_id) in create_operations {
+		if !control.checkpoint().await {
+			break;
+		}
+
+		let path_str = path.to_string_lossy().to_string();
+
+		match find_regrouped_media(ctx, &path_str, &series_id).await {
+			Ok(Some(media)) => match regroup_media(ctx, &media, series_id).await {
+				Ok(media) => {
+					completed_tasks += 1;
+					regrouped_paths.insert(path_str);
+					ctx.emit_client_event(ClientEvent::UpdatedMedia(media));
 				},
-				_ => unreachable!(),
-			}
-		})
-		.filter_map(|res| match res {
-			Ok(entry) => Some(entry.into_action(ctx)),
+				Err(e) => log::error!("Failed to regroup media {}: {:?}", path_str, e),
+			},
+			Ok(None) => new_files.push((path, series_id)),
+			Err(e) => {
+				log::error!("Failed to find regrouped media: {:?}", e);
+				new_files.push((path, series_id));
+			},
+		}
+	}
+
+	missing_paths.retain(|path| !regrouped_paths.contains(path));
+
+	// Note: metadata and page indexes can only be created once the media exists, so they
+	// are tracked by path until the media has been created.
+	let mut metadata_map = HashMap::new();
+	let mut page_index_map = HashMap::new();
+
+	let mut failed_creates = vec![];
+	let mut tentative_media = vec![];
+
+	for (path, series_id) in new_files {
+		if !control.checkpoint().await {
+			break;
+		}
+
+		match get_tentative_media(&path, series_id.clone(), library_options) {
+			Ok(entry) => tentative_media.push(entry),
 			Err(e) => {
 				log::error!("Failed to create media: {:?}", e);
 
-				None
+				failed_creates.push(failed_media_action(ctx, &path, series_id, &e));
 			},
-		});
+		}
+	}
 
-	let missing_paths = mark_missing_operations
-		.into_iter()
-		.map(|operation| match operation {
-			BatchScanOperation::MarkMediaMissing { path } => path,
-			_ => unreachable!(),
+	let mut moved_media = 0;
+	let mut media_creates = vec![];
+
+	// Note: moved media are matched one at a time, so that a media is never reattached to
+	// more than one file.
+	for mut entry in tentative_media {
+		if !control.checkpoint().await {
+			break;
+		}
+
+		match find_moved_media(ctx, library_id, &entry).await {
+			Ok(Some(media)) => {
+				match reattach_media(ctx, &media, entry).await {
+					Ok(moved) => {
+						completed_tasks += 1;
+						moved_media += 1;
+
+						ctx.handle_log_event(ClientEvent::MovedMedia {
+							runner_id: Some(runner_id.to_string()),
+							from: media.path,
+							to: moved.path,
+						})
+						.await;
+					},
+					Err(e) => log::error!("Failed to reattach moved media: {:?}", e),
+				}
+
+				continue;
+			},
+			Ok(None) => {},
+			Err(e) => log::error!("Failed to find moved media: {:?}", e),
+		}
+
+		if let Some(metadata) = entry.metadata.take() {
+			metadata_map.insert(entry.path.clone(), metadata);
+		}
+
+		if !entry.page_index.is_empty() {
+			page_index_map
+				.insert(entry.path.clone(), std::mem::take(&mut entry.page_index));
+		}
+
+		media_creates.push(entry.into_action(ctx));
+	}
+
+	// Note: media are updated in place, which can't be batched like the creates are.
+	for media in updated_media {
+		if !control.checkpoint().await {
+			break;
+		}
+
+		match reprocess_media(ctx, &media, library_options).await {
+			Ok(media) => {
+				completed_tasks += 1;
+				ctx.emit_client_event(ClientEvent::UpdatedMedia(media));
+			},
+			Err(e) => log::error!("Failed to update media {}: {:?}", media.path, e),
+		}
+	}
+
+	// Note: the files of a cancelled scan which weren't regrouped yet may belong to media
+	// which would otherwise be marked as MISSING.
+	if !control.is_cancelled() {
+		let result = mark_media_missing(ctx, missing_paths).await;
+
+		if let Err(err) = result {
+			log::error!("Failed to mark media as MISSING: {:?}", err);
+		} else {
+			log::debug!("Marked {} media as MISSING", result.unwrap());
+		}
+	}
+
+	let mut created_media = ctx.db._batch(media_creates).await?;
+
+	completed_tasks += created_media.len() as u64;
+
+	let metadata_creates = created_media.iter().filter_map(|media| {
+		metadata_map
+			.remove(&media.path)
+			.map(|metadata| metadata.into_action(ctx, media.id.clone()))
+	});
+
+	if let Err(err) = ctx.db._batch(metadata_creates).await {
+		log::error!("Failed to create media metadata: {:?}", err);
+	}
+
+	let page_creates = created_media
+		.iter()
+		.filter_map(|media| {
+			page_index_map.remove(&media.path).map(|page_index| {
+				page_index
+					.into_iter()
+					.map(move |page| page.into_action(ctx, media.id.clone()))
+			})
 		})
-		.collect::<Vec<String>>();
+		.flatten();
+
+	// Note: the created pages are attached to their media, so that thumbnails generated
+	// for the created media can read their first page through the page index.
+	match ctx.db._batch(page_creates).await {
+		Ok(pages) => {
+			let mut pages_by_media = HashMap::<String, Vec<media_page::Data>>::new();
+
+			for page in pages {
+				pages_by_media
+					.entry(page.media_id.clone())
+					.or_default()
+					.push(page);
+			}
 
-	let result = mark_media_missing(ctx, missing_paths).await;
+			for media in created_media.iter_mut() {
+				media.page_index = pages_by_media.remove(&media.id);
+			}
+		},
+		Err(err) => log::error!("Failed to create media page indexes: {:?}", err),
+	}
 
-	if let Err(err) = result {
-		log::error!("Failed to mark media as MISSING: {:?}", err);
-	} else {
-		log::debug!("Marked {} media as MISSING", result.unwrap());
+	match ctx.db._batch(failed_creates).await {
+		Ok(failed_media) => log::debug!("Persisted {} failed media", failed_media.len()),
+		Err(err) => log::error!("Failed to persist failed media: {:?}", err),
 	}
 
-	Ok(ctx.db._batch(media_creates).await?)
+	Ok((
+		created_media,
+		ScanSummary {
+			completed_tasks,
+			moved_media,
+		},
+	))
 }
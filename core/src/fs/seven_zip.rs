@@ -0,0 +1,340 @@
+use std::{
+	collections::HashMap,
+	fs::File,
+	io::{self, Read},
+	path::Path,
+};
+
+use sevenz_rust::{Password, SevenZArchiveEntry, SevenZReader};
+
+use crate::{
+	fs::media_file::{self, IsImage},
+	types::{
+		alias::ProcessFileResult,
+		errors::ProcessFileError,
+		http,
+		models::{media::ProcessedMediaFile, page::MediaPage},
+	},
+};
+
+use super::{
+	checksum, image,
+	page_order::{self, COMIC_INFO_FILE},
+};
+
+impl IsImage for SevenZArchiveEntry {
+	fn is_image(&self) -> bool {
+		if !self.is_directory() {
+			let content_type = media_file::guess_content_type(self.name());
+
+			return content_type.is_jpeg()
+				|| content_type.is_png()
+				|| content_type.is_webp()
+				|| content_type.is_svg()
+				|| content_type.is_tiff();
+		}
+
+		false
+	}
+}
+
+fn open_7z(path: &Path) -> ProcessFileResult<SevenZReader<File>> {
+	SevenZReader::open(path, Password::empty()).map_err(|e| {
+		log::error!("Failed to open 7z archive {:?}: {}", path, e);
+		ProcessFileError::SevenZipError(e)
+	})
+}
+
+/// Calls `checksum::digest` to attempt generating a checksum for the 7z file.
+pub fn digest_7z(path: &str) -> Option<String> {
+	let size = match checksum::sample_size(path) {
+		Ok(size) => size,
+		Err(e) => {
+			log::error!(
+				"Failed to calculate checksum sample size for 7z file {}: {}",
+				path,
+				e
+			);
+			return None;
+		},
+	};
+
+	log::debug!(
+		"Calculated sample size (in bytes) for generating checksum: {}",
+		size
+	);
+
+	match checksum::digest(path, size) {
+		Ok(digest) => Some(digest),
+		Err(e) => {
+			log::error!(
+				"Failed to digest 7z file {}. Unable to generate checksum: {}",
+				path,
+				e
+			);
+
+			None
+		},
+	}
+}
+
+/// Reads the bytes of a single entry from a 7z archive by name. 7z archives are often
+/// solid, meaning entries can only be decompressed in order. Every entry before the
+/// target is therefore drained, rather than skipped.
+pub fn read_7z_entry(path: &Path, name: &str) -> ProcessFileResult<Option<Vec<u8>>> {
+	let mut reader = open_7z(path)?;
+
+	let mut contents = None;
+
+	reader.for_each_entries(|entry, entry_reader| {
+		if entry.name() == name {
+			let mut buf = Vec::new();
+			entry_reader.read_to_end(&mut buf)?;
+			contents = Some(buf);
+
+			// Returning false stops the iteration, there is nothing left to read.
+			return Ok(false);
+		}
+
+		io::copy(entry_reader, &mut io::sink())?;
+
+		Ok(true)
+	})?;
+
+	Ok(contents)
+}
+
+/// Verifies the integrity of a 7z file by decompressing every entry, which checks the crc
+/// of each entry.
+pub fn verify_7z(path: &Path) -> ProcessFileResult<()> {
+	let mut reader = open_7z(path)?;
+
+	reader.for_each_entries(|_, entry_reader| {
+		io::copy(entry_reader, &mut io::sink())?;
+
+		Ok(true)
+	})?;
+
+	Ok(())
+}
+
+/// Collects the names of the image entries of a 7z file, along with the contents of its
+/// ComicInfo.xml file, if present.
+fn get_7z_entries(path: &Path) -> ProcessFileResult<(Vec<String>, Option<String>)> {
+	let reader = open_7z(path)?;
+
+	let entries = &reader.archive().files;
+
+	let image_names = entries
+		.iter()
+		.filter(|entry| entry.is_image())
+		.map(|entry| entry.name().to_string())
+		.collect::<Vec<String>>();
+	let has_comic_info = entries.iter().any(|entry| entry.name() == COMIC_INFO_FILE);
+
+	let comic_info = if has_comic_info {
+		read_7z_entry(path, COMIC_INFO_FILE)?.and_then(|buf| String::from_utf8(buf).ok())
+	} else {
+		None
+	};
+
+	Ok((image_names, comic_info))
+}
+
+/// Processes a 7z file in its entirety, includes: medatadata, page count, and the
+/// generated checksum for the file.
+pub fn process_7z(path: &Path) -> ProcessFileResult<ProcessedMediaFile> {
+	log::info!("Processing 7z: {}", path.display());
+
+	let (image_names, comic_info) = get_7z_entries(path)?;
+
+	let pages = page_order::order_pages(image_names, comic_info.as_deref()).len() as i32;
+
+	Ok(ProcessedMediaFile {
+		thumbnail_path: None,
+		path: path.to_path_buf(),
+		checksum: digest_7z(path.to_str().unwrap_or_default()),
+		metadata: comic_info.and_then(media_file::process_comic_info),
+		pages,
+	})
+}
+
+/// Builds the page index of a 7z file. Entries are decompressed in order in a single
+/// pass, and are read by name later on since their data can't be read directly. Only the
+/// header of each page is kept in memory, the rest is skipped over.
+pub fn index_7z_pages(path: &Path) -> ProcessFileResult<Vec<MediaPage>> {
+	let (image_names, comic_info) = get_7z_entries(path)?;
+	let pages = page_order::order_pages(image_names, comic_info.as_deref());
+
+	let positions = pages
+		.iter()
+		.enumerate()
+		.map(|(i, name)| (name.as_str(), i))
+		.collect::<HashMap<&str, usize>>();
+	let mut index: Vec<Option<MediaPage>> = vec![None; pages.len()];
+
+	let mut reader = open_7z(path)?;
+
+	reader.for_each_entries(|entry, entry_reader| {
+		match positions.get(entry.name()) {
+			Some(&i) => {
+				let dimensions = image::read_image_dimensions(entry_reader)?;
+
+				// Note: entries of solid archives share a single stream, so the rest of
+				// the page still has to be read before the next entry.
+				io::copy(entry_reader, &mut io::sink())?;
+
+				index[i] = Some(media_file::index_page(
+					i + 1,
+					entry.name(),
+					None,
+					entry.size(),
+					dimensions,
+				));
+			},
+			None => {
+				io::copy(entry_reader, &mut io::sink())?;
+			},
+		}
+
+		Ok(true)
+	})?;
+
+	Ok(index.into_iter().flatten().collect())
+}
+
+/// Get an image from a 7z file by index (page). Decompressing a solid archive is costly,
+/// so the page is read in the same pass as the ComicInfo.xml file, using the names from
+/// the header of the archive. The page is first assumed to be in natural order, and a
+/// second pass is only needed when the ComicInfo.xml file reorders the pages and comes
+/// after the page within the archive.
+pub fn get_7z_image(file: &str, page: i32) -> ProcessFileResult<http::ImageResponse> {
+	let path = Path::new(file);
+	let mut reader = open_7z(path)?;
+
+	let entries = &reader.archive().files;
+
+	let image_names = entries
+		.iter()
+		.filter(|entry| entry.is_image())
+		.map(|entry| entry.name().to_string())
+		.collect::<Vec<String>>();
+	let has_comic_info = entries.iter().any(|entry| entry.name() == COMIC_INFO_FILE);
+
+	if image_names.is_empty() {
+		log::error!("7z file {} contains no images", file);
+		return Err(ProcessFileError::ArchiveEmptyError);
+	}
+
+	let index = (page - 1) as usize;
+
+	let mut comic_info_read = !has_comic_info;
+	let mut target = page_order::order_pages(image_names.clone(), None)
+		.get(index)
+		.cloned();
+	let mut contents: Option<(String, Vec<u8>)> = None;
+
+	reader.for_each_entries(|entry, entry_reader| {
+		if !comic_info_read && entry.name() == COMIC_INFO_FILE {
+			let mut buf = Vec::new();
+			entry_reader.read_to_end(&mut buf)?;
+
+			let comic_info = String::from_utf8(buf).ok();
+			target = page_order::order_pages(image_names.clone(), comic_info.as_deref())
+				.get(index)
+				.cloned();
+			comic_info_read = true;
+
+			// Note: the page read so far is only kept if the ComicInfo.xml file didn't
+			// move it.
+			contents = contents
+				.take()
+				.filter(|(name, _)| Some(name) == target.as_ref());
+
+			return Ok(contents.is_none());
+		}
+
+		if contents.is_none() && target.as_deref() == Some(entry.name()) {
+			let mut buf = Vec::new();
+			entry_reader.read_to_end(&mut buf)?;
+			contents = Some((entry.name().to_string(), buf));
+
+			// Returning false stops the iteration, there is nothing left to read.
+			return Ok(!comic_info_read);
+		}
+
+		io::copy(entry_reader, &mut io::sink())?;
+
+		Ok(true)
+	})?;
+
+	let target = target.ok_or_else(|| {
+		log::error!("Could not find image for page {} in 7z file {}", page, file);
+		ProcessFileError::NoImageError
+	})?;
+
+	let contents = match contents {
+		Some((_, contents)) => contents,
+		None => read_7z_entry(path, &target)?.ok_or(ProcessFileError::NoImageError)?,
+	};
+
+	Ok((media_file::guess_content_type(&target), contents))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use std::path::PathBuf;
+
+	use rocket::http::ContentType;
+
+	fn fixture() -> PathBuf {
+		PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+			.join("tests")
+			.join("fixtures")
+			.join("fixture.cb7")
+	}
+
+	#[test]
+	fn can_process_7z() {
+		let processed = process_7z(&fixture()).unwrap();
+
+		assert_eq!(processed.pages, 3);
+		assert!(processed.checksum.is_some());
+		assert_eq!(
+			processed.metadata.and_then(|metadata| metadata.title),
+			Some("Fixture".to_string())
+		);
+	}
+
+	#[test]
+	fn can_get_7z_page() {
+		let file = fixture().to_string_lossy().to_string();
+
+		let (content_type, bytes) = get_7z_image(&file, 2).unwrap();
+
+		assert_eq!(content_type, ContentType::PNG);
+		assert_eq!(image::get_image_dimensions(&bytes), Some((8, 6)));
+		assert!(get_7z_image(&file, 4).is_err());
+	}
+
+	#[test]
+	fn can_index_7z_pages() {
+		let pages = media_file::index_pages(&fixture()).unwrap();
+
+		let names = pages
+			.iter()
+			.map(|page| page.name.as_str())
+			.collect::<Vec<_>>();
+
+		// Note: pages are ordered naturally, rather than in the order of the archive.
+		assert_eq!(names, vec!["1.png", "2.png", "10.png"]);
+		assert_eq!(pages[1].width, Some(8));
+		assert_eq!(pages[1].height, Some(6));
+		assert!(pages[1].is_spread);
+		assert!(!pages[0].is_spread);
+		// Note: 7z entries are compressed, so they can't be read directly.
+		assert!(pages.iter().all(|page| page.offset.is_none()));
+	}
+}
@@ -0,0 +1,298 @@
+use std::{
+	collections::HashMap,
+	fs::File,
+	io::{self, Read},
+	path::Path,
+};
+
+use tar::{Archive, Entry};
+
+use crate::{
+	fs::media_file::{self, IsImage},
+	types::{
+		alias::ProcessFileResult,
+		errors::ProcessFileError,
+		http,
+		models::{media::ProcessedMediaFile, page::MediaPage},
+	},
+};
+
+use super::{
+	checksum, image,
+	page_order::{self, COMIC_INFO_FILE},
+};
+
+fn entry_name<R: Read>(entry: &Entry<'_, R>) -> Option<String> {
+	entry
+		.path()
+		.ok()
+		.map(|path| path.to_string_lossy().to_string())
+}
+
+impl<'a, R: Read> IsImage for Entry<'a, R> {
+	fn is_image(&self) -> bool {
+		if self.header().entry_type().is_file() {
+			if let Some(name) = entry_name(self) {
+				let content_type = media_file::guess_content_type(&name);
+
+				return content_type.is_jpeg()
+					|| content_type.is_png()
+					|| content_type.is_webp()
+					|| content_type.is_svg()
+					|| content_type.is_tiff();
+			}
+		}
+
+		false
+	}
+}
+
+fn open_tar(path: &Path) -> ProcessFileResult<Archive<File>> {
+	Ok(Archive::new(File::open(path)?))
+}
+
+/// Calls `checksum::digest` to attempt generating a checksum for the tar file.
+pub fn digest_tar(path: &str) -> Option<String> {
+	let size = match checksum::sample_size(path) {
+		Ok(size) => size,
+		Err(e) => {
+			log::error!(
+				"Failed to calculate checksum sample size for tar file {}: {}",
+				path,
+				e
+			);
+			return None;
+		},
+	};
+
+	log::debug!(
+		"Calculated sample size (in bytes) for generating checksum: {}",
+		size
+	);
+
+	match checksum::digest(path, size) {
+		Ok(digest) => Some(digest),
+		Err(e) => {
+			log::error!(
+				"Failed to digest tar file {}. Unable to generate checksum: {}",
+				path,
+				e
+			);
+
+			None
+		},
+	}
+}
+
+/// Reads the bytes of a single entry from a tar archive by name. Names are matched case
+/// insensitively, consistent with how entries are listed and ordered (see `page_order`),
+/// although an entry matching the exact case is preferred.
+pub fn read_tar_entry(path: &Path, name: &str) -> ProcessFileResult<Option<Vec<u8>>> {
+	let mut archive = open_tar(path)?;
+
+	let mut contents = None;
+
+	for entry in archive.entries()? {
+		let mut entry = entry?;
+
+		let entry_name = match entry_name(&entry) {
+			Some(entry_name) => entry_name,
+			None => continue,
+		};
+
+		let is_exact = entry_name == name;
+
+		if is_exact || (contents.is_none() && entry_name.eq_ignore_ascii_case(name)) {
+			let mut buf = Vec::new();
+			entry.read_to_end(&mut buf)?;
+			contents = Some(buf);
+
+			if is_exact {
+				break;
+			}
+		}
+	}
+
+	Ok(contents)
+}
+
+/// Verifies the integrity of a tar file by reading every entry in full. Tar files don't
+/// store checksums of their contents, only of their headers, which are checked as each
+/// entry is read.
+pub fn verify_tar(path: &Path) -> ProcessFileResult<()> {
+	let mut archive = open_tar(path)?;
+
+	for entry in archive.entries()? {
+		let mut entry = entry?;
+		io::copy(&mut entry, &mut io::sink())?;
+	}
+
+	Ok(())
+}
+
+/// Collects the names of the image entries of a tar file, along with the contents of its
+/// ComicInfo.xml file, if present.
+fn get_tar_entries(path: &Path) -> ProcessFileResult<(Vec<String>, Option<String>)> {
+	let mut archive = open_tar(path)?;
+
+	let mut image_names = Vec::new();
+	let mut comic_info = None;
+
+	for entry in archive.entries()? {
+		let mut entry = entry?;
+
+		if entry_name(&entry).as_deref() == Some(COMIC_INFO_FILE) {
+			let mut contents = String::new();
+			entry.read_to_string(&mut contents)?;
+			comic_info = Some(contents);
+		} else if entry.is_image() {
+			if let Some(name) = entry_name(&entry) {
+				image_names.push(name);
+			}
+		}
+	}
+
+	Ok((image_names, comic_info))
+}
+
+/// Processes a tar file in its entirety, includes: medatadata, page count, and the
+/// generated checksum for the file.
+pub fn process_tar(path: &Path) -> ProcessFileResult<ProcessedMediaFile> {
+	log::info!("Processing Tar: {}", path.display());
+
+	let (image_names, comic_info) = get_tar_entries(path)?;
+
+	let pages = page_order::order_pages(image_names, comic_info.as_deref()).len() as i32;
+
+	Ok(ProcessedMediaFile {
+		thumbnail_path: None,
+		path: path.to_path_buf(),
+		checksum: digest_tar(path.to_str().unwrap_or_default()),
+		metadata: comic_info.and_then(media_file::process_comic_info),
+		pages,
+	})
+}
+
+/// Builds the page index of a tar file. Tar entries are never compressed, so every page
+/// records the offset of its data and can be read directly from the file later on.
+pub fn index_tar_pages(path: &Path) -> ProcessFileResult<Vec<MediaPage>> {
+	let (image_names, comic_info) = get_tar_entries(path)?;
+	let pages = page_order::order_pages(image_names, comic_info.as_deref());
+
+	let positions = pages
+		.iter()
+		.enumerate()
+		.map(|(i, name)| (name.as_str(), i))
+		.collect::<HashMap<&str, usize>>();
+	let mut index: Vec<Option<MediaPage>> = vec![None; pages.len()];
+
+	let mut archive = open_tar(path)?;
+
+	for entry in archive.entries()? {
+		let mut entry = entry?;
+
+		let name = match entry_name(&entry) {
+			Some(name) => name,
+			None => continue,
+		};
+
+		if let Some(&i) = positions.get(name.as_str()) {
+			let offset = entry.raw_file_position();
+			let size = entry.size();
+
+			index[i] = Some(media_file::index_page(
+				i + 1,
+				&name,
+				Some(offset),
+				size,
+				image::read_image_dimensions(&mut entry)?,
+			));
+		}
+	}
+
+	Ok(index.into_iter().flatten().collect())
+}
+
+/// Get an image from a tar file by index (page).
+pub fn get_tar_image(file: &str, page: i32) -> ProcessFileResult<http::ImageResponse> {
+	let path = Path::new(file);
+
+	let (image_names, comic_info) = get_tar_entries(path)?;
+
+	if image_names.is_empty() {
+		log::error!("Tar file {} contains no images", file);
+		return Err(ProcessFileError::ArchiveEmptyError);
+	}
+
+	let pages = page_order::order_pages(image_names, comic_info.as_deref());
+
+	let target = pages.get((page - 1) as usize).ok_or_else(|| {
+		log::error!(
+			"Could not find image for page {} in tar file {}",
+			page,
+			file
+		);
+		ProcessFileError::NoImageError
+	})?;
+
+	let contents = read_tar_entry(path, target)?.ok_or(ProcessFileError::NoImageError)?;
+
+	Ok((media_file::guess_content_type(target), contents))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use std::path::PathBuf;
+
+	use rocket::http::ContentType;
+
+	fn fixture() -> PathBuf {
+		PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+			.join("tests")
+			.join("fixtures")
+			.join("fixture.cbt")
+	}
+
+	#[test]
+	fn can_process_tar() {
+		let processed = process_tar(&fixture()).unwrap();
+
+		assert_eq!(processed.pages, 3);
+		assert!(processed.checksum.is_some());
+		assert_eq!(
+			processed.metadata.and_then(|metadata| metadata.title),
+			Some("Fixture".to_string())
+		);
+	}
+
+	#[test]
+	fn can_get_tar_page() {
+		let file = fixture().to_string_lossy().to_string();
+
+		let (content_type, bytes) = get_tar_image(&file, 2).unwrap();
+
+		assert_eq!(content_type, ContentType::PNG);
+		assert_eq!(image::get_image_dimensions(&bytes), Some((8, 6)));
+		assert!(get_tar_image(&file, 4).is_err());
+	}
+
+	#[test]
+	fn can_index_tar_pages() {
+		let pages = media_file::index_pages(&fixture()).unwrap();
+
+		let names = pages
+			.iter()
+			.map(|page| page.name.as_str())
+			.collect::<Vec<_>>();
+
+		// Note: pages are ordered naturally, rather than in the order of the archive.
+		assert_eq!(names, vec!["1.png", "2.png", "10.png"]);
+		assert_eq!(pages[1].width, Some(8));
+		assert_eq!(pages[1].height, Some(6));
+		assert!(pages[1].is_spread);
+		assert!(!pages[0].is_spread);
+		assert!(pages.iter().all(|page| page.offset.is_some()));
+	}
+}
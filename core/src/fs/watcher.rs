@@ -0,0 +1,376 @@
+use std::{
+	collections::HashMap,
+	path::{Path, PathBuf},
+	sync::{Arc, Mutex},
+	time::{Duration, Instant},
+};
+
+use notify::{
+	event::ModifyKind, Config, Event, EventKind, RecommendedWatcher, RecursiveMode,
+	Watcher,
+};
+use rocket::tokio::{self, sync::mpsc};
+
+use crate::{
+	config::context::Ctx,
+	fs::scanner::ScannedFileTrait,
+	job::{series_scan::SeriesScanJob, JobPriority},
+	prisma::{library, series},
+	types::{errors::ApiError, models::library::LibraryOptions},
+};
+
+/// The time to wait after the last change within a directory before scanning it, so that
+/// bursts of changes (e.g. copying a large number of files) only trigger a single scan.
+const DEBOUNCE_DURATION: Duration = Duration::from_secs(5);
+
+/// How often directories with pending changes are checked against `DEBOUNCE_DURATION`.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The paths the server is writing to itself (e.g. while converting a media), along with
+/// the time it stopped, if it has. See `suppress_changes`.
+static SUPPRESSED_PATHS: Mutex<Vec<(PathBuf, Option<Instant>)>> = Mutex::new(Vec::new());
+
+/// Ignores the changes to the given paths for as long as the returned guard is alive, so
+/// that the server writing within a library doesn't queue a scan of its own changes.
+/// Changes are still ignored for `DEBOUNCE_DURATION` once the guard is dropped, since the
+/// events of the last writes may be delivered after it.
+pub fn suppress_changes(paths: Vec<PathBuf>) -> SuppressedChanges {
+	SUPPRESSED_PATHS
+		.lock()
+		.unwrap_or_else(|e| e.into_inner())
+		.extend(paths.iter().map(|path| (path.clone(), None)));
+
+	SuppressedChanges { paths }
+}
+
+/// Returns true if the changes to the path are currently ignored, see `suppress_changes`.
+fn is_suppressed(path: &Path) -> bool {
+	let mut suppressed_paths = SUPPRESSED_PATHS.lock().unwrap_or_else(|e| e.into_inner());
+
+	suppressed_paths.retain(|(_, released_at)| {
+		released_at
+			.map(|released_at| released_at.elapsed() < DEBOUNCE_DURATION)
+			.unwrap_or(true)
+	});
+
+	suppressed_paths
+		.iter()
+		.any(|(suppressed_path, _)| suppressed_path == path)
+}
+
+/// A guard ignoring the changes to paths while it is alive, see `suppress_changes`.
+pub struct SuppressedChanges {
+	paths: Vec<PathBuf>,
+}
+
+impl Drop for SuppressedChanges {
+	fn drop(&mut self) {
+		let released_at = Instant::now();
+		let mut suppressed_paths =
+			SUPPRESSED_PATHS.lock().unwrap_or_else(|e| e.into_inner());
+
+		for path in &self.paths {
+			// Note: the same path may be suppressed more than once, so only one of its
+			// entries is released.
+			if let Some(entry) = suppressed_paths
+				.iter_mut()
+				.find(|(suppressed_path, at)| suppressed_path == path && at.is_none())
+			{
+				entry.1 = Some(released_at);
+			}
+		}
+	}
+}
+
+struct LibraryChange {
+	library_id: String,
+	library_path: PathBuf,
+	path: PathBuf,
+}
+
+/// Watches the directories of libraries configured to be watched, and queues a
+/// `SeriesScanJob` for each directory which changed, rather than scanning the entire
+/// library.
+pub struct LibraryWatcher {
+	/// The watchers, keyed by library id. Dropping a watcher stops watching its library.
+	watchers: Mutex<HashMap<String, RecommendedWatcher>>,
+	change_sender: mpsc::UnboundedSender<LibraryChange>,
+}
+
+impl LibraryWatcher {
+	/// Creates a new LibraryWatcher. Internally, a thread will be spawned to debounce
+	/// changes and queue the scans.
+	pub fn new(ctx: Ctx) -> Arc<Self> {
+		let (change_sender, change_receiver) = mpsc::unbounded_channel();
+
+		tokio::spawn(debounce_changes(ctx, change_receiver));
+
+		Arc::new(Self {
+			watchers: Mutex::new(HashMap::new()),
+			change_sender,
+		})
+	}
+
+	/// Starts watching every library configured to be watched.
+	pub async fn init(&self, ctx: &Ctx) -> Result<(), ApiError> {
+		let libraries = ctx
+			.get_db()
+			.library()
+			.find_many(vec![])
+			.with(library::library_options::fetch())
+			.exec()
+			.await?;
+
+		for library in libraries {
+			let library_options: LibraryOptions = library
+				.library_options()
+				.map(|opt| opt.to_owned().into())
+				.unwrap_or_default();
+
+			if library_options.watch_for_changes {
+				self.watch_library(library.id, &library.path);
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Starts watching the directory of a library, replacing the existing watcher of the
+	/// library (if any).
+	pub fn watch_library(&self, library_id: String, path: &str) {
+		let change_sender = self.change_sender.clone();
+		let watched_id = library_id.clone();
+		let library_path = PathBuf::from(path);
+
+		let result = RecommendedWatcher::new(
+			move |result: notify::Result<Event>| match result {
+				Ok(event) if is_relevant_event(&event.kind) => {
+					for path in
+						event.paths.into_iter().filter(|path| !is_suppressed(path))
+					{
+						let _ = change_sender.send(LibraryChange {
+							library_id: watched_id.clone(),
+							library_path: library_path.clone(),
+							path,
+						});
+					}
+				},
+				Ok(_) => {},
+				Err(e) => log::error!("Library watcher error: {}", e),
+			},
+			Config::default(),
+		)
+		.and_then(|mut watcher| {
+			watcher.watch(Path::new(path), RecursiveMode::Recursive)?;
+
+			Ok(watcher)
+		});
+
+		match result {
+			Ok(watcher) => {
+				log::info!("Watching library {} for changes", path);
+
+				self.watchers
+					.lock()
+					.unwrap_or_else(|e| e.into_inner())
+					.insert(library_id, watcher);
+			},
+			Err(e) => log::error!("Failed to watch library {}: {}", path, e),
+		}
+	}
+
+	/// Stops watching the directory of a library, if it is watched.
+	pub fn unwatch_library(&self, library_id: &str) {
+		let removed = self
+			.watchers
+			.lock()
+			.unwrap_or_else(|e| e.into_inner())
+			.remove(library_id);
+
+		if removed.is_some() {
+			log::info!("Stopped watching library {}", library_id);
+		}
+	}
+}
+
+/// Returns true if the event could change the media of a library. Reads, as well as
+/// metadata changes such as access times, are ignored, since scans would otherwise
+/// trigger themselves.
+fn is_relevant_event(kind: &EventKind) -> bool {
+	match kind {
+		EventKind::Create(_) | EventKind::Remove(_) => true,
+		EventKind::Modify(ModifyKind::Metadata(_)) => false,
+		EventKind::Modify(_) => true,
+		_ => false,
+	}
+}
+
+/// Returns the directories to scan for a changed path. A change to a file affects the
+/// directory it is in, while a change to a directory affects the directory itself. Paths
+/// which no longer exist are treated as files, unless they were the directory of a known
+/// series (see `is_removed_series`).
+fn get_affected_dirs(
+	path: &Path,
+	library_path: &Path,
+	removed_series: bool,
+) -> Vec<PathBuf> {
+	if path.is_invisible_file() {
+		return vec![];
+	}
+
+	let dirs = if path.is_dir() || removed_series {
+		vec![path.to_path_buf()]
+	} else {
+		path.parent()
+			.map(|parent| parent.to_path_buf())
+			.into_iter()
+			.collect()
+	};
+
+	dirs.into_iter()
+		.filter(|dir| dir.starts_with(library_path))
+		.collect()
+}
+
+/// Returns true if the path no longer exists, and was the directory of a series of the
+/// library. The series is then scanned itself, so that it is marked as missing.
+async fn is_removed_series(ctx: &Ctx, library_id: &str, path: &Path) -> bool {
+	if path.exists() {
+		return false;
+	}
+
+	let series = ctx
+		.get_db()
+		.series()
+		.find_first(vec![
+			series::library_id::equals(Some(library_id.to_string())),
+			series::path::equals(path.to_string_lossy().to_string()),
+		])
+		.exec()
+		.await;
+
+	match series {
+		Ok(series) => series.is_some(),
+		Err(e) => {
+			log::error!("Failed to find series at {:?}: {}", path, e);
+			false
+		},
+	}
+}
+
+/// Collects the changes of the watched libraries, queueing a `SeriesScanJob` for each
+/// affected directory once it has had no changes for `DEBOUNCE_DURATION`.
+async fn debounce_changes(
+	ctx: Ctx,
+	mut receiver: mpsc::UnboundedReceiver<LibraryChange>,
+) {
+	// Note: the pending directories are keyed by path, along with the library they belong
+	// to and the time of their last change.
+	let mut pending = HashMap::<PathBuf, (String, Instant)>::new();
+	let mut interval = tokio::time::interval(FLUSH_INTERVAL);
+
+	loop {
+		tokio::select! {
+			change = receiver.recv() => {
+				let change = match change {
+					Some(change) => change,
+					None => break,
+				};
+
+				let removed_series =
+					is_removed_series(&ctx, &change.library_id, &change.path).await;
+
+				for dir in get_affected_dirs(&change.path, &change.library_path, removed_series) {
+					log::trace!("Library directory changed: {:?}", dir);
+					pending.insert(dir, (change.library_id.clone(), Instant::now()));
+				}
+			},
+			_ = interval.tick() => {
+				let ready = pending
+					.iter()
+					.filter(|(_, (_, changed_at))| changed_at.elapsed() >= DEBOUNCE_DURATION)
+					.map(|(dir, _)| dir.clone())
+					.collect::<Vec<PathBuf>>();
+
+				for dir in ready {
+					let (library_id, _) = match pending.remove(&dir) {
+						Some(entry) => entry,
+						None => continue,
+					};
+
+					log::debug!("Queueing scan of changed directory: {:?}", dir);
+
+					let job = SeriesScanJob::new(
+						&ctx,
+						library_id,
+						dir.to_string_lossy().to_string(),
+						JobPriority::Normal,
+					)
+					.await;
+
+					if let Err(e) = job.and_then(|job| Ok(ctx.spawn_job(Box::new(job))?)) {
+						log::error!("Failed to queue scan of {:?}: {}", dir, e);
+					}
+				}
+			},
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn ignores_metadata_changes() {
+		use notify::event::{CreateKind, DataChange, MetadataKind};
+
+		assert!(is_relevant_event(&EventKind::Create(CreateKind::File)));
+		assert!(is_relevant_event(&EventKind::Modify(ModifyKind::Data(
+			DataChange::Content
+		))));
+		assert!(!is_relevant_event(&EventKind::Modify(
+			ModifyKind::Metadata(MetadataKind::AccessTime)
+		)));
+	}
+
+	#[test]
+	fn affected_dirs_stay_within_library() {
+		let library_path = Path::new("/nonexistent/library");
+
+		// Note: the paths don't exist, so they are treated as files unless they were the
+		// directory of a series.
+		assert_eq!(
+			get_affected_dirs(
+				&library_path.join("Series/Book 1.cbz"),
+				library_path,
+				false
+			),
+			vec![library_path.join("Series")]
+		);
+		assert_eq!(
+			get_affected_dirs(&library_path.join("Series"), library_path, true),
+			vec![library_path.join("Series")]
+		);
+		assert!(get_affected_dirs(library_path, library_path, false).is_empty());
+		assert!(get_affected_dirs(
+			&library_path.join("Series/.DS_Store"),
+			library_path,
+			false
+		)
+		.is_empty());
+	}
+
+	#[test]
+	fn suppresses_changes_while_guarded() {
+		let path = PathBuf::from("/nonexistent/library/Series/Book 1.cbr");
+
+		let guard = suppress_changes(vec![path.clone()]);
+		assert!(is_suppressed(&path));
+		assert!(!is_suppressed(&path.with_extension("cbz")));
+
+		drop(guard);
+		// Note: the events of the last writes may still be delivered.
+		assert!(is_suppressed(&path));
+	}
+}
@@ -1,8 +1,10 @@
 use crate::{
 	fs::media_file::{self, IsImage},
 	types::{
-		alias::ProcessFileResult, errors::ProcessFileError, http,
-		models::media::ProcessedMediaFile,
+		alias::ProcessFileResult,
+		errors::ProcessFileError,
+		http,
+		models::{media::ProcessedMediaFile, page::MediaPage},
 	},
 };
 
@@ -12,9 +14,12 @@ use std::{
 	path::{Path, PathBuf},
 };
 use walkdir::WalkDir;
-use zip::{read::ZipFile, write::FileOptions};
+use zip::{read::ZipFile, result::ZipError, write::FileOptions, CompressionMethod};
 
-use super::checksum;
+use super::{
+	checksum, image,
+	page_order::{self, COMIC_INFO_FILE},
+};
 
 impl<'a> IsImage for ZipFile<'a> {
 	// FIXME: use infer here
@@ -83,21 +88,27 @@ fn zip_dir(
 	Ok(())
 }
 
-pub fn create_zip(
-	unpacked_path: &Path,
-	name: &str,
-	original_ext: &str,
-	parent: &Path,
-) -> zip::result::ZipResult<PathBuf> {
+/// Returns the path of the zip file `create_zip` would create for the given name. Files
+/// converted from cbr use the cbz extension, all others use zip.
+pub fn get_zip_path(name: &str, original_ext: &str, parent: &Path) -> PathBuf {
 	let mut ext = "cbz";
 
-	if original_ext != "cbr" {
+	if !original_ext.eq_ignore_ascii_case("cbr") {
 		ext = "zip";
 	}
 
 	log::trace!("Calculated extension for zip file: {}", ext);
 
-	let zip_path = parent.join(format!("{}.{}", name, ext));
+	parent.join(format!("{}.{}", name, ext))
+}
+
+pub fn create_zip(
+	unpacked_path: &Path,
+	name: &str,
+	original_ext: &str,
+	parent: &Path,
+) -> zip::result::ZipResult<PathBuf> {
+	let zip_path = get_zip_path(name, original_ext, parent);
 
 	zip_dir(&unpacked_path, &zip_path, unpacked_path)?;
 
@@ -105,9 +116,9 @@ pub fn create_zip(
 }
 
 /// Get the sample size (in bytes) to use for generating a checksum of a zip file.
-pub fn zip_sample(file: &str) -> u64 {
-	let zip_file = File::open(file).unwrap();
-	let mut archive = zip::ZipArchive::new(zip_file).unwrap();
+pub fn zip_sample(file: &str) -> ProcessFileResult<u64> {
+	let zip_file = File::open(file)?;
+	let mut archive = zip::ZipArchive::new(zip_file)?;
 
 	let mut sample_size = 0;
 
@@ -116,17 +127,28 @@ pub fn zip_sample(file: &str) -> u64 {
 			break;
 		}
 
-		let file = archive.by_index(i).unwrap();
+		let file = archive.by_index(i)?;
 
 		sample_size += file.size();
 	}
 
-	sample_size
+	Ok(sample_size)
 }
 
 /// Calls `checksum::digest` to attempt generating a checksum for the zip file.
 pub fn digest_zip(path: &str) -> Option<String> {
-	let size = zip_sample(path);
+	let size = match zip_sample(path) {
+		Ok(size) => size,
+		Err(e) => {
+			log::error!(
+				"Failed to read zip file {}. Unable to generate checksum: {}",
+				path,
+				e
+			);
+
+			return None;
+		},
+	};
 
 	log::debug!(
 		"Calculated sample size (in bytes) for generating checksum: {}",
@@ -147,6 +169,64 @@ pub fn digest_zip(path: &str) -> Option<String> {
 	}
 }
 
+/// Collects the names of the image entries of a zip file, along with the contents of its
+/// ComicInfo.xml file, if present.
+fn get_zip_entries(
+	archive: &mut zip::ZipArchive<File>,
+) -> ProcessFileResult<(Vec<String>, Option<String>)> {
+	let mut image_names = Vec::new();
+	let mut comic_info = None;
+
+	for i in 0..archive.len() {
+		let mut file = archive.by_index(i)?;
+
+		if file.name() == COMIC_INFO_FILE {
+			let mut contents = String::new();
+			file.read_to_string(&mut contents)?;
+			comic_info = Some(contents);
+		} else if file.is_image() {
+			image_names.push(file.name().to_string());
+		}
+	}
+
+	Ok((image_names, comic_info))
+}
+
+/// Verifies the integrity of a zip file by reading every entry in full, which checks the
+/// crc of each entry.
+pub fn verify_zip(path: &Path) -> ProcessFileResult<()> {
+	let zip_file = File::open(path)?;
+	let mut archive = zip::ZipArchive::new(zip_file)?;
+
+	for i in 0..archive.len() {
+		let mut file = archive.by_index(i)?;
+		std::io::copy(&mut file, &mut std::io::sink())?;
+	}
+
+	Ok(())
+}
+
+/// Counts the image entries of a zip file. Every image is read in full, so that a zip file
+/// which is incomplete or corrupted results in an error rather than a partial count.
+pub fn count_zip_images(path: &Path) -> ProcessFileResult<usize> {
+	let zip_file = File::open(path)?;
+	let mut archive = zip::ZipArchive::new(zip_file)?;
+
+	let mut count = 0;
+
+	for i in 0..archive.len() {
+		let mut file = archive.by_index(i)?;
+
+		if file.is_image() {
+			// Note: the crc of an entry is only checked once it has been read entirely.
+			std::io::copy(&mut file, &mut std::io::sink())?;
+			count += 1;
+		}
+	}
+
+	Ok(count)
+}
+
 /// Processes a zip file in its entirety, includes: medatadata, page count, and the
 /// generated checksum for the file.
 // TODO: do I need to pass in the library options here?
@@ -156,76 +236,99 @@ pub fn process_zip(path: &Path) -> ProcessFileResult<ProcessedMediaFile> {
 	let zip_file = File::open(path)?;
 	let mut archive = zip::ZipArchive::new(zip_file)?;
 
-	let mut comic_info = None;
-	// let mut entries = Vec::new();
-	let mut pages = 0;
+	let (image_names, comic_info) = get_zip_entries(&mut archive)?;
 
-	for i in 0..archive.len() {
-		let mut file = archive.by_index(i)?;
-		// entries.push(file.name().to_owned());
-		if file.name() == "ComicInfo.xml" {
-			let mut contents = String::new();
-			file.read_to_string(&mut contents)?;
-			comic_info = media_file::process_comic_info(contents);
-		} else if file.is_image() {
-			pages += 1;
-		}
-	}
+	let pages = page_order::order_pages(image_names, comic_info.as_deref()).len() as i32;
 
 	Ok(ProcessedMediaFile {
 		thumbnail_path: None,
 		path: path.to_path_buf(),
 		checksum: digest_zip(path.to_str().unwrap()),
-		metadata: comic_info,
+		metadata: comic_info.and_then(media_file::process_comic_info),
 		pages,
 	})
 }
 
-// FIXME: this solution is terrible, was just fighting with borrow checker and wanted
-// a quick solve. TODO: rework this!
+/// Builds the page index of a zip file. Entries stored without compression record the
+/// offset of their data, so they can be read directly from the file later on.
+pub fn index_zip_pages(path: &Path) -> ProcessFileResult<Vec<MediaPage>> {
+	let zip_file = File::open(path)?;
+	let mut archive = zip::ZipArchive::new(zip_file)?;
+
+	let (image_names, comic_info) = get_zip_entries(&mut archive)?;
+	let pages = page_order::order_pages(image_names, comic_info.as_deref());
+
+	let mut index = Vec::with_capacity(pages.len());
+
+	for (i, name) in pages.iter().enumerate() {
+		let mut file = archive.by_name(name)?;
+
+		// Note: `data_start` is only known once the entry has been opened.
+		let offset = match file.compression() {
+			CompressionMethod::Stored => Some(file.data_start()),
+			_ => None,
+		};
+		let size = file.size();
+
+		index.push(media_file::index_page(
+			i + 1,
+			name,
+			offset,
+			size,
+			image::read_image_dimensions(&mut file)?,
+		));
+	}
+
+	Ok(index)
+}
+
+/// Reads the bytes of a single entry from a zip file by name.
+pub fn read_zip_entry(path: &Path, name: &str) -> ProcessFileResult<Option<Vec<u8>>> {
+	let zip_file = File::open(path)?;
+	let mut archive = zip::ZipArchive::new(zip_file)?;
+
+	let mut file = match archive.by_name(name) {
+		Ok(file) => file,
+		Err(ZipError::FileNotFound) => return Ok(None),
+		Err(e) => return Err(e.into()),
+	};
+
+	let mut contents = Vec::new();
+	file.read_to_end(&mut contents)?;
+
+	Ok(Some(contents))
+}
+
 /// Get an image from a zip file by index (page).
 pub fn get_zip_image(file: &str, page: i32) -> ProcessFileResult<http::ImageResponse> {
 	let zip_file = File::open(file)?;
-
-	let mut archive = zip::ZipArchive::new(&zip_file)?;
-	// FIXME: stinky clone here
-	let file_names_archive = archive.clone();
+	let mut archive = zip::ZipArchive::new(zip_file)?;
 
 	if archive.len() == 0 {
 		log::error!("Zip file {} is empty", file);
 		return Err(ProcessFileError::ArchiveEmptyError);
 	}
 
-	let mut file_names = file_names_archive.file_names().collect::<Vec<_>>();
-	// NOTE: I noticed some zip files *also* come back out of order >:(
-	// TODO: look more into this!
-	file_names.sort_by(|a, b| a.cmp(b));
+	let (image_names, comic_info) = get_zip_entries(&mut archive)?;
+	let pages = page_order::order_pages(image_names, comic_info.as_deref());
 
-	let mut images_seen = 0;
-	for name in file_names {
-		let mut file = archive.by_name(name)?;
+	let name = pages.get((page - 1) as usize).ok_or_else(|| {
+		log::error!(
+			"Could not find image for page {} in zip file {}",
+			page,
+			file
+		);
+		ProcessFileError::NoImageError
+	})?;
 
-		let mut contents = Vec::new();
-		// Note: guessing mime here since this file isn't accessible from the filesystem,
-		// it lives inside the zip file.
-		let content_type = media_file::guess_content_type(name);
+	log::trace!("Found target image: {}", name);
 
-		if images_seen + 1 == page && file.is_image() {
-			log::trace!("Found target image: {}", name);
-			file.read_to_end(&mut contents)?;
-			return Ok((content_type, contents));
-		} else if file.is_image() {
-			images_seen += 1;
-		}
-	}
-
-	log::error!(
-		"Could not find image for page {} in zip file {}",
-		page,
-		file
-	);
+	let mut contents = Vec::new();
+	archive.by_name(name)?.read_to_end(&mut contents)?;
 
-	Err(ProcessFileError::NoImageError)
+	// Note: guessing mime here since this file isn't accessible from the filesystem,
+	// it lives inside the zip file.
+	Ok((media_file::guess_content_type(name), contents))
 }
 
 #[cfg(test)]
@@ -256,7 +359,7 @@ mod tests {
 		}
 
 		for zip in zips {
-			let zip_sample = zip_sample(&zip.path);
+			let zip_sample = zip_sample(&zip.path).unwrap();
 
 			let checksum = match checksum::digest_async(&zip.path, zip_sample).await {
 				Ok(digest) => {
@@ -296,7 +399,7 @@ mod tests {
 		}
 
 		for zip in zips {
-			let zip_sample = zip_sample(&zip.path);
+			let zip_sample = zip_sample(&zip.path).unwrap();
 
 			let checksum = match checksum::digest(&zip.path, zip_sample) {
 				Ok(digest) => {
@@ -0,0 +1,126 @@
+use std::sync::Arc;
+
+use rocket::tokio::sync::watch;
+
+/// The state of a job, as requested through its `JobControl`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobState {
+	Running,
+	Paused,
+	Cancelled,
+}
+
+/// A handle used to cancel, pause and resume a running job. The job is given a copy of
+/// the handle, and is expected to call `checkpoint` between each of its tasks (e.g.
+/// between files of a scan), which is where it pauses or stops.
+#[derive(Clone)]
+pub struct JobControl {
+	sender: Arc<watch::Sender<JobState>>,
+	receiver: watch::Receiver<JobState>,
+}
+
+impl JobControl {
+	pub fn new() -> Self {
+		let (sender, receiver) = watch::channel(JobState::Running);
+
+		Self {
+			sender: Arc::new(sender),
+			receiver,
+		}
+	}
+
+	pub fn state(&self) -> JobState {
+		*self.receiver.borrow()
+	}
+
+	/// Moves the job to the given state, if it is currently in the expected one. Returns
+	/// false otherwise, e.g. when resuming a job which isn't paused.
+	fn transition(&self, from: &[JobState], to: JobState) -> bool {
+		if !from.contains(&self.state()) {
+			return false;
+		}
+
+		self.sender.send(to).is_ok()
+	}
+
+	/// Requests the job to stop at its next checkpoint. Paused jobs are stopped as well.
+	pub fn cancel(&self) -> bool {
+		self.transition(&[JobState::Running, JobState::Paused], JobState::Cancelled)
+	}
+
+	/// Requests the job to wait at its next checkpoint, until it is resumed or cancelled.
+	pub fn pause(&self) -> bool {
+		self.transition(&[JobState::Running], JobState::Paused)
+	}
+
+	pub fn resume(&self) -> bool {
+		self.transition(&[JobState::Paused], JobState::Running)
+	}
+
+	pub fn is_cancelled(&self) -> bool {
+		self.state() == JobState::Cancelled
+	}
+
+	/// Waits for as long as the job is paused. Returns true if the job should carry on
+	/// with its next task, or false if it was cancelled.
+	pub async fn checkpoint(&self) -> bool {
+		let mut receiver = self.receiver.clone();
+
+		loop {
+			match *receiver.borrow_and_update() {
+				JobState::Running => return true,
+				JobState::Cancelled => return false,
+				JobState::Paused => {},
+			}
+
+			// Note: the sender is kept alive by `self`, so this only fails if the job
+			// outlives its control, in which case it shouldn't wait forever.
+			if receiver.changed().await.is_err() {
+				return !self.is_cancelled();
+			}
+		}
+	}
+}
+
+impl Default for JobControl {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use rocket::tokio;
+
+	use super::*;
+
+	#[test]
+	fn only_valid_transitions_apply() {
+		let control = JobControl::new();
+
+		assert!(!control.resume());
+		assert!(control.pause());
+		assert!(!control.pause());
+		assert!(control.resume());
+		assert!(control.cancel());
+		assert!(!control.resume());
+		assert!(!control.cancel());
+		assert_eq!(control.state(), JobState::Cancelled);
+	}
+
+	#[tokio::test]
+	async fn checkpoint_waits_while_paused() {
+		let control = JobControl::new();
+
+		assert!(control.checkpoint().await);
+
+		control.pause();
+
+		let job_control = control.clone();
+		let job = tokio::spawn(async move { job_control.checkpoint().await });
+
+		control.cancel();
+
+		assert!(!job.await.unwrap());
+	}
+}
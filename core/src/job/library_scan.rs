@@ -1,4 +1,9 @@
-use super::{persist_job_end, Job};
+use super::{
+	control::JobControl, persist_job_end, persist_moved_media, serialize_params, Job,
+	JobPriority,
+};
+
+use serde::{Deserialize, Serialize};
 
 use crate::{
 	config::context::Ctx,
@@ -7,7 +12,7 @@ use crate::{
 	types::{errors::ApiError, models::library::LibraryScanMode},
 };
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct LibraryScanJob {
 	pub path: String,
 	pub scan_mode: LibraryScanMode,
@@ -23,29 +28,55 @@ impl Job for LibraryScanJob {
 		Some(Box::new(self.path.as_str()))
 	}
 
-	async fn run(&self, runner_id: String, ctx: Ctx) -> Result<(), ApiError> {
+	fn params(&self) -> Option<String> {
+		serialize_params(self)
+	}
+
+	fn priority(&self) -> JobPriority {
+		JobPriority::Low
+	}
+
+	fn scan_path(&self) -> Option<&str> {
+		Some(self.path.as_str())
+	}
+
+	async fn run(
+		&self,
+		runner_id: String,
+		ctx: Ctx,
+		control: JobControl,
+	) -> Result<(), ApiError> {
 		let start = std::time::Instant::now();
-		let completed_tasks = match self.scan_mode {
-			LibraryScanMode::Sync => scan_sync(ctx.get_ctx(), self.path.clone(), runner_id.clone()).await?,
-			LibraryScanMode::Batched => scan_batch(ctx.get_ctx(), self.path.clone(), runner_id.clone()).await?,
+		let summary = match self.scan_mode {
+			LibraryScanMode::Sync => scan_sync(ctx.get_ctx(), self.path.clone(), runner_id.clone(), control.clone()).await?,
+			LibraryScanMode::Batched => scan_batch(ctx.get_ctx(), self.path.clone(), runner_id.clone(), control.clone()).await?,
 			_ => unreachable!("If a library scan job was started with the scan mode of NONE, it should not have been started."),
 		};
 		let duration = start.elapsed();
 
 		log::info!(
-			"Finished library scan in {}.{:03} seconds. {} files processed.",
+			"Finished library scan in {}.{:03} seconds. {} files processed, {} media moved.",
 			duration.as_secs(),
 			duration.subsec_millis(),
-			completed_tasks
+			summary.completed_tasks,
+			summary.moved_media
 		);
 
-		persist_job_end(&ctx, runner_id, completed_tasks, duration.as_secs()).await?;
+		persist_moved_media(&ctx, runner_id.clone(), summary.moved_media).await?;
+		persist_job_end(
+			&ctx,
+			runner_id,
+			summary.completed_tasks,
+			duration.as_secs(),
+			&control,
+		)
+		.await?;
 
 		Ok(())
 	}
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct AllLibrariesScanJob {
 	pub scan_mode: LibraryScanMode,
 }
@@ -60,7 +91,20 @@ impl Job for AllLibrariesScanJob {
 		todo!()
 	}
 
-	async fn run(&self, _runner_id: String, _ctx: Ctx) -> Result<(), ApiError> {
+	fn params(&self) -> Option<String> {
+		serialize_params(self)
+	}
+
+	fn priority(&self) -> JobPriority {
+		JobPriority::Low
+	}
+
+	async fn run(
+		&self,
+		_runner_id: String,
+		_ctx: Ctx,
+		_control: JobControl,
+	) -> Result<(), ApiError> {
 		todo!()
 	}
 }
@@ -0,0 +1,160 @@
+use std::path::Path;
+
+use super::{
+	control::JobControl, persist_job_end, persist_job_start, serialize_params, Job,
+	JobScope,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+	config::context::Ctx,
+	event::ClientEvent,
+	fs::media_file,
+	prisma::media,
+	types::{
+		enums::FileStatus,
+		errors::{ApiError, ProcessFileError},
+	},
+};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LibraryVerificationJob {
+	/// The id of the library, series or media, depending on the scope.
+	pub id: String,
+	pub scope: JobScope,
+	/// The path of the library, series or media in scope, so that the job never runs
+	/// alongside a scan of it.
+	pub path: String,
+}
+
+/// Verifies the integrity of a media file, then updates its status accordingly. Broken
+/// media are flagged as `ERROR` along with the reason, and media which were previously
+/// flagged are marked as `READY` again once they pass verification (e.g. after being
+/// re-downloaded). Media which can't be verified in this environment are skipped, and
+/// keep their status. Returns the reason the media is broken, if any.
+pub async fn verify_media(
+	ctx: &Ctx,
+	media: &media::Data,
+) -> Result<Option<String>, ApiError> {
+	let path = media.path.clone();
+
+	// Note: reading every entry of a large file takes a while, so it shouldn't block the
+	// async runtime.
+	let result =
+		rocket::tokio::task::spawn_blocking(move || media_file::verify(Path::new(&path)))
+			.await
+			.map_err(|e| ApiError::InternalServerError(e.to_string()))?;
+
+	let (status, reason) = match result {
+		Ok(_) if media.status == FileStatus::Error.to_string() => {
+			(FileStatus::Ready, None)
+		},
+		Ok(_) => return Ok(None),
+		Err(ProcessFileError::VerificationUnsupported(reason)) => {
+			log::debug!("Skipping verification of {}: {}", media.path, reason);
+			return Ok(None);
+		},
+		Err(e) => (FileStatus::Error, Some(e.to_string())),
+	};
+
+	ctx.get_db()
+		.media()
+		.update(
+			media::id::equals(media.id.clone()),
+			vec![
+				media::status::set(status.to_string()),
+				media::status_reason::set(reason.clone()),
+			],
+		)
+		.exec()
+		.await?;
+
+	Ok(reason)
+}
+
+#[async_trait::async_trait]
+impl Job for LibraryVerificationJob {
+	fn kind(&self) -> &'static str {
+		"LibraryVerificationJob"
+	}
+
+	fn details(&self) -> Option<Box<&str>> {
+		Some(Box::new(self.id.as_str()))
+	}
+
+	fn params(&self) -> Option<String> {
+		serialize_params(self)
+	}
+
+	fn scan_path(&self) -> Option<&str> {
+		Some(self.path.as_str())
+	}
+
+	async fn run(
+		&self,
+		runner_id: String,
+		ctx: Ctx,
+		control: JobControl,
+	) -> Result<(), ApiError> {
+		let start = std::time::Instant::now();
+
+		let media = ctx
+			.get_db()
+			.media()
+			.find_many(vec![self.scope.media_filter(self.id.clone())])
+			.exec()
+			.await?;
+		let task_count = media.len() as u64;
+
+		persist_job_start(&ctx, runner_id.clone(), task_count, &control).await?;
+
+		let mut broken_media = 0;
+		let mut verified_media = 0;
+
+		for (index, m) in media.iter().enumerate() {
+			if !control.checkpoint().await {
+				break;
+			}
+
+			ctx.emit_client_event(ClientEvent::job_progress(
+				runner_id.clone(),
+				Some(index as u64 + 1),
+				task_count,
+				Some(format!("Verifying {}", m.name)),
+			));
+
+			match verify_media(&ctx, m).await {
+				Ok(Some(reason)) => {
+					log::warn!("Media {} failed verification: {}", m.path, reason);
+					broken_media += 1;
+				},
+				Ok(None) => {},
+				Err(e) => log::error!("Failed to verify {}: {:?}", m.path, e),
+			}
+
+			verified_media += 1;
+		}
+
+		let duration = start.elapsed();
+
+		log::info!(
+			"Finished verifying media in {}.{:03} seconds. {} of {} media are broken.",
+			duration.as_secs(),
+			duration.subsec_millis(),
+			broken_media,
+			verified_media
+		);
+
+		persist_job_end(
+			&ctx,
+			runner_id,
+			verified_media,
+			duration.as_secs(),
+			&control,
+		)
+		.await?;
+
+		Ok(())
+	}
+}
@@ -0,0 +1,272 @@
+use std::{collections::HashMap, path::Path};
+
+use super::{
+	control::JobControl, persist_job_end, persist_job_start, serialize_params, Job,
+	JobScope,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+	config::context::Ctx,
+	db::utils::PrismaClientTrait,
+	event::ClientEvent,
+	fs::{media_file, rar, scanner::utils, watcher, zip},
+	prisma::{media, media_page},
+	types::errors::ApiError,
+};
+
+/// The extensions of the media which can be converted to zip.
+pub const CONVERTIBLE_EXTENSIONS: [&str; 2] = ["cbr", "rar"];
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MediaConversionJob {
+	/// The id of the library, series or media, depending on the scope.
+	pub id: String,
+	pub scope: JobScope,
+	/// The path of the library, series or media in scope, so that the job never runs
+	/// alongside a scan of it.
+	pub path: String,
+}
+
+impl MediaConversionJob {
+	/// Loads the rar media in scope, along with their series. Media of any other format
+	/// are left as is.
+	async fn load_media(&self, ctx: &Ctx) -> Result<Vec<media::Data>, ApiError> {
+		let media = ctx
+			.get_db()
+			.media()
+			.find_many(vec![self.scope.media_filter(self.id.clone())])
+			.with(media::series::fetch())
+			.exec()
+			.await?;
+
+		// Note: extensions are stored as they appear on disk, e.g. `CBR`, and the database
+		// can't compare them case insensitively, so they are filtered here instead.
+		Ok(media
+			.into_iter()
+			.filter(|m| is_convertible(&m.extension))
+			.collect())
+	}
+}
+
+/// Returns true if media with the given extension can be converted to zip.
+pub fn is_convertible(extension: &str) -> bool {
+	CONVERTIBLE_EXTENSIONS
+		.iter()
+		.any(|convertible| extension.eq_ignore_ascii_case(convertible))
+}
+
+/// Converts a rar media to zip, then updates the media in place so that its read
+/// progress, tags and metadata are kept. The page index is rebuilt, since the pages now
+/// live at different offsets. The rar file is only removed once the media points at the
+/// verified zip file, and is hard deleted rather than moved to the trash if `hard_delete`
+/// is true.
+pub async fn convert_media(
+	ctx: &Ctx,
+	media: &media::Data,
+	hard_delete: bool,
+) -> Result<media::Data, ApiError> {
+	let rar_path = Path::new(&media.path);
+
+	// Note: the conversion writes within the library, which would otherwise queue a scan
+	// of the series being converted.
+	let _suppressed = watcher::suppress_changes(rar::get_conversion_paths(rar_path));
+	let zip_path = rar::convert_rar_to_zip(rar_path)?;
+
+	let updated_media = match update_converted_media(ctx, media, &zip_path).await {
+		Ok(updated_media) => updated_media,
+		Err(e) => {
+			// Note: the media still points at the rar file, so the zip file would otherwise
+			// be left untracked.
+			if let Err(err) = std::fs::remove_file(&zip_path) {
+				log::error!("Failed to remove zip file {:?}: {}", zip_path, err);
+			}
+
+			return Err(e);
+		},
+	};
+
+	rar::remove_converted_file(rar_path, hard_delete)?;
+
+	Ok(updated_media)
+}
+
+/// Points the media at its converted zip file and rebuilds its page index. Only the media
+/// update is fatal, pages are read from the file when the index is missing.
+async fn update_converted_media(
+	ctx: &Ctx,
+	media: &media::Data,
+	zip_path: &Path,
+) -> Result<media::Data, ApiError> {
+	let db = ctx.get_db();
+
+	let zip_path_str = zip_path.to_string_lossy().to_string();
+
+	let extension = zip_path
+		.extension()
+		.unwrap_or_default()
+		.to_string_lossy()
+		.to_string();
+
+	let size = zip_path.metadata()?.len().try_into()?;
+
+	let checksum = zip::digest_zip(&zip_path_str);
+
+	let page_index = media_file::index_pages(zip_path).unwrap_or_else(|e| {
+		log::warn!("Failed to index pages of {:?}: {:?}", zip_path, e);
+
+		vec![]
+	});
+
+	let updated_media = db
+		.media()
+		.update(
+			media::id::equals(media.id.clone()),
+			vec![
+				media::path::set(zip_path_str),
+				media::extension::set(extension),
+				media::size::set(size),
+				media::checksum::set(checksum),
+				// Note: the scanner compares this against the file on disk to detect
+				// changes, so it must describe the zip file rather than the rar file.
+				media::modified_at::set(utils::get_modified_at(zip_path)),
+			],
+		)
+		.exec()
+		.await?;
+
+	let deleted = db
+		.media_page()
+		.delete_many(vec![media_page::media_id::equals(media.id.clone())])
+		.exec()
+		.await;
+
+	if let Err(e) = deleted {
+		log::warn!("Failed to remove the page index of {}: {:?}", media.id, e);
+	} else if !page_index.is_empty() {
+		let page_creates = page_index
+			.into_iter()
+			.map(|page| page.into_action(ctx, media.id.clone()));
+
+		if let Err(e) = db._batch(page_creates).await {
+			log::warn!("Failed to index pages of {:?}: {:?}", zip_path, e);
+		}
+	}
+
+	Ok(updated_media)
+}
+
+#[async_trait::async_trait]
+impl Job for MediaConversionJob {
+	fn kind(&self) -> &'static str {
+		"MediaConversionJob"
+	}
+
+	fn details(&self) -> Option<Box<&str>> {
+		Some(Box::new(self.id.as_str()))
+	}
+
+	fn params(&self) -> Option<String> {
+		serialize_params(self)
+	}
+
+	fn scan_path(&self) -> Option<&str> {
+		Some(self.path.as_str())
+	}
+
+	async fn run(
+		&self,
+		runner_id: String,
+		ctx: Ctx,
+		control: JobControl,
+	) -> Result<(), ApiError> {
+		let start = std::time::Instant::now();
+
+		let media = self.load_media(&ctx).await?;
+		let task_count = media.len() as u64;
+
+		persist_job_start(&ctx, runner_id.clone(), task_count, &control).await?;
+
+		let mut completed_tasks = 0;
+		let mut hard_delete_conversions = HashMap::new();
+
+		for (index, m) in media.iter().enumerate() {
+			if !control.checkpoint().await {
+				break;
+			}
+
+			let library_id = m
+				.series()
+				.ok()
+				.flatten()
+				.and_then(|series| series.library_id.clone());
+
+			let hard_delete = match hard_delete_conversions.get(&library_id) {
+				Some(hard_delete) => *hard_delete,
+				None => {
+					// Note: the default options keep the converted file in the trash, so
+					// they are a safe fallback.
+					let hard_delete =
+						match ctx.db.library_options(library_id.clone()).await {
+							Ok(options) => options.hard_delete_conversions,
+							Err(e) => {
+								log::error!(
+									"Failed to load the options of library {:?}: {:?}",
+									library_id,
+									e
+								);
+
+								false
+							},
+						};
+
+					hard_delete_conversions.insert(library_id, hard_delete);
+					hard_delete
+				},
+			};
+
+			ctx.emit_client_event(ClientEvent::job_progress(
+				runner_id.clone(),
+				Some(index as u64 + 1),
+				task_count,
+				Some(format!("Converting {} to zip", m.name)),
+			));
+
+			match convert_media(&ctx, m, hard_delete).await {
+				Ok(_) => completed_tasks += 1,
+				Err(e) => {
+					log::error!("Failed to convert {}: {:?}", m.path, e);
+
+					ctx.handle_failure_event(ClientEvent::ConvertMediaFailed {
+						runner_id: Some(runner_id.clone()),
+						path: m.path.clone(),
+						message: e.to_string(),
+					})
+					.await;
+				},
+			}
+		}
+
+		let duration = start.elapsed();
+
+		log::info!(
+			"Finished converting media in {}.{:03} seconds. {} of {} media converted.",
+			duration.as_secs(),
+			duration.subsec_millis(),
+			completed_tasks,
+			task_count
+		);
+
+		persist_job_end(
+			&ctx,
+			runner_id,
+			completed_tasks,
+			duration.as_secs(),
+			&control,
+		)
+		.await?;
+
+		Ok(())
+	}
+}
@@ -1,26 +1,44 @@
+pub mod control;
 pub mod library_scan;
+pub mod library_verification;
+pub mod media_conversion;
 pub mod pool;
 pub mod runner;
+pub mod series_scan;
+pub mod thumbnail_generation;
 
 use std::fmt::Debug;
 
+use prisma_client_rust::chrono;
+use rocket::serde::json;
 use rocket_okapi::JsonSchema;
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use specta::Type;
 
 use crate::{
 	config::context::Ctx,
 	event::ClientEvent,
-	prisma::{self},
+	prisma::{self, media, series},
 	types::errors::ApiError,
 };
 
+use self::{
+	control::{JobControl, JobState},
+	library_scan::LibraryScanJob,
+	library_verification::LibraryVerificationJob,
+	media_conversion::MediaConversionJob,
+	series_scan::SeriesScanJob,
+	thumbnail_generation::ThumbnailGenerationJob,
+};
+
 #[derive(Clone, Serialize, Deserialize, Debug, JsonSchema, Type)]
 pub enum JobStatus {
 	#[serde(rename = "RUNNING")]
 	Running,
 	#[serde(rename = "QUEUED")]
 	Queued,
+	#[serde(rename = "PAUSED")]
+	Paused,
 	#[serde(rename = "COMPLETED")]
 	Completed,
 	#[serde(rename = "CANCELLED")]
@@ -34,6 +52,7 @@ impl std::fmt::Display for JobStatus {
 		match self {
 			JobStatus::Running => write!(f, "RUNNING"),
 			JobStatus::Queued => write!(f, "QUEUED"),
+			JobStatus::Paused => write!(f, "PAUSED"),
 			JobStatus::Completed => write!(f, "COMPLETED"),
 			JobStatus::Cancelled => write!(f, "CANCELLED"),
 			JobStatus::Failed => write!(f, "FAILED"),
@@ -46,6 +65,7 @@ impl From<&str> for JobStatus {
 		match s {
 			"RUNNING" => JobStatus::Running,
 			"QUEUED" => JobStatus::Queued,
+			"PAUSED" => JobStatus::Paused,
 			"COMPLETED" => JobStatus::Completed,
 			"CANCELLED" => JobStatus::Cancelled,
 			"FAILED" => JobStatus::Failed,
@@ -76,12 +96,14 @@ pub struct JobReport {
 	pub kind: String,
 	/// The extra details of the job, e.g. "/Users/oromei/Documents/Stump/MainLibrary"
 	pub details: Option<String>,
-	/// The status of the job (i.e. COMPLETED, FAILED, CANCELLED). Running jobs are not persisted to DB.
+	/// The status of the job (i.e. QUEUED, RUNNING, PAUSED, COMPLETED, FAILED, CANCELLED).
 	status: JobStatus,
 	/// The total number of tasks
 	task_count: Option<i32>,
 	/// The total number of tasks completed (i.e. without error/failure)
 	completed_task_count: Option<i32>,
+	/// The number of media found moved (or renamed) by a scan
+	moved_media_count: Option<i32>,
 	/// The time (in seconds) to complete the job
 	seconds_elapsed: Option<u64>,
 	/// The datetime stamp of when the job completed
@@ -97,6 +119,7 @@ impl From<prisma::job::Data> for JobReport {
 			status: JobStatus::from(data.status.as_str()),
 			task_count: Some(data.task_count),
 			completed_task_count: Some(data.completed_task_count),
+			moved_media_count: data.moved_media_count,
 			seconds_elapsed: Some(data.seconds_elapsed as u64),
 			completed_at: Some(data.completed_at.to_string()),
 		}
@@ -113,20 +136,111 @@ impl From<&Box<dyn Job>> for JobReport {
 
 			task_count: None,
 			completed_task_count: None,
+			moved_media_count: None,
 			seconds_elapsed: None,
 			completed_at: None,
 		}
 	}
 }
 
+/// The priority of a job within the job queue. Jobs of a higher priority run before
+/// any queued job of a lower priority, e.g. so that a series scan requested by a user
+/// doesn't wait on a full library scan.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum JobPriority {
+	Low,
+	Normal,
+	High,
+}
+
+impl Default for JobPriority {
+	fn default() -> Self {
+		Self::Normal
+	}
+}
+
+/// The entity a job operates on, for jobs which may run against a single media, every
+/// media in a series, or every media in a library.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum JobScope {
+	Library,
+	Series,
+	Media,
+}
+
+impl JobScope {
+	/// Returns the filter matching the media in scope, where `id` is the id of the
+	/// library, series or media.
+	pub fn media_filter(&self, id: String) -> media::WhereParam {
+		match self {
+			JobScope::Library => {
+				media::series::is(vec![series::library_id::equals(Some(id))])
+			},
+			JobScope::Series => media::series_id::equals(Some(id)),
+			JobScope::Media => media::id::equals(id),
+		}
+	}
+}
+
 #[async_trait::async_trait]
 pub trait Job: Send + Sync {
 	fn kind(&self) -> &'static str;
 	fn details(&self) -> Option<Box<&str>>;
+	/// The parameters of the job, serialized as JSON so that the job can be restored
+	/// after a restart (see `restore_job`). Jobs which can't be restored return `None`.
+	fn params(&self) -> Option<String>;
+
+	fn priority(&self) -> JobPriority {
+		JobPriority::Normal
+	}
+
+	/// The path the job scans or otherwise touches, e.g. the library or series whose media
+	/// it converts. Jobs whose paths overlap never run at the same time.
+	fn scan_path(&self) -> Option<&str> {
+		None
+	}
 
-	async fn run(&self, runner_id: String, ctx: Ctx) -> Result<(), ApiError>;
+	/// Runs the job. Jobs are expected to call `JobControl::checkpoint` between each of
+	/// their tasks, and to stop early (persisting the tasks completed so far) once it
+	/// returns false.
+	async fn run(
+		&self,
+		runner_id: String,
+		ctx: Ctx,
+		control: JobControl,
+	) -> Result<(), ApiError>;
 }
 
+/// Serializes the parameters of a job, see `Job::params`.
+pub fn serialize_params<T: Serialize>(job: &T) -> Option<String> {
+	json::to_string(job)
+		.map_err(|e| log::error!("Failed to serialize job parameters: {}", e))
+		.ok()
+}
+
+fn deserialize_job<T: Job + DeserializeOwned + 'static>(
+	params: &str,
+) -> Result<Box<dyn Job>, String> {
+	json::from_str::<T>(params)
+		.map(|job| Box::new(job) as Box<dyn Job>)
+		.map_err(|e| e.to_string())
+}
+
+/// Restores a persisted job from its kind and serialized parameters, e.g. a job which
+/// was still queued when the server stopped.
+pub fn restore_job(kind: &str, params: &str) -> Result<Box<dyn Job>, String> {
+	match kind {
+		"LibraryScanJob" => deserialize_job::<LibraryScanJob>(params),
+		"SeriesScanJob" => deserialize_job::<SeriesScanJob>(params),
+		"LibraryVerificationJob" => deserialize_job::<LibraryVerificationJob>(params),
+		"MediaConversionJob" => deserialize_job::<MediaConversionJob>(params),
+		"ThumbnailGenerationJob" => deserialize_job::<ThumbnailGenerationJob>(params),
+		_ => Err(format!("Jobs of kind {} can't be restored", kind)),
+	}
+}
+
+/// Persists a new job as QUEUED, along with its parameters so that it can be restored
+/// if the server stops before it runs.
 pub async fn persist_new_job(
 	ctx: &Ctx,
 	id: String,
@@ -143,6 +257,9 @@ pub async fn persist_new_job(
 			job.kind().to_string(),
 			vec![
 				job::details::set(job.details().map(|d| d.clone().to_string())),
+				job::params::set(job.params()),
+				job::status::set(JobStatus::Queued.to_string()),
+				job::created_at::set(chrono::Utc::now().into()),
 				// job::task_count::set(task_count.try_into()?),
 			],
 		)
@@ -150,24 +267,27 @@ pub async fn persist_new_job(
 		.await?)
 }
 
+/// Persists the number of tasks of a job once it starts. The job may have been paused or
+/// cancelled before it got here, in which case the status persisted by the pool is kept.
 pub async fn persist_job_start(
 	ctx: &Ctx,
 	id: String,
 	task_count: u64,
+	control: &JobControl,
 ) -> Result<crate::prisma::job::Data, ApiError> {
 	use crate::prisma::job;
 
 	let db = ctx.get_db();
 
+	let mut set_params = vec![job::task_count::set(task_count.try_into()?)];
+
+	if control.state() == JobState::Running {
+		set_params.push(job::status::set(JobStatus::Running.to_string()));
+	}
+
 	let job = db
 		.job()
-		.update(
-			job::id::equals(id.clone()),
-			vec![
-				job::task_count::set(task_count.try_into()?),
-				job::status::set(JobStatus::Running.to_string()),
-			],
-		)
+		.update(job::id::equals(id.clone()), set_params)
 		.exec()
 		.await?;
 
@@ -181,11 +301,43 @@ pub async fn persist_job_start(
 	Ok(job)
 }
 
+/// Persists the tasks a job completed once it ends. Cancelled jobs are left for the runner
+/// to mark as cancelled, so that they are never reported as completed.
 pub async fn persist_job_end(
 	ctx: &Ctx,
 	id: String,
 	completed_task_count: u64,
 	elapsed_seconds: u64,
+	control: &JobControl,
+) -> Result<crate::prisma::job::Data, ApiError> {
+	use crate::prisma::job;
+
+	let db = ctx.get_db();
+
+	let mut set_params = vec![
+		job::completed_task_count::set(completed_task_count.try_into()?),
+		job::seconds_elapsed::set(elapsed_seconds.try_into()?),
+	];
+
+	if !control.is_cancelled() {
+		set_params.push(job::status::set(JobStatus::Completed.to_string()));
+	}
+
+	let job = db
+		.job()
+		.update(job::id::equals(id.clone()), set_params)
+		.exec()
+		.await?;
+
+	Ok(job)
+}
+
+/// Persists the number of media a scan found moved (or renamed), so that it is reported
+/// along with the job.
+pub async fn persist_moved_media(
+	ctx: &Ctx,
+	id: String,
+	moved_media: u64,
 ) -> Result<crate::prisma::job::Data, ApiError> {
 	use crate::prisma::job;
 
@@ -194,12 +346,32 @@ pub async fn persist_job_end(
 	let job = db
 		.job()
 		.update(
-			job::id::equals(id.clone()),
-			vec![
-				job::completed_task_count::set(completed_task_count.try_into()?),
-				job::seconds_elapsed::set(elapsed_seconds.try_into()?),
-				job::status::set(JobStatus::Completed.to_string()),
-			],
+			job::id::equals(id),
+			vec![job::moved_media_count::set(Some(moved_media.try_into()?))],
+		)
+		.exec()
+		.await?;
+
+	Ok(job)
+}
+
+/// Updates the status of a persisted job, e.g. when it is paused or cancelled. The task
+/// counts are left as they are, so cancelled jobs keep the counts persisted when they
+/// stopped (see `persist_job_end`).
+pub async fn persist_job_status(
+	ctx: &Ctx,
+	id: String,
+	status: JobStatus,
+) -> Result<crate::prisma::job::Data, ApiError> {
+	use crate::prisma::job;
+
+	let db = ctx.get_db();
+
+	let job = db
+		.job()
+		.update(
+			job::id::equals(id),
+			vec![job::status::set(status.to_string())],
 		)
 		.exec()
 		.await?;
@@ -1,26 +1,111 @@
 use std::{
 	collections::{HashMap, VecDeque},
+	path::Path,
 	sync::Arc,
 };
 
 use rocket::tokio;
 use tokio::sync::{mpsc, Mutex, RwLock};
 
-use crate::{config::context::Ctx, types::alias::ApiResult};
+use prisma_client_rust::Direction;
 
-use super::{runner::Runner, Job, JobReport};
+use crate::{
+	config::{self, context::Ctx},
+	event::ClientEvent,
+	prisma::job,
+	types::alias::ApiResult,
+};
+
+use super::{
+	control::JobControl, persist_job_status, persist_new_job, restore_job,
+	runner::Runner, Job, JobPriority, JobReport, JobStatus,
+};
+
+/// Returns the index a job of the given priority is inserted at within the queue, i.e.
+/// before the first job of a lower priority.
+fn get_queue_index(job_queue: &VecDeque<QueuedJob>, priority: JobPriority) -> usize {
+	job_queue
+		.iter()
+		.position(|queued| queued.job.priority() < priority)
+		.unwrap_or(job_queue.len())
+}
 
 // Note: this is 12 hours
 pub const DEFAULT_SCAN_INTERVAL_IN_SEC: i64 = 43200;
 
 pub enum JobPoolEvent {
 	Init(Ctx),
-	EnqueueJob(Ctx, Box<dyn Job>),
+	StartQueuedJobs(Ctx),
+}
+
+/// A job waiting to run, along with the id it was persisted with.
+pub struct QueuedJob {
+	pub id: String,
+	pub job: Box<dyn Job>,
+}
+
+/// The kind and scan path of a running job, see `Runner`.
+type RunningJob = (&'static str, Option<String>);
+
+/// Returns true if one of the paths is, or is within, the other.
+fn paths_overlap(path: &str, other: &str) -> bool {
+	let (path, other) = (Path::new(path), Path::new(other));
+
+	path.starts_with(other) || other.starts_with(path)
+}
+
+/// The limits deciding how many jobs may run at the same time.
+pub struct JobLimits {
+	/// The maximum number of jobs running at the same time, see `config::get_max_jobs`.
+	pub max_jobs: usize,
+	/// The maximum number of jobs of a kind running at the same time, keyed by the kind
+	/// of job. See `config::get_job_kind_limits`.
+	pub kind_limits: HashMap<String, usize>,
+}
+
+impl JobLimits {
+	pub fn from_env() -> Self {
+		Self {
+			max_jobs: config::get_max_jobs(),
+			kind_limits: config::get_job_kind_limits(),
+		}
+	}
+
+	/// Returns true if the job may start alongside the running jobs, i.e. neither the
+	/// total nor the limit of its kind is reached, and it doesn't touch a path which is
+	/// already being touched (or is within one, or contains one), see `Job::scan_path`.
+	fn allows(&self, job: &dyn Job, running: &[RunningJob]) -> bool {
+		if running.len() >= self.max_jobs {
+			return false;
+		}
+
+		if let Some(limit) = self.kind_limits.get(job.kind()) {
+			let running_of_kind = running
+				.iter()
+				.filter(|(kind, _)| *kind == job.kind())
+				.count();
+
+			if running_of_kind >= *limit {
+				return false;
+			}
+		}
+
+		match job.scan_path() {
+			Some(path) => !running.iter().any(|(_, scan_path)| {
+				scan_path
+					.as_deref()
+					.map(|scan_path| paths_overlap(scan_path, path))
+					.unwrap_or(false)
+			}),
+			None => true,
+		}
+	}
 }
 
 pub struct JobPool {
-	job_queue: RwLock<VecDeque<Box<dyn Job>>>,
+	job_queue: RwLock<VecDeque<QueuedJob>>,
 	job_runners: RwLock<HashMap<String, Arc<Mutex<Runner>>>>,
+	limits: JobLimits,
 	internal_sender: mpsc::UnboundedSender<JobPoolEvent>,
 }
 
@@ -33,6 +118,7 @@ impl JobPool {
 		let pool = Arc::new(Self {
 			job_queue: RwLock::new(VecDeque::new()),
 			job_runners: RwLock::new(HashMap::new()),
+			limits: JobLimits::from_env(),
 			internal_sender,
 		});
 
@@ -40,12 +126,13 @@ impl JobPool {
 		tokio::spawn(async move {
 			while let Some(e) = internal_receiver.recv().await {
 				match e {
-					// TODO: should I just take in a &Ctx for this function and handle the Init event outside this?
-					JobPoolEvent::Init(_ctx) => {
-						log::warn!("TODO: unimplemented. This event will handle readding queued jobs on the event the server was stopped before they were completed");
+					JobPoolEvent::Init(ctx) => {
+						if let Err(e) = pool_cpy.clone().restore_jobs(&ctx).await {
+							log::error!("Failed to restore persisted jobs: {:?}", e);
+						}
 					},
-					JobPoolEvent::EnqueueJob(ctx, job) => {
-						pool_cpy.clone().enqueue_job(&ctx, job).await
+					JobPoolEvent::StartQueuedJobs(ctx) => {
+						pool_cpy.clone().try_start_jobs(&ctx).await
 					},
 				}
 			}
@@ -91,53 +178,283 @@ impl JobPool {
 		pool
 	}
 
-	/// Adds a new job to the job queue. If the queue is empty, it will immediately get
-	/// run.
+	/// Restores the jobs persisted before the server stopped, by emitting a JobPoolEvent
+	/// to the JobPool's internal sender. See `restore_jobs`.
+	pub fn init(&self, ctx: &Ctx) {
+		self.internal_sender
+			.send(JobPoolEvent::Init(ctx.get_ctx()))
+			.unwrap_or_else(|e| {
+				log::error!("Failed to initialize job pool: {}", e.to_string())
+			})
+	}
+
+	/// Restores the jobs which were still queued when the server stopped, in the order
+	/// they were queued (within their priority). Jobs which were running (or paused) can't pick up where they
+	/// stopped, so they are marked as FAILED instead.
+	async fn restore_jobs(self: Arc<Self>, ctx: &Ctx) -> ApiResult<()> {
+		let db = ctx.get_db();
+
+		let interrupted_jobs = db
+			.job()
+			.find_many(vec![job::status::in_vec(vec![
+				JobStatus::Running.to_string(),
+				JobStatus::Paused.to_string(),
+			])])
+			.exec()
+			.await?;
+
+		for interrupted_job in interrupted_jobs {
+			log::warn!("Job {} was interrupted by a restart", interrupted_job.id);
+
+			persist_job_status(ctx, interrupted_job.id.clone(), JobStatus::Failed)
+				.await?;
+
+			ctx.handle_failure_event(ClientEvent::JobFailed {
+				runner_id: interrupted_job.id,
+				message: "The job was interrupted by a restart of the server".to_string(),
+			})
+			.await;
+		}
+
+		let queued_jobs = db
+			.job()
+			.find_many(vec![job::status::equals(JobStatus::Queued.to_string())])
+			.order_by(job::created_at::order(Direction::Asc))
+			.exec()
+			.await?;
+
+		for queued_job in queued_jobs {
+			let result = match queued_job.params.as_deref() {
+				Some(params) => restore_job(&queued_job.kind, params),
+				None => Err("The job has no parameters".to_string()),
+			};
+
+			match result {
+				Ok(job) => {
+					log::info!("Restoring queued job {}", queued_job.id);
+
+					self.insert_job(QueuedJob {
+						id: queued_job.id,
+						job,
+					})
+					.await;
+				},
+				Err(e) => {
+					log::error!("Failed to restore job {}: {}", queued_job.id, e);
+
+					persist_job_status(ctx, queued_job.id, JobStatus::Failed).await?;
+				},
+			}
+		}
+
+		self.try_start_jobs(ctx).await;
+
+		Ok(())
+	}
+
+	/// Returns true if an equivalent job (of the same kind and scan path) is already
+	/// queued with the same or a higher priority, in which case queuing the job again
+	/// would only repeat the same work.
+	async fn is_queued(&self, job: &dyn Job) -> bool {
+		job.scan_path().is_some()
+			&& self.job_queue.read().await.iter().any(|queued| {
+				queued.job.kind() == job.kind()
+					&& queued.job.scan_path() == job.scan_path()
+					&& queued.job.priority() >= job.priority()
+			})
+	}
+
+	/// Persists a new job and adds it to the job queue. It will immediately get run if
+	/// the limits of the pool allow it, see `try_start_jobs`.
 	pub async fn enqueue_job(self: Arc<Self>, ctx: &Ctx, job: Box<dyn Job>) {
+		if self.is_queued(job.as_ref()).await {
+			log::info!(
+				"Skipping {} of {:?}, an equivalent job is already queued",
+				job.kind(),
+				job.scan_path()
+			);
+
+			return;
+		}
+
+		let id = Runner::create_id();
+
+		// FIXME: error handling
+		if let Err(e) = persist_new_job(ctx, id.clone(), &job).await {
+			log::error!("Failed to persist job {}: {:?}", id, e);
+		}
+
+		self.insert_job(QueuedJob { id, job }).await;
+		self.try_start_jobs(ctx).await;
+	}
+
+	/// Adds an already persisted job to the job queue, after every queued job of the same
+	/// or a higher priority.
+	async fn insert_job(&self, queued_job: QueuedJob) {
+		let mut job_queue = self.job_queue.write().await;
+
+		let index = get_queue_index(&job_queue, queued_job.job.priority());
+
+		job_queue.insert(index, queued_job);
+	}
+
+	/// Starts every queued job which the limits of the pool allow to run, in the order of
+	/// the queue. Jobs which can't start yet (e.g. a scan of a library which is already
+	/// being scanned) stay queued, without holding back the jobs behind them.
+	async fn try_start_jobs(self: Arc<Self>, ctx: &Ctx) {
 		let mut job_runners = self.job_runners.write().await;
+		let mut job_queue = self.job_queue.write().await;
+
+		let mut running = Vec::with_capacity(job_runners.len());
+
+		for runner in job_runners.values() {
+			let runner = runner.lock().await;
 
-		if job_runners.is_empty() {
-			let runner = Runner::new(ctx, job).await;
+			running.push((runner.kind, runner.scan_path.clone()));
+		}
+
+		let mut index = 0;
+
+		while index < job_queue.len() && running.len() < self.limits.max_jobs {
+			if !self.limits.allows(job_queue[index].job.as_ref(), &running) {
+				index += 1;
+				continue;
+			}
+
+			let queued_job = match job_queue.remove(index) {
+				Some(queued_job) => queued_job,
+				None => break,
+			};
+
+			if let Err(e) =
+				persist_job_status(ctx, queued_job.id.clone(), JobStatus::Running).await
+			{
+				log::error!("Failed to persist job start: {:?}", e);
+			}
+
+			let runner = Runner::new(queued_job.id, queued_job.job);
 			let runner_id = runner.id.clone();
 
+			running.push((runner.kind, runner.scan_path.clone()));
+
 			let runner_arc = Arc::new(Mutex::new(runner));
 
 			Runner::spawn(Arc::clone(&self), Arc::clone(&runner_arc), ctx.get_ctx())
 				.await;
 
 			job_runners.insert(runner_id, runner_arc);
-		} else {
-			self.job_queue.write().await.push_back(job);
 		}
 	}
 
-	/// Removes a job by its runner id. It will attempt to queue the next job,
-	/// if there is one, by emiting a JobPoolEvent to the JobPool's internal sender.
+	/// Removes a job by its runner id. It will attempt to start the next queued jobs, if
+	/// there are any, by emiting a JobPoolEvent to the JobPool's internal sender.
 	pub async fn dequeue_job(self: Arc<Self>, ctx: &Ctx, runner_id: String) {
 		self.job_runners.write().await.remove(&runner_id);
 
-		let next_job = self.job_queue.write().await.pop_front();
-
-		if let Some(job) = next_job {
+		if !self.job_queue.read().await.is_empty() {
 			self.internal_sender
-				.send(JobPoolEvent::EnqueueJob(ctx.get_ctx(), job))
+				.send(JobPoolEvent::StartQueuedJobs(ctx.get_ctx()))
 				.unwrap_or_else(|e| {
 					log::error!("Failed to queue next job: {}", e.to_string())
 				})
 		}
 	}
 
-	/// Clears the job queue.
-	pub async fn clear_queue(self: Arc<Self>, _ctx: &Ctx) {
-		self.job_queue.write().await.clear();
+	/// Returns the control of a running job, given its runner id.
+	async fn get_control(&self, runner_id: &str) -> Option<JobControl> {
+		let runner = self.job_runners.read().await.get(runner_id).cloned()?;
+		let control = runner.lock().await.control.clone();
+
+		Some(control)
 	}
 
-	/// Returns a vector of JobReport for all persisted jobs, and appends the JobReports
-	/// for jobs in the job queue.
+	/// Cancels a job. Running jobs stop at their next checkpoint, while queued jobs are
+	/// removed from the queue. Returns false if there is no such job, or if it was already
+	/// cancelled.
+	pub async fn cancel_job(self: Arc<Self>, ctx: &Ctx, runner_id: &str) -> bool {
+		if let Some(control) = self.get_control(runner_id).await {
+			return control.cancel();
+		}
+
+		let mut job_queue = self.job_queue.write().await;
+
+		let position = job_queue.iter().position(|queued| queued.id == runner_id);
+
+		match position.and_then(|position| job_queue.remove(position)) {
+			Some(queued_job) => {
+				if let Err(e) =
+					persist_job_status(ctx, queued_job.id, JobStatus::Cancelled).await
+				{
+					log::error!("Failed to persist cancelled job: {:?}", e);
+				}
+
+				true
+			},
+			None => false,
+		}
+	}
+
+	/// Pauses a running job, which waits at its next checkpoint until it is resumed (or
+	/// cancelled). Returns false if there is no such job, or if it isn't running.
+	pub async fn pause_job(self: Arc<Self>, ctx: &Ctx, runner_id: &str) -> bool {
+		let paused = match self.get_control(runner_id).await {
+			Some(control) => control.pause(),
+			None => false,
+		};
+
+		if paused {
+			if let Err(e) =
+				persist_job_status(ctx, runner_id.to_string(), JobStatus::Paused).await
+			{
+				log::error!("Failed to persist paused job: {:?}", e);
+			}
+
+			ctx.emit_client_event(ClientEvent::JobPaused(runner_id.to_string()));
+		}
+
+		paused
+	}
+
+	/// Resumes a paused job. Returns false if there is no such job, or if it isn't paused.
+	pub async fn resume_job(self: Arc<Self>, ctx: &Ctx, runner_id: &str) -> bool {
+		let resumed = match self.get_control(runner_id).await {
+			Some(control) => control.resume(),
+			None => false,
+		};
+
+		if resumed {
+			if let Err(e) =
+				persist_job_status(ctx, runner_id.to_string(), JobStatus::Running).await
+			{
+				log::error!("Failed to persist resumed job: {:?}", e);
+			}
+
+			ctx.emit_client_event(ClientEvent::JobResumed(runner_id.to_string()));
+		}
+
+		resumed
+	}
+
+	/// Clears the job queue. The queued jobs are persisted as CANCELLED, so they aren't
+	/// restored after a restart.
+	pub async fn clear_queue(self: Arc<Self>, ctx: &Ctx) {
+		let queued_jobs = self.job_queue.write().await.drain(..).collect::<Vec<_>>();
+
+		for queued_job in queued_jobs {
+			if let Err(e) =
+				persist_job_status(ctx, queued_job.id, JobStatus::Cancelled).await
+			{
+				log::error!("Failed to persist cancelled job: {:?}", e);
+			}
+		}
+	}
+
+	/// Returns a vector of JobReport for all persisted jobs, including the jobs in the job
+	/// queue.
 	pub async fn report(self: Arc<Self>, ctx: &Ctx) -> ApiResult<Vec<JobReport>> {
 		let db = ctx.get_db();
 
-		let mut jobs = db
+		let jobs = db
 			.job()
 			.find_many(vec![])
 			.exec()
@@ -146,16 +463,123 @@ impl JobPool {
 			.map(JobReport::from)
 			.collect::<Vec<JobReport>>();
 
-		jobs.append(
-			&mut self
-				.job_queue
-				.write()
-				.await
-				.iter()
-				.map(JobReport::from)
-				.collect::<Vec<JobReport>>(),
-		);
-
 		Ok(jobs)
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use crate::{
+		job::{
+			library_scan::LibraryScanJob, media_conversion::MediaConversionJob,
+			series_scan::SeriesScanJob, JobScope,
+		},
+		types::models::library::LibraryScanMode,
+	};
+
+	use super::*;
+
+	fn library_scan(path: &str) -> Box<dyn Job> {
+		Box::new(LibraryScanJob {
+			path: path.to_string(),
+			scan_mode: LibraryScanMode::Batched,
+		})
+	}
+
+	fn series_scan(path: &str, priority: JobPriority) -> Box<dyn Job> {
+		Box::new(SeriesScanJob {
+			library_id: "library".to_string(),
+			path: path.to_string(),
+			scan_root: path.to_string(),
+			priority,
+		})
+	}
+
+	#[test]
+	fn queues_jobs_by_priority() {
+		let mut job_queue = VecDeque::new();
+
+		for (id, job) in [
+			("a", library_scan("/library/a")),
+			("b", series_scan("/library/b/Batman", JobPriority::Normal)),
+			("c", series_scan("/library/b/Saga", JobPriority::High)),
+			("d", library_scan("/library/c")),
+			("e", series_scan("/library/b/Saga", JobPriority::High)),
+		] {
+			let index = get_queue_index(&job_queue, job.priority());
+
+			job_queue.insert(
+				index,
+				QueuedJob {
+					id: id.to_string(),
+					job,
+				},
+			);
+		}
+
+		let ids = job_queue
+			.iter()
+			.map(|queued| queued.id.as_str())
+			.collect::<Vec<_>>();
+
+		assert_eq!(ids, vec!["c", "e", "b", "a", "d"]);
+	}
+
+	#[test]
+	fn limits_running_jobs() {
+		let limits = JobLimits {
+			max_jobs: 3,
+			kind_limits: HashMap::from([("LibraryScanJob".to_string(), 1)]),
+		};
+		let running = vec![("LibraryScanJob", Some("/library/a".to_string()))];
+
+		// Note: only one library scan may run at once, and never twice on the same path.
+		assert!(!limits.allows(library_scan("/library/b").as_ref(), &running));
+		assert!(!limits.allows(
+			series_scan("/library/a/Batman", JobPriority::High).as_ref(),
+			&running
+		));
+		assert!(limits.allows(
+			series_scan("/library/ab/Batman", JobPriority::High).as_ref(),
+			&running
+		));
+
+		let running = vec![
+			("SeriesScanJob", Some("/library/b/Saga".to_string())),
+			("ThumbnailGenerationJob", None),
+			("ThumbnailGenerationJob", None),
+		];
+
+		assert!(!limits.allows(library_scan("/library/c").as_ref(), &running));
+	}
+
+	#[test]
+	fn serialises_jobs_touching_scanned_paths() {
+		let limits = JobLimits {
+			max_jobs: 3,
+			kind_limits: HashMap::new(),
+		};
+		let running = vec![("SeriesScanJob", Some("/library/Batman".to_string()))];
+
+		let conversion = |path: &str| MediaConversionJob {
+			id: "id".to_string(),
+			scope: JobScope::Series,
+			path: path.to_string(),
+		};
+
+		assert!(!limits.allows(&conversion("/library/Batman"), &running));
+		assert!(!limits.allows(&conversion("/library"), &running));
+		assert!(limits.allows(&conversion("/library/Saga"), &running));
+
+		let series_scan = SeriesScanJob {
+			library_id: "library".to_string(),
+			path: "/library/Batman/Vol 1".to_string(),
+			scan_root: "/library".to_string(),
+			priority: JobPriority::Normal,
+		};
+		let running = vec![("MediaConversionJob", Some("/library/Saga".to_string()))];
+
+		// Note: the scan covers its scan root, rather than the directory it was queued for.
+		assert!(!limits.allows(&series_scan, &running));
+	}
+}
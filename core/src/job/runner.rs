@@ -5,7 +5,9 @@ use serde::{Deserialize, Serialize};
 
 use crate::{config::context::Ctx, event::ClientEvent};
 
-use super::{persist_new_job, pool::JobPool, Job, JobUpdate};
+use super::{
+	control::JobControl, persist_job_status, pool::JobPool, Job, JobStatus, JobUpdate,
+};
 
 #[derive(Clone, Serialize, Deserialize)]
 pub enum RunnerEvent {
@@ -16,7 +18,13 @@ pub enum RunnerEvent {
 
 pub struct Runner {
 	pub id: String,
+	/// The kind of the job, see `Job::kind`.
+	pub kind: &'static str,
+	/// The path scanned by the job, see `Job::scan_path`.
+	pub scan_path: Option<String>,
 	job: Option<Box<dyn Job>>,
+	/// The handle used to cancel, pause and resume the job while it runs.
+	pub control: JobControl,
 }
 
 impl Runner {
@@ -26,13 +34,16 @@ impl Runner {
 			.to_string()
 	}
 
-	pub async fn new(ctx: &Ctx, job: Box<dyn Job>) -> Self {
-		let id = Runner::create_id();
-
-		// FIXME: error handling
-		let _ = persist_new_job(ctx, id.clone(), &job).await;
-
-		Runner { id, job: Some(job) }
+	/// Creates a runner for a persisted job, see `persist_new_job`. The id of the runner
+	/// is the id of the job.
+	pub fn new(id: String, job: Box<dyn Job>) -> Self {
+		Runner {
+			id,
+			kind: job.kind(),
+			scan_path: job.scan_path().map(String::from),
+			job: Some(job),
+			control: JobControl::new(),
+		}
 	}
 
 	pub async fn spawn(job_pool: Arc<JobPool>, runner_arc: Arc<Mutex<Self>>, ctx: Ctx) {
@@ -43,18 +54,41 @@ impl Runner {
 			.job
 			.take()
 			.expect(&format!("Missing job in job runner {}", runner_id));
+		let control = runner.control.clone();
 
 		tokio::spawn(async move {
 			let runner_id = runner_id.clone();
 
-			if let Err(e) = job.run(runner_id.clone(), ctx.get_ctx()).await {
+			let result = job
+				.run(runner_id.clone(), ctx.get_ctx(), control.clone())
+				.await;
+
+			if let Err(e) = result {
 				log::error!("job failed {:?}", e);
 
+				if let Err(e) =
+					persist_job_status(&ctx, runner_id.clone(), JobStatus::Failed).await
+				{
+					log::error!("Failed to persist failed job: {:?}", e);
+				}
+
 				ctx.handle_failure_event(ClientEvent::JobFailed {
 					runner_id: runner_id.clone(),
 					message: e.to_string(),
 				})
 				.await;
+			} else if control.is_cancelled() {
+				// Note: the job persisted the tasks it completed before stopping, only its
+				// status is left to update.
+				if let Err(e) =
+					persist_job_status(&ctx, runner_id.clone(), JobStatus::Cancelled)
+						.await
+				{
+					log::error!("Failed to persist cancelled job: {:?}", e);
+				}
+
+				ctx.handle_log_event(ClientEvent::JobCancelled(runner_id.clone()))
+					.await;
 			} else {
 				ctx.emit_client_event(ClientEvent::JobComplete(runner_id.clone()));
 			}
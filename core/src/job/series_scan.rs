@@ -0,0 +1,126 @@
+use super::{
+	control::JobControl, persist_job_end, persist_moved_media, serialize_params, Job,
+	JobPriority,
+};
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+	config::context::Ctx,
+	fs::scanner::{library::scan_series_path, series_detection::get_scan_root},
+	prisma::library,
+	types::{errors::ApiError, models::library::LibraryOptions},
+};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SeriesScanJob {
+	pub library_id: String,
+	/// The path of the directory to scan, which may no longer exist.
+	pub path: String,
+	/// The directory the scan actually covers, e.g. the directory of the series `path`
+	/// belongs to, or the entire library. See `get_scan_root`.
+	pub scan_root: String,
+	/// The priority of the scan, which is higher for scans requested by a user than for
+	/// scans of changes found while watching the library.
+	pub priority: JobPriority,
+}
+
+impl SeriesScanJob {
+	/// Creates a scan of a directory of a library, resolving the directory the scan
+	/// covers according to the series strategy of the library.
+	pub async fn new(
+		ctx: &Ctx,
+		library_id: String,
+		path: String,
+		priority: JobPriority,
+	) -> Result<Self, ApiError> {
+		let library = ctx
+			.get_db()
+			.library()
+			.find_unique(library::id::equals(library_id.clone()))
+			.with(library::library_options::fetch())
+			.exec()
+			.await?
+			.ok_or_else(|| {
+				ApiError::NotFound(format!("Library with id {} not found", library_id))
+			})?;
+
+		let library_options: LibraryOptions = library
+			.library_options()
+			.map(|opt| opt.to_owned().into())
+			.unwrap_or_default();
+
+		let scan_root =
+			get_scan_root(Path::new(&library.path), Path::new(&path), &library_options);
+
+		Ok(Self {
+			library_id,
+			path,
+			scan_root: scan_root.to_string_lossy().to_string(),
+			priority,
+		})
+	}
+}
+
+#[async_trait::async_trait]
+impl Job for SeriesScanJob {
+	fn kind(&self) -> &'static str {
+		"SeriesScanJob"
+	}
+
+	fn details(&self) -> Option<Box<&str>> {
+		Some(Box::new(self.path.as_str()))
+	}
+
+	fn params(&self) -> Option<String> {
+		serialize_params(self)
+	}
+
+	fn priority(&self) -> JobPriority {
+		self.priority
+	}
+
+	fn scan_path(&self) -> Option<&str> {
+		Some(self.scan_root.as_str())
+	}
+
+	async fn run(
+		&self,
+		runner_id: String,
+		ctx: Ctx,
+		control: JobControl,
+	) -> Result<(), ApiError> {
+		let start = std::time::Instant::now();
+		let summary = scan_series_path(
+			ctx.get_ctx(),
+			self.library_id.clone(),
+			self.path.clone(),
+			runner_id.clone(),
+			control.clone(),
+		)
+		.await?;
+		let duration = start.elapsed();
+
+		log::info!(
+			"Finished series scan in {}.{:03} seconds. {} files processed, {} media moved.",
+			duration.as_secs(),
+			duration.subsec_millis(),
+			summary.completed_tasks,
+			summary.moved_media
+		);
+
+		persist_moved_media(&ctx, runner_id.clone(), summary.moved_media).await?;
+		persist_job_end(
+			&ctx,
+			runner_id,
+			summary.completed_tasks,
+			duration.as_secs(),
+			&control,
+		)
+		.await?;
+
+		Ok(())
+	}
+}
@@ -0,0 +1,207 @@
+use std::collections::HashMap;
+
+use super::{
+	control::JobControl, persist_job_end, persist_job_start, serialize_params, Job,
+	JobScope,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+	config::{context::Ctx, get_thumbnail_options},
+	event::ClientEvent,
+	fs::image,
+	prisma::{library, media, series},
+	types::{
+		errors::ApiError,
+		models::library::{LibraryOptions, ThumbnailOptions},
+	},
+};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ThumbnailGenerationJob {
+	/// The id of the library, series or media, depending on the scope.
+	pub id: String,
+	pub scope: JobScope,
+	/// The path of the library, series or media in scope, so that the job never runs
+	/// alongside a scan of it.
+	pub path: String,
+}
+
+impl ThumbnailGenerationJob {
+	/// Loads the series in scope which have a cover image. Series without one use the
+	/// thumbnail of their first media, so there is nothing to generate for them.
+	async fn load_series(&self, ctx: &Ctx) -> Result<Vec<series::Data>, ApiError> {
+		let where_param = match self.scope {
+			JobScope::Library => series::library_id::equals(Some(self.id.clone())),
+			JobScope::Series => series::id::equals(self.id.clone()),
+			JobScope::Media => return Ok(vec![]),
+		};
+
+		Ok(ctx
+			.get_db()
+			.series()
+			.find_many(vec![where_param])
+			.exec()
+			.await?
+			.into_iter()
+			.filter(|s| s.cover_path.is_some())
+			.collect())
+	}
+
+	/// Loads the media in scope, along with the thumbnail options of the library each
+	/// media (or one of the given series) belongs to, keyed by library id.
+	async fn load_media(
+		&self,
+		ctx: &Ctx,
+		series: &[series::Data],
+	) -> Result<(Vec<media::Data>, HashMap<String, ThumbnailOptions>), ApiError> {
+		let db = ctx.get_db();
+
+		let media = db
+			.media()
+			.find_many(vec![self.scope.media_filter(self.id.clone())])
+			.with(media::series::fetch())
+			.with(media::page_index::fetch(vec![]))
+			.exec()
+			.await?;
+
+		let library_ids = media
+			.iter()
+			.filter_map(|m| m.series().ok().flatten())
+			.chain(series)
+			.filter_map(|s| s.library_id.clone())
+			.collect::<Vec<String>>();
+
+		let thumbnail_options = db
+			.library()
+			.find_many(vec![library::id::in_vec(library_ids)])
+			.with(library::library_options::fetch())
+			.exec()
+			.await?
+			.into_iter()
+			.map(|library| {
+				let library_options: LibraryOptions = library
+					.library_options()
+					.map(|opt| opt.to_owned().into())
+					.unwrap_or_default();
+
+				(library.id, library_options.get_thumbnail_options())
+			})
+			.collect::<HashMap<String, ThumbnailOptions>>();
+
+		Ok((media, thumbnail_options))
+	}
+}
+
+#[async_trait::async_trait]
+impl Job for ThumbnailGenerationJob {
+	fn kind(&self) -> &'static str {
+		"ThumbnailGenerationJob"
+	}
+
+	fn details(&self) -> Option<Box<&str>> {
+		Some(Box::new(self.id.as_str()))
+	}
+
+	fn params(&self) -> Option<String> {
+		serialize_params(self)
+	}
+
+	fn scan_path(&self) -> Option<&str> {
+		Some(self.path.as_str())
+	}
+
+	async fn run(
+		&self,
+		runner_id: String,
+		ctx: Ctx,
+		control: JobControl,
+	) -> Result<(), ApiError> {
+		let start = std::time::Instant::now();
+
+		let series = self.load_series(&ctx).await?;
+		let (media, thumbnail_options) = self.load_media(&ctx, &series).await?;
+		let task_count = (series.len() + media.len()) as u64;
+
+		persist_job_start(&ctx, runner_id.clone(), task_count, &control).await?;
+
+		let mut completed_tasks = 0;
+
+		for (index, s) in series.iter().enumerate() {
+			if !control.checkpoint().await {
+				break;
+			}
+
+			let options = s
+				.library_id
+				.as_ref()
+				.and_then(|library_id| thumbnail_options.get(library_id).cloned())
+				.unwrap_or_else(get_thumbnail_options);
+
+			match image::generate_series_thumbnail(s, &options) {
+				Ok(_) => completed_tasks += 1,
+				Err(e) => {
+					log::error!("Failed to generate thumbnail for {}: {:?}", s.path, e)
+				},
+			}
+
+			ctx.emit_client_event(ClientEvent::job_progress(
+				runner_id.clone(),
+				Some(index as u64 + 1),
+				task_count,
+				Some(format!("Generated thumbnail for {}", s.name)),
+			));
+		}
+
+		for (index, m) in media.iter().enumerate() {
+			if !control.checkpoint().await {
+				break;
+			}
+
+			// Note: media without a library (which shouldn't happen) use the server defaults.
+			let options = m
+				.series()
+				.ok()
+				.flatten()
+				.and_then(|s| s.library_id.as_ref())
+				.and_then(|library_id| thumbnail_options.get(library_id).cloned())
+				.unwrap_or_else(get_thumbnail_options);
+
+			match image::generate_media_thumbnail(m, &options) {
+				Ok(_) => completed_tasks += 1,
+				Err(e) => {
+					log::error!("Failed to generate thumbnail for {}: {:?}", m.path, e)
+				},
+			}
+
+			ctx.emit_client_event(ClientEvent::job_progress(
+				runner_id.clone(),
+				Some((series.len() + index) as u64 + 1),
+				task_count,
+				Some(format!("Generated thumbnail for {}", m.name)),
+			));
+		}
+
+		let duration = start.elapsed();
+
+		log::info!(
+			"Finished generating thumbnails in {}.{:03} seconds. {} of {} thumbnails generated.",
+			duration.as_secs(),
+			duration.subsec_millis(),
+			completed_tasks,
+			task_count
+		);
+
+		persist_job_end(
+			&ctx,
+			runner_id,
+			completed_tasks,
+			duration.as_secs(),
+			&control,
+		)
+		.await?;
+
+		Ok(())
+	}
+}
@@ -12,6 +12,7 @@ use rocket_okapi::openapi;
 
 use crate::{
 	event::ClientRequest,
+	guards::auth::AdminGuard,
 	job::JobReport,
 	types::{
 		alias::{ApiResult, Ctx},
@@ -76,7 +77,65 @@ pub async fn jobs_listener(
 	}
 }
 
-// #[delete("/jobs/<thread>")]
-// pub async fn cancel_job() {
-//     unimplemented!()
-// }
+/// Submits a request to control a running job (see `ClientRequest::CancelJob`), and
+/// waits for the outcome. Jobs which aren't running, or can't be controlled as requested
+/// (e.g. resuming a job which isn't paused), result in a 404 with the given message.
+async fn control_job(
+	ctx: &Ctx,
+	request: impl FnOnce(oneshot::Sender<bool>) -> ClientRequest,
+	not_found_message: String,
+) -> ApiResult<()> {
+	let (sender, recv) = oneshot::channel();
+
+	ctx.internal_task(request(sender)).map_err(|e| {
+		ApiError::InternalServerError(format!("Failed to submit internal task: {}", e))
+	})?;
+
+	let applied = recv.await.map_err(|e| {
+		ApiError::InternalServerError(format!("Failed to control job: {}", e))
+	})?;
+
+	if !applied {
+		return Err(ApiError::NotFound(not_found_message));
+	}
+
+	Ok(())
+}
+
+/// Cancel a running (or paused) job. The job stops once it finishes its current task,
+/// and is persisted as CANCELLED along with the tasks it completed.
+#[openapi(tag = "Job")]
+#[delete("/jobs/<id>")]
+pub async fn cancel_job(id: String, ctx: &Ctx, _auth: AdminGuard) -> ApiResult<()> {
+	control_job(
+		ctx,
+		|sender| ClientRequest::CancelJob(id.clone(), sender),
+		format!("No running job with id {} to cancel", id),
+	)
+	.await
+}
+
+/// Pause a running job. The job waits once it finishes its current task, until it is
+/// resumed or cancelled.
+#[openapi(tag = "Job")]
+#[post("/jobs/<id>/pause")]
+pub async fn pause_job(id: String, ctx: &Ctx, _auth: AdminGuard) -> ApiResult<()> {
+	control_job(
+		ctx,
+		|sender| ClientRequest::PauseJob(id.clone(), sender),
+		format!("No running job with id {} to pause", id),
+	)
+	.await
+}
+
+/// Resume a paused job.
+#[openapi(tag = "Job")]
+#[post("/jobs/<id>/resume")]
+pub async fn resume_job(id: String, ctx: &Ctx, _auth: AdminGuard) -> ApiResult<()> {
+	control_job(
+		ctx,
+		|sender| ClientRequest::ResumeJob(id.clone(), sender),
+		format!("No paused job with id {} to resume", id),
+	)
+	.await
+}
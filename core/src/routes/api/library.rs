@@ -6,11 +6,16 @@ use rocket_okapi::openapi;
 
 use crate::{
 	db::utils::{FindManyTrait, PrismaClientTrait},
+	event::ClientRequest,
 	fs::{self, image},
 	guards::auth::{AdminGuard, Auth},
-	job::library_scan::LibraryScanJob,
+	job::{
+		library_scan::LibraryScanJob, library_verification::LibraryVerificationJob,
+		media_conversion::MediaConversionJob,
+		thumbnail_generation::ThumbnailGenerationJob, JobScope,
+	},
 	prisma::{
-		library, library_options, media,
+		library, library_options, media, media_page,
 		series::{self, OrderByParam},
 		tag,
 	},
@@ -21,7 +26,7 @@ use crate::{
 		models::{
 			library::{
 				CreateLibraryArgs, LibrariesStats, Library, LibraryScanMode,
-				UpdateLibraryArgs,
+				ThumbnailOptions, UpdateLibraryArgs,
 			},
 			series::Series,
 		},
@@ -195,7 +200,12 @@ pub async fn get_library_thumbnail(
 	let library_series = db
 		.series()
 		.find_many(vec![series::library_id::equals(Some(id.clone()))])
-		.with(series::media::fetch(vec![]).order_by(media::name::order(Direction::Asc)))
+		.with(
+			series::media::fetch(vec![])
+				.order_by(media::sort_key::order(Direction::Asc))
+				.order_by(media::name::order(Direction::Asc))
+				.with(media::page_index::fetch(vec![media_page::page::equals(1)])),
+		)
 		.exec()
 		.await?;
 
@@ -205,7 +215,11 @@ pub async fn get_library_thumbnail(
 
 	let media = series.media()?.first().unwrap();
 
-	Ok(fs::media_file::get_page(media.path.as_str(), 1)?)
+	Ok(fs::media_file::get_page_with_index(
+		media.path.as_str(),
+		1,
+		fs::image::find_indexed_page(media, 1).as_ref(),
+	)?)
 }
 
 /// Queue a ScannerJob to scan the library by id. The job, when started, is
@@ -250,6 +264,108 @@ pub async fn scan_library(
 	Ok(())
 }
 
+/// Queue a ThumbnailGenerationJob to regenerate the thumbnails of every media in the
+/// library, using the thumbnail options of the library. Existing thumbnails are replaced.
+#[openapi(tag = "Library")]
+#[post("/libraries/<id>/thumbnails")]
+pub async fn regenerate_library_thumbnails(
+	id: String,
+	ctx: &Ctx,
+	_auth: AdminGuard,
+) -> Result<(), ApiError> {
+	let db = ctx.get_db();
+
+	let lib = db
+		.library()
+		.find_unique(library::id::equals(id.clone()))
+		.exec()
+		.await?;
+
+	let lib = match lib {
+		Some(lib) => lib,
+		None => {
+			return Err(ApiError::NotFound(format!(
+				"Library with id {} not found",
+				id
+			)))
+		},
+	};
+
+	Ok(ctx.spawn_job(Box::new(ThumbnailGenerationJob {
+		id,
+		scope: JobScope::Library,
+		path: lib.path,
+	}))?)
+}
+
+/// Queue a MediaConversionJob to convert every cbr (or rar) media in the library to cbz
+/// (or zip). Media of other formats are left as is.
+#[openapi(tag = "Library")]
+#[post("/libraries/<id>/convert")]
+pub async fn convert_library_media(
+	id: String,
+	ctx: &Ctx,
+	_auth: AdminGuard,
+) -> Result<(), ApiError> {
+	let db = ctx.get_db();
+
+	let lib = db
+		.library()
+		.find_unique(library::id::equals(id.clone()))
+		.exec()
+		.await?;
+
+	let lib = match lib {
+		Some(lib) => lib,
+		None => {
+			return Err(ApiError::NotFound(format!(
+				"Library with id {} not found",
+				id
+			)))
+		},
+	};
+
+	Ok(ctx.spawn_job(Box::new(MediaConversionJob {
+		id,
+		scope: JobScope::Library,
+		path: lib.path,
+	}))?)
+}
+
+/// Queue a LibraryVerificationJob to check the integrity of every media in the library.
+/// Broken media are flagged with an `ERROR` status, see `GET /media/errored`.
+#[openapi(tag = "Library")]
+#[post("/libraries/<id>/verify")]
+pub async fn verify_library(
+	id: String,
+	ctx: &Ctx,
+	_auth: AdminGuard,
+) -> Result<(), ApiError> {
+	let db = ctx.get_db();
+
+	let lib = db
+		.library()
+		.find_unique(library::id::equals(id.clone()))
+		.exec()
+		.await?;
+
+	let lib = match lib {
+		Some(lib) => lib,
+		None => {
+			return Err(ApiError::NotFound(format!(
+				"Library with id {} not found",
+				id
+			)))
+		},
+	};
+
+	Ok(ctx.spawn_job(Box::new(LibraryVerificationJob {
+		id,
+		scope: JobScope::Library,
+		path: lib.path,
+	}))?)
+}
+
 /// Create a new library. Will queue a ScannerJob to scan the library, and return the library
 #[openapi(tag = "Library")]
 #[post("/libraries", data = "<input>")]
@@ -275,19 +391,30 @@ pub async fn create_library(
 
 	// FIXME: until nested create, library_options.library_id will be NULL in the database... unless I run ANOTHER
 	// update. Which I am not doing lol.
+	let mut library_options_params = vec![
+		library_options::convert_rar_to_zip::set(library_options_arg.convert_rar_to_zip),
+		library_options::hard_delete_conversions::set(
+			library_options_arg.hard_delete_conversions,
+		),
+		library_options::create_webp_thumbnails::set(
+			library_options_arg.create_webp_thumbnails,
+		),
+		library_options::treat_image_folders_as_media::set(
+			library_options_arg.treat_image_folders_as_media,
+		),
+		library_options::watch_for_changes::set(library_options_arg.watch_for_changes),
+		library_options_arg.ignore_globs_param(),
+		library_options::series_strategy::set(
+			library_options_arg.series_strategy.to_string(),
+		),
+	];
+	library_options_params.extend(ThumbnailOptions::set_params(
+		library_options_arg.thumbnail_options.as_ref(),
+	));
+
 	let library_options = db
 		.library_options()
-		.create(vec![
-			library_options::convert_rar_to_zip::set(
-				library_options_arg.convert_rar_to_zip,
-			),
-			library_options::hard_delete_conversions::set(
-				library_options_arg.hard_delete_conversions,
-			),
-			library_options::create_webp_thumbnails::set(
-				library_options_arg.create_webp_thumbnails,
-			),
-		])
+		.create(library_options_params)
 		.exec()
 		.await?;
 
@@ -317,6 +444,13 @@ pub async fn create_library(
 		db._batch(tag_connects).await?;
 	}
 
+	if library_options_arg.watch_for_changes {
+		ctx.internal_task(ClientRequest::WatchLibrary {
+			id: lib.id.clone(),
+			path: lib.path.clone(),
+		})?;
+	}
+
 	let scan_mode = input.scan_mode.unwrap_or_default();
 
 	// `scan` is not a required field, however it will default to BATCHED if not provided
@@ -350,20 +484,31 @@ pub async fn update_library(
 
 	let library_options = input.library_options.to_owned();
 
+	let mut library_options_params = vec![
+		library_options::convert_rar_to_zip::set(library_options.convert_rar_to_zip),
+		library_options::hard_delete_conversions::set(
+			library_options.hard_delete_conversions,
+		),
+		library_options::create_webp_thumbnails::set(
+			library_options.create_webp_thumbnails,
+		),
+		library_options::treat_image_folders_as_media::set(
+			library_options.treat_image_folders_as_media,
+		),
+		library_options::watch_for_changes::set(library_options.watch_for_changes),
+		library_options.ignore_globs_param(),
+		library_options::series_strategy::set(
+			library_options.series_strategy.to_string(),
+		),
+	];
+	library_options_params.extend(ThumbnailOptions::set_params(
+		library_options.thumbnail_options.as_ref(),
+	));
+
 	db.library_options()
 		.update(
 			library_options::id::equals(library_options.id.unwrap_or_default()),
-			vec![
-				library_options::convert_rar_to_zip::set(
-					library_options.convert_rar_to_zip,
-				),
-				library_options::hard_delete_conversions::set(
-					library_options.hard_delete_conversions,
-				),
-				library_options::create_webp_thumbnails::set(
-					library_options.create_webp_thumbnails,
-				),
-			],
+			library_options_params,
 		)
 		.exec()
 		.await?;
@@ -415,6 +560,17 @@ pub async fn update_library(
 		.exec()
 		.await?;
 
+	// Note: the library is watched again even if it already was, since its path may have
+	// changed.
+	if library_options.watch_for_changes {
+		ctx.internal_task(ClientRequest::WatchLibrary {
+			id: updated.id.clone(),
+			path: updated.path.clone(),
+		})?;
+	} else {
+		ctx.internal_task(ClientRequest::UnwatchLibrary(updated.id.clone()))?;
+	}
+
 	let scan_mode = input.scan_mode.unwrap_or_default();
 
 	// `scan` is not a required field, however it will default to BATCHED if not provided
@@ -453,6 +609,14 @@ pub async fn delete_library(
 		.exec()
 		.await?;
 
+	ctx.internal_task(ClientRequest::UnwatchLibrary(deleted.id.clone()))?;
+
+	let series_ids = deleted
+		.series
+		.iter()
+		.map(|series| series.id.clone())
+		.collect::<Vec<_>>();
+
 	let media_ids = deleted
 		.series
 		.into_iter()
@@ -475,5 +639,17 @@ pub async fn delete_library(
 		}
 	}
 
+	// Note: series only have a thumbnail when they have a cover image, and both series and
+	// media may have an uploaded cover image.
+	for id in series_ids.iter().chain(media_ids.iter()) {
+		if let Err(err) = image::remove_cover_image(id) {
+			log::error!("Failed to remove cover image for {}: {:?}", id, err);
+		}
+	}
+
+	if let Err(err) = image::remove_thumbnails(&series_ids) {
+		log::error!("Failed to remove thumbnails for library series: {:?}", err);
+	}
+
 	Ok(Json(deleted.id))
 }
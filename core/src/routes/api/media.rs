@@ -1,23 +1,31 @@
+use std::path::Path;
+
 use prisma_client_rust::{raw, Direction};
-use rocket::{fs::NamedFile, http::ContentType, serde::json::Json};
+use rocket::{data::ToByteUnit, fs::NamedFile, serde::json::Json, Data};
 use rocket_okapi::openapi;
 
 use crate::{
-	config::get_config_dir,
 	db::utils::{FindManyTrait, PrismaClientTrait},
-	fs::{self, image},
-	guards::auth::Auth,
+	fs::{self, image, scanner::utils::retry_failed_media},
+	guards::auth::{AdminGuard, Auth},
+	job::{
+		library_verification::verify_media,
+		media_conversion::{self, MediaConversionJob},
+		thumbnail_generation::ThumbnailGenerationJob,
+		JobScope,
+	},
 	prisma::{
 		media::{self, OrderByParam},
-		read_progress, user,
+		media_page, read_progress, user,
 	},
 	types::{
 		alias::{ApiResult, Ctx},
+		enums::FileStatus,
 		errors::ApiError,
 		http::{FileResponse, ImageResponse},
-		models::{media::Media, read_progress::ReadProgress},
+		models::{media::Media, page::MediaPage, read_progress::ReadProgress},
 		pageable::{PageParams, Pageable, PagedRequestParams},
-		query::QueryOrder,
+		query::{ImageRequestParams, QueryOrder},
 	},
 };
 
@@ -96,6 +104,44 @@ pub async fn get_duplicate_media(
 
 	Ok(Json((media, page_params).into()))
 }
+
+/// Get all media which failed to be processed during a scan, or which failed
+/// verification (see `POST /libraries/<id>/verify`), along with their series. The reason
+/// each media failed is stored in its `statusReason`. This is a paginated request, and
+/// has various pagination params available.
+#[openapi(tag = "Media")]
+#[get("/media/errored?<unpaged>&<page_params..>")]
+pub async fn get_errored_media(
+	unpaged: Option<bool>,
+	page_params: Option<PagedRequestParams>,
+	ctx: &Ctx,
+	_auth: AdminGuard,
+) -> ApiResult<Json<Pageable<Vec<Media>>>> {
+	let db = ctx.get_db();
+
+	let media = db
+		.media()
+		.find_many(vec![media::status::in_vec(vec![
+			FileStatus::Error.to_string(),
+			FileStatus::Unsupported.to_string(),
+		])])
+		.with(media::series::fetch())
+		.order_by(media::path::order(Direction::Asc))
+		.exec()
+		.await?
+		.into_iter()
+		.map(|m| m.into())
+		.collect::<Vec<Media>>();
+
+	let unpaged = unpaged.unwrap_or(page_params.is_none());
+
+	if unpaged {
+		return Ok(Json(media.into()));
+	}
+
+	Ok(Json((media, page_params).into()))
+}
+
 // TODO: I will need to add epub progress in here SOMEHOW... this will be rather
 // difficult...
 // TODO: paginate?
@@ -160,6 +206,7 @@ pub async fn get_media_by_id(
 		.with(media::read_progresses::fetch(vec![
 			read_progress::user_id::equals(auth.0.id),
 		]))
+		.with(media::metadata::fetch())
 		.exec()
 		.await?;
 
@@ -205,12 +252,14 @@ pub async fn get_media_file(
 	// Ok(NamedFile::open(media.path.clone()).await?)
 }
 
+/// Queue a MediaConversionJob to convert a cbr (or rar) media to cbz (or zip). The media
+/// is updated in place, so its read progress and tags are kept.
 #[openapi(tag = "Media")]
 #[post("/media/<id>/convert")]
 pub async fn convert_media_to_cbz(
 	id: String,
 	ctx: &Ctx,
-	_auth: Auth,
+	_auth: AdminGuard,
 ) -> Result<(), ApiError> {
 	let db = ctx.get_db();
 
@@ -229,22 +278,118 @@ pub async fn convert_media_to_cbz(
 
 	let media = media.unwrap();
 
-	if media.extension != "cbr" {
+	if !media_conversion::is_convertible(&media.extension) {
 		return Err(ApiError::BadRequest(format!(
 			"Media with id {} is not a cbr file. Only cbr files can be converted to cbz",
 			id
 		)));
 	}
 
-	// TODO: write me...
-	unimplemented!()
+	Ok(ctx.spawn_job(Box::new(MediaConversionJob {
+		id,
+		scope: JobScope::Media,
+		path: media.path,
+	}))?)
+}
+
+/// Process a media which previously failed again, e.g. once the file has been replaced,
+/// and return the updated media. The media is updated in place, so its read progress and
+/// tags are kept. If it fails again, the new reason is stored in its `statusReason`.
+#[openapi(tag = "Media")]
+#[post("/media/<id>/retry")]
+pub async fn retry_media(
+	id: String,
+	ctx: &Ctx,
+	_auth: AdminGuard,
+) -> ApiResult<Json<Media>> {
+	let db = ctx.get_db();
+
+	let media = db
+		.media()
+		.find_unique(media::id::equals(id.clone()))
+		.with(media::series::fetch())
+		.exec()
+		.await?;
+
+	if media.is_none() {
+		return Err(ApiError::NotFound(format!(
+			"Media with id {} not found",
+			id
+		)));
+	}
+
+	let media = media.unwrap();
+
+	if media.status != FileStatus::Error.to_string()
+		&& media.status != FileStatus::Unsupported.to_string()
+	{
+		return Err(ApiError::BadRequest(format!(
+			"Media with id {} has not failed, only failed media can be retried",
+			id
+		)));
+	}
+
+	let library_id = media
+		.series()
+		.ok()
+		.flatten()
+		.and_then(|series| series.library_id.clone());
+	let library_options = db.library_options(library_id).await?;
+
+	let media = retry_failed_media(ctx, &media, &library_options).await?;
+
+	Ok(Json(media.into()))
 }
 
+/// Check the integrity of a media right away, rather than in a job, and return the
+/// updated media. Useful to confirm a re-downloaded media is no longer broken.
 #[openapi(tag = "Media")]
-#[get("/media/<id>/page/<page>")]
+#[post("/media/<id>/verify")]
+pub async fn verify_media_file(
+	id: String,
+	ctx: &Ctx,
+	_auth: AdminGuard,
+) -> ApiResult<Json<Media>> {
+	let db = ctx.get_db();
+
+	let media = db
+		.media()
+		.find_unique(media::id::equals(id.clone()))
+		.exec()
+		.await?;
+
+	if media.is_none() {
+		return Err(ApiError::NotFound(format!(
+			"Media with id {} not found",
+			id
+		)));
+	}
+
+	verify_media(ctx, &media.unwrap()).await?;
+
+	let media = db
+		.media()
+		.find_unique(media::id::equals(id.clone()))
+		.exec()
+		.await?;
+
+	match media {
+		Some(m) => Ok(Json(m.into())),
+		None => Err(ApiError::NotFound(format!(
+			"Media with id {} not found",
+			id
+		))),
+	}
+}
+
+/// Get a page of a media. The page may be resized and re-encoded using the image params,
+/// in which case the transformed page is cached for subsequent requests.
+#[openapi(tag = "Media")]
+#[get("/media/<id>/page/<page>?<image_params..>")]
 pub async fn get_media_page(
 	id: String,
 	page: i32,
+	image_params: Option<ImageRequestParams>,
 	ctx: &Ctx,
 	auth: Auth,
 ) -> ApiResult<ImageResponse> {
@@ -268,7 +413,32 @@ pub async fn get_media_page(
 					id, book.pages
 				)))
 			} else {
-				Ok(fs::media_file::get_page(&book.path, page)?)
+				let indexed_page = db
+					.media_page()
+					.find_unique(media_page::UniqueWhereParam::MediaIdPageEquals(
+						id.clone(),
+						page,
+					))
+					.exec()
+					.await?
+					.map(Into::into);
+
+				let path = book.path.clone();
+
+				Ok(fs::page_cache::get_transformed_page(
+					id,
+					book.path,
+					page,
+					image_params.unwrap_or_default(),
+					move || {
+						fs::media_file::get_page_with_index(
+							&path,
+							page,
+							indexed_page.as_ref(),
+						)
+					},
+				)
+				.await?)
 			}
 		},
 		None => Err(ApiError::NotFound(format!(
@@ -278,6 +448,80 @@ pub async fn get_media_page(
 	}
 }
 
+/// Get the page manifest of a media, which describes each page of the media: its
+/// dimensions and whether it is a double-page spread. Media which were scanned before
+/// their pages were indexed are indexed on the first request.
+#[openapi(tag = "Media")]
+#[get("/media/<id>/pages")]
+pub async fn get_media_pages(
+	id: String,
+	ctx: &Ctx,
+	_auth: Auth,
+) -> ApiResult<Json<Vec<MediaPage>>> {
+	let db = ctx.get_db();
+
+	let book = db
+		.media()
+		.find_unique(media::id::equals(id.clone()))
+		.exec()
+		.await?;
+
+	if book.is_none() {
+		return Err(ApiError::NotFound(format!(
+			"Media with id {} not found",
+			id
+		)));
+	}
+
+	let book = book.unwrap();
+
+	let page_index = db
+		.media_page()
+		.find_many(vec![media_page::media_id::equals(id.clone())])
+		.order_by(media_page::page::order(Direction::Asc))
+		.exec()
+		.await?;
+
+	if !page_index.is_empty() {
+		return Ok(Json(
+			page_index.into_iter().map(|page| page.into()).collect(),
+		));
+	}
+
+	// Note: indexing reads every page of the file, so it shouldn't block the async runtime.
+	let path = book.path.clone();
+	let page_index = rocket::tokio::task::spawn_blocking(move || {
+		fs::media_file::index_pages(Path::new(&path))
+	})
+	.await
+	.map_err(|e| ApiError::InternalServerError(e.to_string()))??;
+
+	let page_creates = page_index
+		.clone()
+		.into_iter()
+		.map(|page| page.into_action(ctx, id.clone()));
+
+	// Note: concurrent requests for the same media may both index it, in which case only
+	// the first insert succeeds and the others violate the unique (mediaId, page)
+	// constraint. The pages are the same either way, so the conflict is only an error if
+	// nothing was indexed.
+	if let Err(err) = db._batch(page_creates).await {
+		let indexed_pages = db
+			.media_page()
+			.count(vec![media_page::media_id::equals(id.clone())])
+			.exec()
+			.await?;
+
+		if indexed_pages == 0 {
+			return Err(err.into());
+		}
+
+		log::debug!("Media {} was indexed by a concurrent request", id);
+	}
+
+	Ok(Json(page_index))
+}
+
 #[openapi(tag = "Media")]
 #[get("/media/<id>/thumbnail")]
 pub async fn get_media_thumbnail(
@@ -287,13 +531,9 @@ pub async fn get_media_thumbnail(
 ) -> ApiResult<ImageResponse> {
 	let db = ctx.get_db();
 
-	let webp_path = get_config_dir()
-		.join("thumbnails")
-		.join(format!("{}.webp", id));
-
-	if webp_path.exists() {
-		log::trace!("Found webp thumbnail for media {}", id);
-		return Ok((ContentType::WEBP, image::get_image_bytes(webp_path)?));
+	if let Some(thumbnail) = image::get_thumbnail(&id)? {
+		log::trace!("Found thumbnail for media {}", id);
+		return Ok(thumbnail);
 	}
 
 	let book = db
@@ -302,6 +542,7 @@ pub async fn get_media_thumbnail(
 		.with(media::read_progresses::fetch(vec![
 			read_progress::user_id::equals(auth.0.id),
 		]))
+		.with(media::page_index::fetch(vec![]))
 		.exec()
 		.await?;
 
@@ -314,7 +555,193 @@ pub async fn get_media_thumbnail(
 
 	let book = book.unwrap();
 
-	Ok(fs::media_file::get_page(book.path.as_str(), 1)?)
+	Ok(image::get_media_cover(&book)?)
+}
+
+/// Loads a media along with its series, so that the thumbnail options of its library can
+/// be resolved when its cover changes, and its page index, so that its cover page can be
+/// read without listing the file.
+async fn get_media_with_series(ctx: &Ctx, id: &str) -> ApiResult<media::Data> {
+	let media = ctx
+		.get_db()
+		.media()
+		.find_unique(media::id::equals(id.to_string()))
+		.with(media::series::fetch())
+		.with(media::page_index::fetch(vec![]))
+		.exec()
+		.await?;
+
+	media.ok_or_else(|| ApiError::NotFound(format!("Media with id {} not found", id)))
+}
+
+/// Regenerates the thumbnail of a media after its cover has changed.
+async fn update_media_thumbnail(ctx: &Ctx, media: &media::Data) -> ApiResult<()> {
+	let library_id = media
+		.series()
+		.ok()
+		.flatten()
+		.and_then(|series| series.library_id.clone());
+
+	let options = ctx.get_db().thumbnail_options(library_id).await?;
+
+	image::generate_media_thumbnail(media, &options)?;
+
+	Ok(())
+}
+
+/// Upload an image to use as the cover of a media, rather than one of its pages.
+#[openapi(tag = "Media")]
+#[put("/media/<id>/cover", data = "<cover>")]
+pub async fn upload_media_cover(
+	id: String,
+	cover: Data<'_>,
+	ctx: &Ctx,
+	_auth: AdminGuard,
+) -> ApiResult<Json<Media>> {
+	let media = get_media_with_series(ctx, &id).await?;
+
+	// Note: the default limit for raw bytes is far too small for most images, so the
+	// upload is read with its own limit instead.
+	let cover = cover
+		.open(image::MAX_COVER_SIZE.mebibytes())
+		.into_bytes()
+		.await?;
+
+	if !cover.is_complete() {
+		return Err(ApiError::BadRequest(format!(
+			"The uploaded cover must be at most {} MiB",
+			image::MAX_COVER_SIZE
+		)));
+	}
+
+	let cover = cover.into_inner();
+
+	if !infer::is_image(&cover) {
+		return Err(ApiError::BadRequest(
+			"The uploaded cover must be an image".to_string(),
+		));
+	}
+
+	let cover_path = image::save_cover_image(&media.id, &cover)?;
+
+	let media = ctx
+		.get_db()
+		.media()
+		.update(
+			media::id::equals(media.id),
+			vec![
+				media::cover_path::set(Some(cover_path.to_string_lossy().to_string())),
+				media::cover_page::set(None),
+			],
+		)
+		.with(media::series::fetch())
+		.exec()
+		.await?;
+
+	update_media_thumbnail(ctx, &media).await?;
+
+	Ok(Json(media.into()))
+}
+
+/// Use a page of a media as its cover, rather than the first page.
+#[openapi(tag = "Media")]
+#[put("/media/<id>/cover/<page>")]
+pub async fn set_media_cover_page(
+	id: String,
+	page: i32,
+	ctx: &Ctx,
+	_auth: AdminGuard,
+) -> ApiResult<Json<Media>> {
+	let media = get_media_with_series(ctx, &id).await?;
+
+	if page < 1 || page > media.pages {
+		return Err(ApiError::BadRequest(format!(
+			"Page {} is out of range for media with {} pages",
+			page, media.pages
+		)));
+	}
+
+	image::remove_cover_image(&media.id)?;
+
+	let media = ctx
+		.get_db()
+		.media()
+		.update(
+			media::id::equals(media.id),
+			vec![
+				media::cover_path::set(None),
+				media::cover_page::set(Some(page)),
+			],
+		)
+		.with(media::series::fetch())
+		.exec()
+		.await?;
+
+	update_media_thumbnail(ctx, &media).await?;
+
+	Ok(Json(media.into()))
+}
+
+/// Remove the custom cover of a media, so that its first page is used again.
+#[openapi(tag = "Media")]
+#[delete("/media/<id>/cover")]
+pub async fn remove_media_cover(
+	id: String,
+	ctx: &Ctx,
+	_auth: AdminGuard,
+) -> ApiResult<Json<Media>> {
+	let media = get_media_with_series(ctx, &id).await?;
+
+	image::remove_cover_image(&media.id)?;
+
+	let media = ctx
+		.get_db()
+		.media()
+		.update(
+			media::id::equals(media.id),
+			vec![media::cover_path::set(None), media::cover_page::set(None)],
+		)
+		.with(media::series::fetch())
+		.exec()
+		.await?;
+
+	update_media_thumbnail(ctx, &media).await?;
+
+	Ok(Json(media.into()))
+}
+
+/// Queue a ThumbnailGenerationJob to regenerate the thumbnail of the media. An existing
+/// thumbnail is replaced.
+#[openapi(tag = "Media")]
+#[post("/media/<id>/thumbnail")]
+pub async fn regenerate_media_thumbnail(
+	id: String,
+	ctx: &Ctx,
+	_auth: AdminGuard,
+) -> Result<(), ApiError> {
+	let db = ctx.get_db();
+
+	let media = db
+		.media()
+		.find_unique(media::id::equals(id.clone()))
+		.exec()
+		.await?;
+
+	let media = match media {
+		Some(media) => media,
+		None => {
+			return Err(ApiError::NotFound(format!(
+				"Media with id {} not found",
+				id
+			)))
+		},
+	};
+
+	Ok(ctx.spawn_job(Box::new(ThumbnailGenerationJob {
+		id,
+		scope: JobScope::Media,
+		path: media.path,
+	}))?)
 }
 
 // FIXME: this doesn't really handle certain errors correctly, e.g. media/user not found
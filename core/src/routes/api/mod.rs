@@ -37,12 +37,18 @@ pub fn api() -> Vec<Route> {
 		// user::update_user
 		job::get_jobs,
 		job::jobs_listener,
+		job::cancel_job,
+		job::pause_job,
+		job::resume_job,
 		// library api
 		library::get_libraries,
 		library::get_libraries_stats,
 		library::get_library_by_id,
 		library::get_library_series,
 		library::scan_library,
+		library::regenerate_library_thumbnails,
+		library::convert_library_media,
+		library::verify_library,
 		library::create_library,
 		library::update_library,
 		library::delete_library,
@@ -50,6 +56,12 @@ pub fn api() -> Vec<Route> {
 		series::get_series,
 		series::get_series_by_id,
 		series::get_series_thumbnail,
+		series::regenerate_series_thumbnails,
+		series::upload_series_cover,
+		series::remove_series_cover,
+		series::convert_series_media,
+		series::verify_series,
+		series::scan_series,
 		series::get_series_media,
 		series::series_next_media,
 		// media api
@@ -58,9 +70,18 @@ pub fn api() -> Vec<Route> {
 		media::get_media_by_id,
 		media::get_media_file,
 		media::get_media_page,
+		media::get_media_pages,
 		media::get_media_thumbnail,
+		media::regenerate_media_thumbnail,
+		media::upload_media_cover,
+		media::set_media_cover_page,
+		media::remove_media_cover,
+		media::convert_media_to_cbz,
+		media::verify_media_file,
+		media::retry_media,
 		media::update_media_progress,
 		media::get_duplicate_media,
+		media::get_errored_media,
 		// epub api
 		epub::get_epub,
 		epub::get_epub_chatper,
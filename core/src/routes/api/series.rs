@@ -1,14 +1,20 @@
 use prisma_client_rust::{raw, Direction, PrismaValue};
-use rocket::{http::ContentType, serde::json::Json};
+use rocket::{data::ToByteUnit, serde::json::Json, Data};
 use rocket_okapi::openapi;
 
 use crate::{
-	db::migration::CountQueryReturn,
-	fs::{self, image},
-	guards::auth::Auth,
+	db::{migration::CountQueryReturn, utils::PrismaClientTrait},
+	fs::image,
+	guards::auth::{AdminGuard, Auth},
+	job::{
+		library_verification::LibraryVerificationJob,
+		media_conversion::MediaConversionJob, series_scan::SeriesScanJob,
+		thumbnail_generation::ThumbnailGenerationJob, JobPriority, JobScope,
+	},
 	prisma::{media, read_progress, series},
 	types::{
 		alias::{ApiResult, Ctx},
+		enums::FileStatus,
 		errors::ApiError,
 		http::ImageResponse,
 		models::{media::Media, series::Series},
@@ -40,6 +46,7 @@ pub async fn get_series(
 				.with(media::read_progresses::fetch(vec![
 					read_progress::user_id::equals(auth.0.id),
 				]))
+				.order_by(media::sort_key::order(Direction::Asc))
 				.order_by(media::name::order(Direction::Asc)),
 		),
 		false => action,
@@ -62,7 +69,9 @@ pub async fn get_series(
 }
 
 /// Get a series by ID. Optional query param `load_media` that will load the media
-/// relation (i.e. the media entities will be loaded and sent with the response)
+/// relation (i.e. the media entities will be loaded and sent with the response). Only
+/// media which can be read are loaded or counted, i.e. broken or missing media are left
+/// out.
 #[openapi(tag = "Series")]
 #[get("/series/<id>?<load_media>")]
 pub async fn get_series_by_id(
@@ -79,11 +88,14 @@ pub async fn get_series_by_id(
 
 	if load_media {
 		query = query.with(
-			series::media::fetch(vec![])
-				.with(media::read_progresses::fetch(vec![
-					read_progress::user_id::equals(auth.0.id),
-				]))
-				.order_by(media::name::order(Direction::Asc)),
+			series::media::fetch(vec![media::status::equals(
+				FileStatus::Ready.to_string(),
+			)])
+			.with(media::read_progresses::fetch(vec![
+				read_progress::user_id::equals(auth.0.id),
+			]))
+			.order_by(media::sort_key::order(Direction::Asc))
+			.order_by(media::name::order(Direction::Asc)),
 		);
 	}
 
@@ -99,8 +111,9 @@ pub async fn get_series_by_id(
 	if !load_media {
 		let count_res: Vec<CountQueryReturn> = db
 			._query_raw(raw!(
-				"SELECT COUNT(*) as count FROM media WHERE seriesId={}",
-				PrismaValue::String(id.clone())
+				"SELECT COUNT(*) as count FROM media WHERE seriesId={} AND status={}",
+				PrismaValue::String(id.clone()),
+				PrismaValue::String(FileStatus::Ready.to_string())
 			))
 			.exec()
 			.await?;
@@ -124,32 +137,304 @@ pub async fn get_series_thumbnail(
 ) -> ApiResult<ImageResponse> {
 	let db = ctx.get_db();
 
+	if let Some(thumbnail) = image::get_thumbnail(&id)? {
+		log::trace!("Found cover thumbnail for series {}", &id);
+		return Ok(thumbnail);
+	}
+
+	let series = db
+		.series()
+		.find_unique(series::id::equals(id.clone()))
+		.exec()
+		.await?;
+
+	if series.is_none() {
+		return Err(ApiError::NotFound(format!(
+			"Series with id {} not found",
+			id
+		)));
+	}
+
+	if let Some(cover) = image::get_series_cover(&series.unwrap()) {
+		return Ok(cover);
+	}
+
+	// Note: the cover is taken from the first media of the reader, so broken or
+	// unsupported media are skipped.
 	let media = db
 		.media()
-		.find_first(vec![media::series_id::equals(Some(id.clone()))])
+		.find_first(vec![
+			media::series_id::equals(Some(id.clone())),
+			media::status::equals(FileStatus::Ready.to_string()),
+		])
+		.order_by(media::sort_key::order(Direction::Asc))
 		.order_by(media::name::order(Direction::Asc))
+		.with(media::page_index::fetch(vec![]))
 		.exec()
 		.await?;
 
 	if media.is_none() {
 		return Err(ApiError::NotFound(format!(
-			"Series with id {} not found",
+			"Series with id {} has no media",
 			id
 		)));
 	}
 
 	let media = media.unwrap();
 
-	if let Some(webp_path) = image::get_thumbnail_path(&media.id) {
-		log::trace!("Found webp thumbnail for series {}", &id);
-		return Ok((ContentType::WEBP, image::get_image_bytes(webp_path)?));
+	if let Some(thumbnail) = image::get_thumbnail(&media.id)? {
+		log::trace!("Found thumbnail for series {}", &id);
+		return Ok(thumbnail);
 	}
 
-	Ok(fs::media_file::get_page(media.path.as_str(), 1)?)
+	Ok(image::get_media_cover(&media)?)
 }
 
-/// Returns the media in a given series. This is a paginated respone, and
-/// accepts various paginated request params.
+/// Upload an image to use as the cover of a series, rather than the cover of its first
+/// media. Uploaded covers take precedence over cover images found while scanning.
+#[openapi(tag = "Series")]
+#[put("/series/<id>/cover", data = "<cover>")]
+pub async fn upload_series_cover(
+	id: String,
+	cover: Data<'_>,
+	ctx: &Ctx,
+	_auth: AdminGuard,
+) -> ApiResult<Json<Series>> {
+	let db = ctx.get_db();
+
+	let series = db
+		.series()
+		.find_unique(series::id::equals(id.clone()))
+		.exec()
+		.await?;
+
+	if series.is_none() {
+		return Err(ApiError::NotFound(format!(
+			"Series with id {} not found",
+			id
+		)));
+	}
+
+	// Note: the default limit for raw bytes is far too small for most images, so the
+	// upload is read with its own limit instead.
+	let cover = cover
+		.open(image::MAX_COVER_SIZE.mebibytes())
+		.into_bytes()
+		.await?;
+
+	if !cover.is_complete() {
+		return Err(ApiError::BadRequest(format!(
+			"The uploaded cover must be at most {} MiB",
+			image::MAX_COVER_SIZE
+		)));
+	}
+
+	let cover = cover.into_inner();
+
+	if !infer::is_image(&cover) {
+		return Err(ApiError::BadRequest(
+			"The uploaded cover must be an image".to_string(),
+		));
+	}
+
+	let cover_path = image::save_cover_image(&id, &cover)?;
+
+	let series = db
+		.series()
+		.update(
+			series::id::equals(id),
+			vec![series::cover_path::set(Some(
+				cover_path.to_string_lossy().to_string(),
+			))],
+		)
+		.exec()
+		.await?;
+
+	let options = db.thumbnail_options(series.library_id.clone()).await?;
+	image::generate_series_thumbnail(&series, &options)?;
+
+	Ok(Json(series.into()))
+}
+
+/// Remove the custom cover of a series, so that the cover of its first media is used
+/// again. Note that a cover image within the series directory will be picked up again
+/// by the next scan.
+#[openapi(tag = "Series")]
+#[delete("/series/<id>/cover")]
+pub async fn remove_series_cover(
+	id: String,
+	ctx: &Ctx,
+	_auth: AdminGuard,
+) -> ApiResult<Json<Series>> {
+	let db = ctx.get_db();
+
+	let series = db
+		.series()
+		.find_unique(series::id::equals(id.clone()))
+		.exec()
+		.await?;
+
+	if series.is_none() {
+		return Err(ApiError::NotFound(format!(
+			"Series with id {} not found",
+			id
+		)));
+	}
+
+	image::remove_cover_image(&id)?;
+
+	let series = db
+		.series()
+		.update(series::id::equals(id), vec![series::cover_path::set(None)])
+		.exec()
+		.await?;
+
+	image::remove_thumbnail(&series.id)?;
+
+	Ok(Json(series.into()))
+}
+
+/// Queue a ThumbnailGenerationJob to regenerate the thumbnails of every media in the
+/// series. Existing thumbnails are replaced.
+#[openapi(tag = "Series")]
+#[post("/series/<id>/thumbnails")]
+pub async fn regenerate_series_thumbnails(
+	id: String,
+	ctx: &Ctx,
+	_auth: AdminGuard,
+) -> Result<(), ApiError> {
+	let db = ctx.get_db();
+
+	let series = db
+		.series()
+		.find_unique(series::id::equals(id.clone()))
+		.exec()
+		.await?;
+
+	let series = match series {
+		Some(series) => series,
+		None => {
+			return Err(ApiError::NotFound(format!(
+				"Series with id {} not found",
+				id
+			)))
+		},
+	};
+
+	Ok(ctx.spawn_job(Box::new(ThumbnailGenerationJob {
+		id,
+		scope: JobScope::Series,
+		path: series.path,
+	}))?)
+}
+
+/// Queue a MediaConversionJob to convert every cbr (or rar) media in the series to cbz
+/// (or zip). Media of other formats are left as is.
+#[openapi(tag = "Series")]
+#[post("/series/<id>/convert")]
+pub async fn convert_series_media(
+	id: String,
+	ctx: &Ctx,
+	_auth: AdminGuard,
+) -> Result<(), ApiError> {
+	let db = ctx.get_db();
+
+	let series = db
+		.series()
+		.find_unique(series::id::equals(id.clone()))
+		.exec()
+		.await?;
+
+	let series = match series {
+		Some(series) => series,
+		None => {
+			return Err(ApiError::NotFound(format!(
+				"Series with id {} not found",
+				id
+			)))
+		},
+	};
+
+	Ok(ctx.spawn_job(Box::new(MediaConversionJob {
+		id,
+		scope: JobScope::Series,
+		path: series.path,
+	}))?)
+}
+
+/// Queue a LibraryVerificationJob to check the integrity of every media in the series.
+/// Broken media are flagged with an `ERROR` status, see `GET /media/errored`.
+#[openapi(tag = "Series")]
+#[post("/series/<id>/verify")]
+pub async fn verify_series(
+	id: String,
+	ctx: &Ctx,
+	_auth: AdminGuard,
+) -> Result<(), ApiError> {
+	let db = ctx.get_db();
+
+	let series = db
+		.series()
+		.find_unique(series::id::equals(id.clone()))
+		.exec()
+		.await?;
+
+	let series = match series {
+		Some(series) => series,
+		None => {
+			return Err(ApiError::NotFound(format!(
+				"Series with id {} not found",
+				id
+			)))
+		},
+	};
+
+	Ok(ctx.spawn_job(Box::new(LibraryVerificationJob {
+		id,
+		scope: JobScope::Series,
+		path: series.path,
+	}))?)
+}
+
+/// Queue a SeriesScanJob to rescan the directory of the series. The scan is queued ahead
+/// of any library scan, since it was requested by a user.
+#[openapi(tag = "Series")]
+#[post("/series/<id>/scan")]
+pub async fn scan_series(
+	id: String,
+	ctx: &Ctx,
+	_auth: AdminGuard,
+) -> Result<(), ApiError> {
+	let db = ctx.get_db();
+
+	let series = db
+		.series()
+		.find_unique(series::id::equals(id.clone()))
+		.exec()
+		.await?;
+
+	let series = match series {
+		Some(series) => series,
+		None => {
+			return Err(ApiError::NotFound(format!(
+				"Series with id {} not found",
+				id
+			)))
+		},
+	};
+
+	let library_id = series.library_id.ok_or_else(|| {
+		ApiError::BadRequest(format!("Series with id {} has no library", id))
+	})?;
+
+	let job = SeriesScanJob::new(ctx, library_id, series.path, JobPriority::High).await?;
+
+	Ok(ctx.spawn_job(Box::new(job))?)
+}
+
+/// Returns the media in a given series which can be read, i.e. broken or missing media
+/// are left out. This is a paginated respone, and accepts various paginated request
+/// params.
 #[openapi(tag = "Series")]
 #[get("/series/<id>/media?<unpaged>&<page_params..>")]
 pub async fn get_series_media(
@@ -163,10 +448,14 @@ pub async fn get_series_media(
 
 	let media = db
 		.media()
-		.find_many(vec![media::series_id::equals(Some(id))])
+		.find_many(vec![
+			media::series_id::equals(Some(id)),
+			media::status::equals(FileStatus::Ready.to_string()),
+		])
 		.with(media::read_progresses::fetch(vec![
 			read_progress::user_id::equals(auth.0.id),
 		]))
+		.order_by(media::sort_key::order(Direction::Asc))
 		.order_by(media::name::order(Direction::Asc))
 		.exec()
 		.await?
@@ -189,7 +478,8 @@ pub async fn get_series_media(
 /// Get the next media in a series, based on the read progress for the requesting user.
 /// Stump will return the first book in the series without progress, or return the first
 /// with partial progress. E.g. if a user has read pages 32/32 of book 3, then book 4 is
-/// next. If a user has read pages 31/32 of book 4, then book 4 is still next.
+/// next. If a user has read pages 31/32 of book 4, then book 4 is still next. Media which
+/// can't be read, i.e. broken or missing media, are skipped.
 #[openapi(tag = "Series")]
 #[get("/series/<id>/media/next")]
 pub async fn series_next_media(
@@ -203,11 +493,14 @@ pub async fn series_next_media(
 		.series()
 		.find_unique(series::id::equals(id.clone()))
 		.with(
-			series::media::fetch(vec![])
-				.with(media::read_progresses::fetch(vec![
-					read_progress::user_id::equals(auth.0.id),
-				]))
-				.order_by(media::name::order(Direction::Asc)),
+			series::media::fetch(vec![media::status::equals(
+				FileStatus::Ready.to_string(),
+			)])
+			.with(media::read_progresses::fetch(vec![
+				read_progress::user_id::equals(auth.0.id),
+			]))
+			.order_by(media::sort_key::order(Direction::Asc))
+			.order_by(media::name::order(Direction::Asc)),
 		)
 		.exec()
 		.await?;
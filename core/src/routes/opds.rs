@@ -9,11 +9,13 @@ use crate::{
 		feed::OpdsFeed,
 		link::{OpdsLink, OpdsLinkRel, OpdsLinkType},
 	},
-	prisma::{self, library, media, read_progress, series},
+	prisma::{self, library, media, media_page, read_progress, series},
 	types::{
 		alias::{ApiResult, Ctx},
+		enums::FileStatus,
 		errors::ApiError,
 		http::{ImageResponse, XmlResponse},
+		query::ImageRequestParams,
 	},
 };
 
@@ -386,9 +388,13 @@ async fn series_by_id(
 	let page = page.unwrap_or(0);
 	let (skip, take) = pagination_bounds(page, 20);
 
+	// Note: broken or missing media can't be read, so they are left out of the feed.
 	let series_media_count = db
 		.media()
-		.count(vec![media::series_id::equals(Some(id.clone()))])
+		.count(vec![
+			media::series_id::equals(Some(id.clone())),
+			media::status::equals(FileStatus::Ready.to_string()),
+		])
 		.exec()
 		.await?;
 
@@ -396,10 +402,13 @@ async fn series_by_id(
 		.series()
 		.find_unique(series::id::equals(id.clone()))
 		.with(
-			series::media::fetch(vec![])
-				.skip(skip)
-				.take(take)
-				.order_by(media::name::order(Direction::Asc)),
+			series::media::fetch(vec![media::status::equals(
+				FileStatus::Ready.to_string(),
+			)])
+			.skip(skip)
+			.take(take)
+			.order_by(media::sort_key::order(Direction::Asc))
+			.order_by(media::name::order(Direction::Asc)),
 		)
 		.exec()
 		.await?;
@@ -430,6 +439,7 @@ async fn book_thumbnail(id: String, ctx: &Ctx, _auth: Auth) -> ApiResult<ImageRe
 	let book = db
 		.media()
 		.find_unique(media::id::equals(id.clone()))
+		.with(media::page_index::fetch(vec![]))
 		.exec()
 		.await?;
 
@@ -439,17 +449,22 @@ async fn book_thumbnail(id: String, ctx: &Ctx, _auth: Auth) -> ApiResult<ImageRe
 
 	let book = book.unwrap();
 
-	Ok(fs::media_file::get_page(&book.path, 1)?)
+	if let Some(thumbnail) = fs::image::get_thumbnail(&book.id)? {
+		return Ok(thumbnail);
+	}
+
+	Ok(fs::image::get_media_cover(&book)?)
 }
 
 // TODO: generalize the function call
 // TODO: cache this? Look into this, I can send a cache-control header to the client, but not sure if I should
 // also cache on server. Check my types::rocket crate
-#[get("/books/<id>/pages/<page>?<zero_based>")]
+#[get("/books/<id>/pages/<page>?<zero_based>&<image_params..>")]
 async fn book_page(
 	id: String,
 	page: usize,
 	zero_based: Option<bool>,
+	image_params: Option<ImageRequestParams>,
 	ctx: &Ctx,
 	_auth: Auth,
 ) -> ApiResult<ImageResponse> {
@@ -475,9 +490,34 @@ async fn book_page(
 
 	let book = book.unwrap();
 
-	if book.path.ends_with(".epub") && correct_page == 1 {
-		return Ok(fs::epub::get_epub_cover(&book.path)?);
-	}
-
-	Ok(fs::media_file::get_page(&book.path, correct_page as i32)?)
+	let indexed_page = db
+		.media_page()
+		.find_unique(media_page::UniqueWhereParam::MediaIdPageEquals(
+			id.clone(),
+			correct_page as i32,
+		))
+		.exec()
+		.await?
+		.map(Into::into);
+
+	let path = book.path.clone();
+
+	Ok(fs::page_cache::get_transformed_page(
+		id,
+		book.path,
+		correct_page as i32,
+		image_params.unwrap_or_default(),
+		move || {
+			if path.ends_with(".epub") && correct_page == 1 {
+				fs::epub::get_epub_cover(&path)
+			} else {
+				fs::media_file::get_page_with_index(
+					&path,
+					correct_page as i32,
+					indexed_page.as_ref(),
+				)
+			}
+		},
+	)
+	.await?)
 }
@@ -1,5 +1,6 @@
 use std::{fmt, str::FromStr};
 
+use rocket::http::ContentType;
 use rocket_okapi::JsonSchema;
 use serde::{Deserialize, Serialize};
 use specta::Type;
@@ -75,3 +76,175 @@ impl Into<String> for UserRole {
 		}
 	}
 }
+
+#[derive(
+	Debug, Deserialize, Serialize, JsonSchema, FromFormField, Type, Clone, Copy, PartialEq,
+)]
+pub enum ImageFormat {
+	#[serde(rename = "webp")]
+	#[field(value = "webp")]
+	Webp,
+	#[serde(rename = "jpeg")]
+	#[field(value = "jpeg")]
+	#[field(value = "jpg")]
+	Jpeg,
+	#[serde(rename = "png")]
+	#[field(value = "png")]
+	Png,
+}
+
+impl ImageFormat {
+	pub fn extension(&self) -> &'static str {
+		match self {
+			ImageFormat::Webp => "webp",
+			ImageFormat::Jpeg => "jpeg",
+			ImageFormat::Png => "png",
+		}
+	}
+
+	pub fn content_type(&self) -> ContentType {
+		match self {
+			ImageFormat::Webp => ContentType::WEBP,
+			ImageFormat::Jpeg => ContentType::JPEG,
+			ImageFormat::Png => ContentType::PNG,
+		}
+	}
+
+	pub fn from_content_type(content_type: &ContentType) -> Option<Self> {
+		if content_type.is_webp() {
+			Some(ImageFormat::Webp)
+		} else if content_type.is_jpeg() {
+			Some(ImageFormat::Jpeg)
+		} else if content_type.is_png() {
+			Some(ImageFormat::Png)
+		} else {
+			None
+		}
+	}
+}
+
+impl fmt::Display for ImageFormat {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}", self.extension())
+	}
+}
+
+impl FromStr for ImageFormat {
+	type Err = ();
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.to_lowercase().as_str() {
+			"webp" => Ok(ImageFormat::Webp),
+			"jpeg" | "jpg" => Ok(ImageFormat::Jpeg),
+			"png" => Ok(ImageFormat::Png),
+			_ => Err(()),
+		}
+	}
+}
+
+/// The filter used to resize images, ordered from fastest to highest quality.
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Type, Clone, Copy, PartialEq)]
+pub enum ImageResizeFilter {
+	#[serde(rename = "NEAREST")]
+	Nearest,
+	#[serde(rename = "TRIANGLE")]
+	Triangle,
+	#[serde(rename = "CATMULL_ROM")]
+	CatmullRom,
+	#[serde(rename = "GAUSSIAN")]
+	Gaussian,
+	#[serde(rename = "LANCZOS3")]
+	Lanczos3,
+}
+
+impl fmt::Display for ImageResizeFilter {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			ImageResizeFilter::Nearest => write!(f, "NEAREST"),
+			ImageResizeFilter::Triangle => write!(f, "TRIANGLE"),
+			ImageResizeFilter::CatmullRom => write!(f, "CATMULL_ROM"),
+			ImageResizeFilter::Gaussian => write!(f, "GAUSSIAN"),
+			ImageResizeFilter::Lanczos3 => write!(f, "LANCZOS3"),
+		}
+	}
+}
+
+impl FromStr for ImageResizeFilter {
+	type Err = ();
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.to_uppercase().as_str() {
+			"NEAREST" => Ok(ImageResizeFilter::Nearest),
+			"TRIANGLE" => Ok(ImageResizeFilter::Triangle),
+			"CATMULL_ROM" => Ok(ImageResizeFilter::CatmullRom),
+			"GAUSSIAN" => Ok(ImageResizeFilter::Gaussian),
+			"LANCZOS3" => Ok(ImageResizeFilter::Lanczos3),
+			_ => Err(()),
+		}
+	}
+}
+
+impl Into<image::imageops::FilterType> for ImageResizeFilter {
+	fn into(self) -> image::imageops::FilterType {
+		match self {
+			ImageResizeFilter::Nearest => image::imageops::FilterType::Nearest,
+			ImageResizeFilter::Triangle => image::imageops::FilterType::Triangle,
+			ImageResizeFilter::CatmullRom => image::imageops::FilterType::CatmullRom,
+			ImageResizeFilter::Gaussian => image::imageops::FilterType::Gaussian,
+			ImageResizeFilter::Lanczos3 => image::imageops::FilterType::Lanczos3,
+		}
+	}
+}
+
+/// How the series of a library are detected when scanning it.
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Type, Clone, Copy, PartialEq)]
+pub enum SeriesStrategy {
+	/// Every directory directly containing media is a series.
+	#[serde(rename = "DIRECTORY")]
+	Directory,
+	/// Every top-level directory of the library is a series, along with all of the media
+	/// nested within it. Media at the root of the library are not part of any series.
+	#[serde(rename = "TOP_LEVEL")]
+	TopLevel,
+	/// Every leaf directory containing media is a series, i.e. directories whose
+	/// subdirectories contain no media. Media in other directories are not part of any
+	/// series.
+	#[serde(rename = "LEAF")]
+	Leaf,
+	/// Media are grouped by the `<Series>` element of their ComicInfo.xml file, wherever
+	/// they are in the library. Media without one are grouped by the name of their
+	/// directory.
+	#[serde(rename = "COMIC_INFO")]
+	ComicInfo,
+}
+
+impl Default for SeriesStrategy {
+	fn default() -> Self {
+		Self::Directory
+	}
+}
+
+impl fmt::Display for SeriesStrategy {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			SeriesStrategy::Directory => write!(f, "DIRECTORY"),
+			SeriesStrategy::TopLevel => write!(f, "TOP_LEVEL"),
+			SeriesStrategy::Leaf => write!(f, "LEAF"),
+			SeriesStrategy::ComicInfo => write!(f, "COMIC_INFO"),
+		}
+	}
+}
+
+impl FromStr for SeriesStrategy {
+	type Err = ();
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.to_uppercase().as_str() {
+			"DIRECTORY" => Ok(SeriesStrategy::Directory),
+			"TOP_LEVEL" => Ok(SeriesStrategy::TopLevel),
+			"LEAF" => Ok(SeriesStrategy::Leaf),
+			"COMIC_INFO" => Ok(SeriesStrategy::ComicInfo),
+			_ => Err(()),
+		}
+	}
+}
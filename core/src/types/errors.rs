@@ -29,8 +29,8 @@ pub enum ProcessFileError {
 	EpubOpenError(String),
 	#[error("Error while attempting to read .epub file: {0}")]
 	EpubReadError(String),
-	// #[error("Error while attempting to read .pdf file")]
-	// PdfReadError(#[from] pdf_rs::PdfError),
+	#[error("Error while attempting to read .pdf file: {0}")]
+	PdfReadError(#[from] lopdf::Error),
 	#[error("Could not find an image")]
 	NoImageError,
 	#[error("Failed to open rar archive: {0}")]
@@ -43,12 +43,18 @@ pub enum ProcessFileError {
 	RarReadError,
 	#[error("Error reading bytes from rar")]
 	RarByteReadError(#[from] std::str::Utf8Error),
+	#[error("A 7z error occurred: {0}")]
+	SevenZipError(#[from] sevenz_rust::Error),
 	#[error("Unsupported file type: {0}")]
 	UnsupportedFileType(String),
 	#[error("{0}")]
 	ImageIoError(#[from] image::ImageError),
 	#[error("Failed to encode image to webp: {0}")]
 	WebpEncodeError(String),
+	#[error("Failed to verify converted file: {0}")]
+	ConversionVerificationError(String),
+	#[error("Unable to verify file: {0}")]
+	VerificationUnsupported(String),
 	#[error("An unknown error occurred: {0}")]
 	Unknown(String),
 }
@@ -252,6 +258,12 @@ impl From<ProcessFileError> for ApiError {
 	}
 }
 
+impl From<ScanError> for ApiError {
+	fn from(error: ScanError) -> ApiError {
+		ApiError::InternalServerError(error.to_string())
+	}
+}
+
 impl From<anyhow::Error> for ApiError {
 	fn from(error: anyhow::Error) -> ApiError {
 		ApiError::InternalServerError(error.to_string())
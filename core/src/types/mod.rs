@@ -17,8 +17,8 @@ mod tests {
 	use super::{
 		enums::*,
 		models::{
-			epub::*, library::*, list_directory::*, log::*, media::*, read_progress::*,
-			series::*, tag::*, user::*,
+			epub::*, library::*, list_directory::*, log::*, media::*, page::*,
+			read_progress::*, series::*, tag::*, user::*,
 		},
 		pageable::*,
 		query::*,
@@ -42,6 +42,9 @@ mod tests {
 		file.write_all(format!("{}\n\n", ts_export::<Library>()?).as_bytes())?;
 		file.write_all(format!("{}\n\n", ts_export::<LibraryScanMode>()?).as_bytes())?;
 		file.write_all(format!("{}\n\n", ts_export::<LibraryOptions>()?).as_bytes())?;
+		file.write_all(format!("{}\n\n", ts_export::<ThumbnailOptions>()?).as_bytes())?;
+		file.write_all(format!("{}\n\n", ts_export::<ImageFormat>()?).as_bytes())?;
+		file.write_all(format!("{}\n\n", ts_export::<ImageResizeFilter>()?).as_bytes())?;
 		file.write_all(format!("{}\n\n", ts_export::<CreateLibraryArgs>()?).as_bytes())?;
 		file.write_all(format!("{}\n\n", ts_export::<UpdateLibraryArgs>()?).as_bytes())?;
 		file.write_all(format!("{}\n\n", ts_export::<LibrariesStats>()?).as_bytes())?;
@@ -49,6 +52,7 @@ mod tests {
 		file.write_all(format!("{}\n\n", ts_export::<Series>()?).as_bytes())?;
 		file.write_all(format!("{}\n\n", ts_export::<Media>()?).as_bytes())?;
 		file.write_all(format!("{}\n\n", ts_export::<MediaMetadata>()?).as_bytes())?;
+		file.write_all(format!("{}\n\n", ts_export::<MediaPage>()?).as_bytes())?;
 		file.write_all(format!("{}\n\n", ts_export::<ReadProgress>()?).as_bytes())?;
 		file.write_all(format!("{}\n\n", ts_export::<Tag>()?).as_bytes())?;
 		file.write_all(format!("{}\n\n", ts_export::<ViewMode>()?).as_bytes())?;
@@ -4,7 +4,12 @@ use rocket_okapi::JsonSchema;
 use serde::{Deserialize, Serialize};
 use specta::Type;
 
-use crate::prisma;
+use crate::{
+	config,
+	fs::scanner::ignore_rules::escape_ignore_glob,
+	prisma,
+	types::enums::{ImageFormat, ImageResizeFilter, SeriesStrategy},
+};
 
 use super::{series::Series, tag::Tag};
 
@@ -40,6 +45,23 @@ pub struct LibraryOptions {
 	pub convert_rar_to_zip: bool,
 	pub hard_delete_conversions: bool,
 	pub create_webp_thumbnails: bool,
+	/// Whether or not directories containing only images should be treated as a single media,
+	/// rather than each image being scanned individually.
+	pub treat_image_folders_as_media: bool,
+	/// Whether or not the library directory should be watched for changes, in which case
+	/// the affected series are scanned as changes happen rather than waiting for a scan.
+	pub watch_for_changes: bool,
+	/// Globs of the paths to ignore when scanning the library, using the syntax of
+	/// .gitignore files. These apply in addition to any `.stumpignore` files found within
+	/// the library. ex: "@eaDir/" or "*_extras"
+	#[serde(default)]
+	pub ignore_globs: Vec<String>,
+	/// How the series of the library are detected when scanning it.
+	#[serde(default)]
+	pub series_strategy: SeriesStrategy,
+	/// The options used to generate thumbnails for the media in the library. When `None`,
+	/// the server defaults are used.
+	pub thumbnail_options: Option<ThumbnailOptions>,
 	// TODO: don't make Option after pcr supports nested create
 	// https://github.com/Brendonovich/prisma-client-rust/issues/44
 	pub library_id: Option<String>,
@@ -52,12 +74,112 @@ impl Default for LibraryOptions {
 			convert_rar_to_zip: false,
 			hard_delete_conversions: false,
 			create_webp_thumbnails: false,
+			treat_image_folders_as_media: false,
+			watch_for_changes: false,
+			ignore_globs: vec![],
+			series_strategy: SeriesStrategy::default(),
+			thumbnail_options: None,
 			library_id: None,
 		}
 	}
 }
 
-#[derive(Deserialize, Debug, JsonSchema, PartialEq, Copy, Clone, Type)]
+impl LibraryOptions {
+	/// Returns the thumbnail options of the library, falling back to the server defaults.
+	pub fn get_thumbnail_options(&self) -> ThumbnailOptions {
+		self.thumbnail_options
+			.clone()
+			.unwrap_or_else(config::get_thumbnail_options)
+	}
+
+	/// Returns the param to set the ignore globs of a library's options. The globs are
+	/// stored newline separated, like the lines of a .gitignore file, with a leading `#`
+	/// escaped so that it isn't read as a comment.
+	pub fn ignore_globs_param(&self) -> prisma::library_options::SetParam {
+		let globs = self
+			.ignore_globs
+			.iter()
+			.map(|glob| glob.trim())
+			.filter(|glob| !glob.is_empty())
+			.map(escape_ignore_glob)
+			.collect::<Vec<String>>();
+
+		prisma::library_options::ignore_globs::set(if globs.is_empty() {
+			None
+		} else {
+			Some(globs.join("\n"))
+		})
+	}
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema, Type, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ThumbnailOptions {
+	/// The maximum width of a thumbnail, in pixels. The aspect ratio is always kept.
+	pub max_width: u32,
+	/// The maximum height of a thumbnail, in pixels.
+	pub max_height: u32,
+	/// The format thumbnails are encoded in. ex: "webp"
+	pub format: ImageFormat,
+	/// The quality thumbnails are encoded with, from 1 to 100. Ignored for png.
+	pub quality: u8,
+	/// The filter used to resize thumbnails.
+	pub filter: ImageResizeFilter,
+}
+
+impl Default for ThumbnailOptions {
+	fn default() -> Self {
+		Self {
+			max_width: 400,
+			max_height: 600,
+			format: ImageFormat::Webp,
+			quality: 75,
+			filter: ImageResizeFilter::Triangle,
+		}
+	}
+}
+
+impl ThumbnailOptions {
+	/// Builds the thumbnail options from the columns of a library's options. All of the
+	/// columns are set together, so `None` is returned if any of them are missing.
+	fn from_library_options(data: &prisma::library_options::Data) -> Option<Self> {
+		Some(Self {
+			max_width: data.thumbnail_max_width? as u32,
+			max_height: data.thumbnail_max_height? as u32,
+			format: data.thumbnail_format.as_deref()?.parse().ok()?,
+			quality: data.thumbnail_quality? as u8,
+			filter: data.thumbnail_filter.as_deref()?.parse().ok()?,
+		})
+	}
+
+	/// Returns the params to set the thumbnail columns of a library's options. Passing
+	/// `None` clears them, so the server defaults are used.
+	pub fn set_params(
+		options: Option<&ThumbnailOptions>,
+	) -> Vec<prisma::library_options::SetParam> {
+		use prisma::library_options;
+
+		vec![
+			library_options::thumbnail_max_width::set(
+				options.map(|options| options.max_width as i32),
+			),
+			library_options::thumbnail_max_height::set(
+				options.map(|options| options.max_height as i32),
+			),
+			library_options::thumbnail_format::set(
+				options.map(|options| options.format.to_string()),
+			),
+			library_options::thumbnail_quality::set(
+				options.map(|options| options.quality as i32),
+			),
+			library_options::thumbnail_filter::set(
+				options.map(|options| options.filter.to_string()),
+			),
+		]
+	}
+}
+
+#[derive(Serialize, Deserialize, Debug, JsonSchema, PartialEq, Copy, Clone, Type)]
 #[serde(rename_all = "camelCase")]
 pub enum LibraryScanMode {
 	#[serde(rename = "SYNC")]
@@ -137,11 +259,21 @@ pub struct LibrariesStats {
 
 impl Into<LibraryOptions> for prisma::library_options::Data {
 	fn into(self) -> LibraryOptions {
+		let thumbnail_options = ThumbnailOptions::from_library_options(&self);
+
 		LibraryOptions {
 			id: Some(self.id),
 			convert_rar_to_zip: self.convert_rar_to_zip,
 			hard_delete_conversions: self.hard_delete_conversions,
 			create_webp_thumbnails: self.create_webp_thumbnails,
+			treat_image_folders_as_media: self.treat_image_folders_as_media,
+			watch_for_changes: self.watch_for_changes,
+			ignore_globs: self
+				.ignore_globs
+				.map(|globs| globs.lines().map(|glob| glob.to_string()).collect())
+				.unwrap_or_default(),
+			series_strategy: self.series_strategy.parse().unwrap_or_default(),
+			thumbnail_options,
 			library_id: self.library_id,
 		}
 	}
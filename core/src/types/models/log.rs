@@ -63,6 +63,11 @@ impl From<ClientEvent> for TentativeLog {
 				message,
 				job_id: Some(runner_id),
 			},
+			ClientEvent::JobCancelled(runner_id) => TentativeLog {
+				level: LogLevel::Info,
+				message: "The job was cancelled".to_string(),
+				job_id: Some(runner_id),
+			},
 			ClientEvent::CreateEntityFailed {
 				runner_id,
 				path,
@@ -72,6 +77,24 @@ impl From<ClientEvent> for TentativeLog {
 				message: format!("{}: {}", path, message),
 				job_id: runner_id,
 			},
+			ClientEvent::ConvertMediaFailed {
+				runner_id,
+				path,
+				message,
+			} => TentativeLog {
+				level: LogLevel::Error,
+				message: format!("Failed to convert {}: {}", path, message),
+				job_id: runner_id,
+			},
+			ClientEvent::MovedMedia {
+				runner_id,
+				from,
+				to,
+			} => TentativeLog {
+				level: LogLevel::Info,
+				message: format!("Moved {} to {}", from, to),
+				job_id: runner_id,
+			},
 			_ => unimplemented!(),
 		}
 	}
@@ -1,12 +1,13 @@
 use std::{path::PathBuf, str::FromStr};
 
+use prisma_client_rust::chrono::{DateTime, FixedOffset};
 use rocket_okapi::JsonSchema;
 use serde::{Deserialize, Serialize};
 use specta::Type;
 
 use crate::{config::context::Ctx, prisma, types::enums::FileStatus};
 
-use super::{read_progress::ReadProgress, series::Series, tag::Tag};
+use super::{page::MediaPage, read_progress::ReadProgress, series::Series, tag::Tag};
 
 #[derive(Debug, Clone, Deserialize, Serialize, JsonSchema, Type)]
 #[serde(rename_all = "camelCase")]
@@ -28,8 +29,14 @@ pub struct Media {
 	pub checksum: Option<String>,
 	/// The path of the media. ex: "/home/user/media/comics/The Amazing Spider-Man (2018) #69.cbz"
 	pub path: String,
+	/// The last modified time of the file when it was last processed. Will be `None` for
+	/// media processed before it was tracked.
+	pub modified_at: Option<String>,
 	/// The status of the media
 	pub status: FileStatus,
+	/// The reason for the status of the media, e.g. why it failed verification. Will be
+	/// `None` when the media is ready.
+	pub status_reason: Option<String>,
 	/// The ID of the series this media belongs to.
 	pub series_id: String,
 	// The series this media belongs to. Will be `None` only if the relation is not loaded.
@@ -41,6 +48,17 @@ pub struct Media {
 	pub current_page: Option<i32>,
 	/// The user assigned tags for the media. ex: ["comic", "spiderman"]. Will be `None` only if the relation is not loaded.
 	pub tags: Option<Vec<Tag>>,
+	/// The metadata of the media, e.g. parsed from a ComicInfo.xml file. Will be `None` if
+	/// the relation is not loaded, or if the media has no metadata.
+	pub metadata: Option<MediaMetadata>,
+	/// The path of an uploaded image used as the cover of the media, rather than one of
+	/// its pages.
+	pub cover_path: Option<String>,
+	/// The page used as the cover of the media. When `None`, the first page is used.
+	pub cover_page: Option<i32>,
+	/// The key used to order the media within its series, derived from its volume and
+	/// number. Will be `None` if neither could be found.
+	pub sort_key: Option<f64>,
 	// pub status: String,
 }
 
@@ -52,8 +70,18 @@ pub struct TentativeMedia {
 	pub extension: String,
 	pub pages: i32,
 	pub checksum: Option<String>,
+	/// The last modified time of the file, used to detect changes on later scans.
+	pub modified_at: Option<DateTime<FixedOffset>>,
 	pub path: String,
 	pub series_id: String,
+	/// The key used to order the media within its series, see `get_sort_key`.
+	pub sort_key: Option<f64>,
+	/// The metadata of the media, which must be created separately once the media
+	/// itself has been created.
+	pub metadata: Option<MediaMetadata>,
+	/// The page index of the media, which must be created separately once the media
+	/// itself has been created.
+	pub page_index: Vec<MediaPage>,
 }
 
 impl TentativeMedia {
@@ -67,6 +95,8 @@ impl TentativeMedia {
 			vec![
 				prisma::media::checksum::set(self.checksum),
 				prisma::media::description::set(self.description),
+				prisma::media::modified_at::set(self.modified_at),
+				prisma::media::sort_key::set(self.sort_key),
 				prisma::media::series::connect(prisma::series::id::equals(
 					self.series_id,
 				)),
@@ -110,6 +140,11 @@ impl Into<Media> for prisma::media::Data {
 			Err(_e) => None,
 		};
 
+		let metadata = match self.metadata() {
+			Ok(metadata) => metadata.map(|m| m.to_owned().into()),
+			Err(_e) => None,
+		};
+
 		Media {
 			id: self.id,
 			name: self.name,
@@ -120,46 +155,129 @@ impl Into<Media> for prisma::media::Data {
 			updated_at: self.updated_at.to_string(),
 			checksum: self.checksum,
 			path: self.path,
+			modified_at: self.modified_at.map(|modified_at| modified_at.to_string()),
 			status: FileStatus::from_str(&self.status).unwrap_or(FileStatus::Error),
+			status_reason: self.status_reason,
 			series_id: self.series_id.unwrap(),
 			series,
 			read_progresses,
 			current_page,
 			tags,
+			metadata,
+			cover_path: self.cover_path,
+			cover_page: self.cover_page,
+			sort_key: self.sort_key,
 		}
 	}
 }
 
-// Derived from ComicInfo.xml
-#[derive(Debug, Serialize, Deserialize, PartialEq, Type)]
+// Derived from ComicInfo.xml (https://anansi-project.github.io/docs/comicinfo/schemas/v2.0),
+// which is parsed by `fs::comic_info`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, JsonSchema, Type)]
 #[serde(rename_all = "camelCase")]
 pub struct MediaMetadata {
-	#[serde(rename = "Series")]
+	pub title: Option<String>,
 	pub series: Option<String>,
-	#[serde(rename = "Number")]
-	pub number: Option<usize>,
-	#[serde(rename = "Web")]
-	pub web: Option<String>,
-	#[serde(rename = "Summary")]
+	pub number: Option<String>,
+	pub volume: Option<i32>,
+	pub count: Option<i32>,
 	pub summary: Option<String>,
-	#[serde(rename = "Publisher")]
+	pub year: Option<i32>,
+	pub month: Option<i32>,
+	pub day: Option<i32>,
+	pub writer: Option<String>,
+	pub penciller: Option<String>,
+	pub inker: Option<String>,
+	pub colorist: Option<String>,
+	pub letterer: Option<String>,
+	pub cover_artist: Option<String>,
+	pub editor: Option<String>,
 	pub publisher: Option<String>,
-	#[serde(rename = "Genre")]
 	pub genre: Option<String>,
-	#[serde(rename = "PageCount")]
+	pub web: Option<String>,
 	pub page_count: Option<usize>,
+	pub language_iso: Option<String>,
+	pub characters: Option<String>,
+	pub teams: Option<String>,
+	pub locations: Option<String>,
+	pub story_arc: Option<String>,
+	pub age_rating: Option<String>,
+	pub manga: Option<String>,
 }
 
 impl MediaMetadata {
-	pub fn default() -> Self {
-		Self {
-			series: None,
-			number: None,
-			web: None,
-			summary: None,
-			publisher: None,
-			genre: None,
-			page_count: None,
+	pub fn into_action<'a>(
+		self,
+		ctx: &'a Ctx,
+		media_id: String,
+	) -> prisma::media_metadata::Create<'a> {
+		ctx.db.media_metadata().create(
+			prisma::media::id::equals(media_id),
+			vec![
+				prisma::media_metadata::title::set(self.title),
+				prisma::media_metadata::series::set(self.series),
+				prisma::media_metadata::number::set(self.number),
+				prisma::media_metadata::volume::set(self.volume),
+				prisma::media_metadata::count::set(self.count),
+				prisma::media_metadata::summary::set(self.summary),
+				prisma::media_metadata::year::set(self.year),
+				prisma::media_metadata::month::set(self.month),
+				prisma::media_metadata::day::set(self.day),
+				prisma::media_metadata::writer::set(self.writer),
+				prisma::media_metadata::penciller::set(self.penciller),
+				prisma::media_metadata::inker::set(self.inker),
+				prisma::media_metadata::colorist::set(self.colorist),
+				prisma::media_metadata::letterer::set(self.letterer),
+				prisma::media_metadata::cover_artist::set(self.cover_artist),
+				prisma::media_metadata::editor::set(self.editor),
+				prisma::media_metadata::publisher::set(self.publisher),
+				prisma::media_metadata::genre::set(self.genre),
+				prisma::media_metadata::web::set(self.web),
+				prisma::media_metadata::page_count::set(
+					self.page_count.map(|count| count as i32),
+				),
+				prisma::media_metadata::language_iso::set(self.language_iso),
+				prisma::media_metadata::characters::set(self.characters),
+				prisma::media_metadata::teams::set(self.teams),
+				prisma::media_metadata::locations::set(self.locations),
+				prisma::media_metadata::story_arc::set(self.story_arc),
+				prisma::media_metadata::age_rating::set(self.age_rating),
+				prisma::media_metadata::manga::set(self.manga),
+			],
+		)
+	}
+}
+
+impl Into<MediaMetadata> for prisma::media_metadata::Data {
+	fn into(self) -> MediaMetadata {
+		MediaMetadata {
+			title: self.title,
+			series: self.series,
+			number: self.number,
+			volume: self.volume,
+			count: self.count,
+			summary: self.summary,
+			year: self.year,
+			month: self.month,
+			day: self.day,
+			writer: self.writer,
+			penciller: self.penciller,
+			inker: self.inker,
+			colorist: self.colorist,
+			letterer: self.letterer,
+			cover_artist: self.cover_artist,
+			editor: self.editor,
+			publisher: self.publisher,
+			genre: self.genre,
+			web: self.web,
+			page_count: self.page_count.map(|count| count as usize),
+			language_iso: self.language_iso,
+			characters: self.characters,
+			teams: self.teams,
+			locations: self.locations,
+			story_arc: self.story_arc,
+			age_rating: self.age_rating,
+			manga: self.manga,
 		}
 	}
 }
@@ -3,6 +3,7 @@ pub mod library;
 pub mod list_directory;
 pub mod log;
 pub mod media;
+pub mod page;
 pub mod read_progress;
 pub mod series;
 pub mod tag;
@@ -0,0 +1,66 @@
+use rocket_okapi::JsonSchema;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+use crate::{config::context::Ctx, prisma};
+
+/// An entry in the page index of a media, which describes where a page lives within the
+/// media file so that it can be read without listing the entire file.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaPage {
+	/// The page number, starting at 1.
+	pub page: i32,
+	/// The name of the entry within the media file. ex: "Chapter 1/001.jpg"
+	pub name: String,
+	/// The byte offset of the entry data within the media file. Only present when the entry
+	/// is stored uncompressed, and can therefore be read directly.
+	pub offset: Option<i64>,
+	/// The size of the entry in bytes.
+	pub size: i64,
+	/// The MIME type of the page. ex: "image/jpeg"
+	pub mime: String,
+	/// The width of the page, in pixels.
+	pub width: Option<i32>,
+	/// The height of the page, in pixels.
+	pub height: Option<i32>,
+	/// Whether the page is wider than it is tall, e.g. a double-page spread.
+	pub is_spread: bool,
+}
+
+impl MediaPage {
+	pub fn into_action<'a>(
+		self,
+		ctx: &'a Ctx,
+		media_id: String,
+	) -> prisma::media_page::Create<'a> {
+		ctx.db.media_page().create(
+			self.page,
+			self.name,
+			self.size,
+			self.mime,
+			prisma::media::id::equals(media_id),
+			vec![
+				prisma::media_page::offset::set(self.offset),
+				prisma::media_page::width::set(self.width),
+				prisma::media_page::height::set(self.height),
+				prisma::media_page::is_spread::set(self.is_spread),
+			],
+		)
+	}
+}
+
+impl Into<MediaPage> for prisma::media_page::Data {
+	fn into(self) -> MediaPage {
+		MediaPage {
+			page: self.page,
+			name: self.name,
+			offset: self.offset,
+			size: self.size,
+			mime: self.mime,
+			width: self.width,
+			height: self.height,
+			is_spread: self.is_spread,
+		}
+	}
+}
@@ -20,6 +20,9 @@ pub struct Series {
 	pub description: Option<String>,
 	/// The status of the series since last scan or access
 	pub status: FileStatus,
+	/// The reason for the status of the series, e.g. why it is missing. Will be `None`
+	/// when the series is ready.
+	pub status_reason: Option<String>,
 	// pub updated_at: DateTime<FixedOffset>,
 	pub updated_at: String,
 	/// The ID of the library this series belongs to.
@@ -32,6 +35,9 @@ pub struct Series {
 	pub media_count: Option<i32>,
 	/// The user assigned tags for the series. ex: ["comic", "family"]. Will be `None` only if the relation is not loaded.
 	pub tags: Option<Vec<Tag>>,
+	/// The path of an image used as the cover of the series, either found within the series
+	/// directory (ex: cover.jpg) or uploaded. When `None`, the first media is used.
+	pub cover_path: Option<String>,
 }
 
 impl Series {
@@ -90,12 +96,14 @@ impl Into<Series> for prisma::series::Data {
 			path: self.path,
 			description: self.description,
 			status: FileStatus::from_str(&self.status).unwrap_or(FileStatus::Error),
+			status_reason: self.status_reason,
 			updated_at: self.updated_at.to_string(),
 			library_id: self.library_id.unwrap(),
 			library,
 			media,
 			media_count,
 			tags,
+			cover_path: self.cover_path,
 		}
 	}
 }
@@ -4,7 +4,7 @@ use specta::Type;
 
 use crate::{
 	prisma::{media, series},
-	types::{errors::ApiError, pageable::PageParams},
+	types::{enums::ImageFormat, errors::ApiError, pageable::PageParams},
 };
 
 #[derive(Debug, Serialize, Deserialize, Clone, JsonSchema, FromFormField, Type)]
@@ -30,6 +30,33 @@ impl Into<prisma_client_rust::Direction> for Direction {
 	}
 }
 
+/// Query params used to transform an image before it is returned, e.g. to resize pages
+/// for smaller screens or re-encode them in a lighter format.
+#[derive(Debug, Clone, Default, Serialize, FromForm, JsonSchema, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageRequestParams {
+	/// The maximum width of the image, in pixels. The aspect ratio is always kept, and
+	/// images are never scaled up.
+	pub width: Option<u32>,
+	/// The maximum height of the image, in pixels.
+	pub height: Option<u32>,
+	/// The format to encode the image in. Defaults to the format of the original image.
+	pub format: Option<ImageFormat>,
+	/// The quality of the encoded image, from 1 to 100. Ignored for png.
+	pub quality: Option<u8>,
+}
+
+impl ImageRequestParams {
+	/// Returns true if no transformation was requested, i.e. the original image should be
+	/// returned as is.
+	pub fn is_empty(&self) -> bool {
+		self.width.is_none()
+			&& self.height.is_none()
+			&& self.format.is_none()
+			&& self.quality.is_none()
+	}
+}
+
 /// Model used in media API to alter sorting/ordering of queried media
 #[derive(Debug, Deserialize, JsonSchema)]
 pub struct QueryOrder {
@@ -71,6 +98,7 @@ impl TryInto<media::OrderByParam> for QueryOrder {
 			"updated_at" => media::updated_at::order(dir),
 			"path" => media::path::order(dir),
 			"series_id" => media::series_id::order(dir),
+			"sort_key" => media::sort_key::order(dir),
 			_ => {
 				return Err(ApiError::BadRequest(format!(
 					"You cannot order media by {:?}",